@@ -0,0 +1,724 @@
+pub use crate::environments::{Environment, EnvironmentId};
+use crate::{
+    environments::{Bound, Environments, Lookup},
+    expression::{DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+};
+
+/// A locally nameless term paired with the environment binding its nameless
+/// variables.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Closure {
+    term: ExpressionId,
+    environment: Environment,
+}
+
+impl Closure {
+    #[inline]
+    pub fn new(term: ExpressionId, environment: Environment) -> Closure {
+        Closure { term, environment }
+    }
+
+    #[inline]
+    pub fn term(&self) -> ExpressionId {
+        self.term
+    }
+
+    #[inline]
+    pub fn environment(&self) -> Environment {
+        self.environment
+    }
+}
+
+/// The Krivine machine, evaluating locally nameless expressions to weak head
+/// normal form in call-by-name order. Its state is a term with its
+/// environment, and a stack of the closures of the arguments it is applied
+/// to. Environments bind unevaluated closures.
+pub struct KrivineMachine<'a> {
+    environments: Environments<'a, Closure>,
+    term: ExpressionId,
+    environment: Environment,
+    stack: Vec<Closure>,
+    steps: usize,
+}
+
+impl<'a> KrivineMachine<'a> {
+    /// Loads the locally nameless `expression` into a machine with an empty
+    /// environment and stack.
+    pub fn new(expressions: &'a ExpressionArena, expression: ExpressionId) -> KrivineMachine<'a> {
+        debug_assert!(Expression::is_locally_nameless(expressions, expression));
+        KrivineMachine {
+            environments: Environments::new(expressions),
+            term: expression,
+            environment: Option::None,
+            stack: Vec::new(),
+            steps: 0,
+        }
+    }
+
+    /// The term under evaluation.
+    pub fn term(&self) -> ExpressionId {
+        self.term
+    }
+
+    /// The environment of [the term](KrivineMachine::term).
+    pub fn environment(&self) -> Environment {
+        self.environment
+    }
+
+    /// The closures of the arguments, from the last to the next one applied.
+    pub fn stack(&self) -> &[Closure] {
+        &self.stack
+    }
+
+    /// The number of transitions taken.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Constructs in `destination` the locally nameless expression of
+    /// `closure`, a closure of the state, with its environment substituted in.
+    pub fn unload_closure(
+        &self,
+        closure: Closure,
+        destination: &mut ExpressionArena,
+    ) -> ExpressionId {
+        self.environments.unload(closure, 0, destination)
+    }
+
+    /// Takes a transition. Returns `false` if none applies, in which case the
+    /// state is a weak head normal form.
+    pub fn step(&mut self) -> bool {
+        let expressions = self.environments.expressions();
+        let environment = self.environment;
+        match &expressions[self.term] {
+            Expression::Application {
+                function,
+                arguments,
+            } => {
+                let closures = arguments
+                    .iter()
+                    .rev()
+                    .map(|&argument| Closure::new(argument, environment));
+                self.stack.extend(closures);
+                self.term = *function;
+            }
+            Expression::NamelessAbstraction {
+                annotation: _,
+                body,
+            } => {
+                let Option::Some(argument) = self.stack.pop() else {
+                    return false;
+                };
+                self.environment = self.environments.bind_value(environment, argument);
+                self.term = *body;
+            }
+            Expression::NamelessVariable { index } => {
+                match self.environments.lookup_closure(environment, *index) {
+                    Option::Some(closure) => {
+                        self.term = closure.term;
+                        self.environment = closure.environment;
+                    }
+                    // The variable refers outside of the loaded expression
+                    Option::None => return false,
+                }
+            }
+            Expression::NamelessLet { definition, body } => {
+                let definition = Closure::new(*definition, environment);
+                self.environment = self.environments.bind_value(environment, definition);
+                self.term = *body;
+            }
+            Expression::NamelessLetRec {
+                definitions: _,
+                body,
+            } => {
+                self.environment = self.environments.bind_group(environment, self.term);
+                self.term = *body;
+            }
+            _ => return false,
+        }
+        self.steps += 1;
+        true
+    }
+
+    /// Takes at most `fuel` transitions. Returns whether the machine reached
+    /// a state where none applies.
+    pub fn run(&mut self, fuel: usize) -> bool {
+        for _ in 0..fuel {
+            if !self.step() {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Constructs in `destination` the locally nameless expression
+    /// represented by the state, that is the term with its environment
+    /// substituted in, applied to the arguments on the stack.
+    pub fn unload(&self, destination: &mut ExpressionArena) -> ExpressionId {
+        let closure = Closure::new(self.term, self.environment);
+        let function = self.environments.unload(closure, 0, destination);
+        if self.stack.is_empty() {
+            return function;
+        }
+        let arguments = self
+            .stack
+            .iter()
+            .rev()
+            .map(|&argument| self.environments.unload(argument, 0, destination))
+            .collect();
+        destination.application(function, arguments)
+    }
+}
+
+/// The component of the CEK machine under evaluation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Control {
+    /// Evaluates the term of `closure` in its environment.
+    Evaluate { closure: Closure },
+    /// Returns `value` to the continuation.
+    Return { value: Closure },
+}
+
+/// Frames of the continuation of the CEK machine.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Frame {
+    /// Evaluates `argument` once the function it is applied to is a value.
+    Argument { argument: Closure },
+    /// Applies the value `function` to the returned argument value.
+    Function { function: Closure },
+    /// Evaluates `body` with the returned definition value bound.
+    Let { body: Closure },
+}
+
+/// The CEK machine, evaluating locally nameless expressions to values in
+/// call-by-value order, with functions evaluated before their arguments. Its
+/// state is a control, an environment within the control, and a
+/// continuation of frames. Environments bind values, and values are the
+/// closures of abstractions, constants, free variables, dependent function
+/// types and case expressions.
+pub struct CekMachine<'a> {
+    environments: Environments<'a, Closure>,
+    control: Control,
+    continuation: Vec<Frame>,
+    steps: usize,
+}
+
+impl<'a> CekMachine<'a> {
+    /// Loads the locally nameless `expression` into a machine evaluating it in
+    /// the empty environment with the empty continuation.
+    pub fn new(expressions: &'a ExpressionArena, expression: ExpressionId) -> CekMachine<'a> {
+        debug_assert!(Expression::is_locally_nameless(expressions, expression));
+        CekMachine {
+            environments: Environments::new(expressions),
+            control: Control::Evaluate {
+                closure: Closure::new(expression, Option::None),
+            },
+            continuation: Vec::new(),
+            steps: 0,
+        }
+    }
+
+    /// The component under evaluation.
+    pub fn control(&self) -> Control {
+        self.control
+    }
+
+    /// The frames of the continuation, from the outermost to the innermost.
+    pub fn continuation(&self) -> &[Frame] {
+        &self.continuation
+    }
+
+    /// The number of transitions taken.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// The value of the loaded expression, once the machine has evaluated it.
+    pub fn value(&self) -> Option<Closure> {
+        match self.control {
+            Control::Return { value } if self.continuation.is_empty() => Option::Some(value),
+            _ => Option::None,
+        }
+    }
+
+    /// Constructs in `destination` the locally nameless expression of
+    /// `closure`, a closure of the state, with its environment substituted in.
+    pub fn unload_closure(
+        &self,
+        closure: Closure,
+        destination: &mut ExpressionArena,
+    ) -> ExpressionId {
+        self.environments.unload(closure, 0, destination)
+    }
+
+    /// Takes a transition. Returns `false` if none applies, in which case the
+    /// machine has either evaluated the loaded expression to
+    /// [a value](CekMachine::value) or is stuck applying a value that is not
+    /// an abstraction.
+    pub fn step(&mut self) -> bool {
+        let control = match self.control {
+            Control::Evaluate { closure } => self.evaluate(closure),
+            Control::Return { value } => match self.continuation.pop() {
+                Option::Some(frame) => self.resume(frame, value),
+                Option::None => Option::None,
+            },
+        };
+        match control {
+            Option::Some(control) => {
+                self.control = control;
+                self.steps += 1;
+                true
+            }
+            Option::None => false,
+        }
+    }
+
+    /// Takes at most `fuel` transitions. Returns whether the machine reached
+    /// a state where none applies.
+    pub fn run(&mut self, fuel: usize) -> bool {
+        for _ in 0..fuel {
+            if !self.step() {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn evaluate(&mut self, closure: Closure) -> Option<Control> {
+        let expressions = self.environments.expressions();
+        let environment = closure.environment;
+        let control = match &expressions[closure.term] {
+            Expression::NamelessVariable { index } => {
+                match self.environments.lookup(environment, *index) {
+                    Lookup::Bound(Bound::Value(value)) => Control::Return { value },
+                    Lookup::Bound(Bound::Recursive {
+                        group,
+                        index,
+                        environment,
+                    }) => {
+                        // Evaluates the recursive definition in its group
+                        let environment = self.environments.bind_group(environment, group);
+                        let definition = expressions[group].children()[index];
+                        Control::Evaluate {
+                            closure: Closure::new(definition, environment),
+                        }
+                    }
+                    // The variable refers outside of the loaded expression
+                    Lookup::Loose { index: _ } => Control::Return { value: closure },
+                }
+            }
+            Expression::Application {
+                function,
+                arguments,
+            } => {
+                let frames = arguments.iter().rev().map(|&argument| Frame::Argument {
+                    argument: Closure::new(argument, environment),
+                });
+                self.continuation.extend(frames);
+                Control::Evaluate {
+                    closure: Closure::new(*function, environment),
+                }
+            }
+            Expression::NamelessLet { definition, body } => {
+                self.continuation.push(Frame::Let {
+                    body: Closure::new(*body, environment),
+                });
+                Control::Evaluate {
+                    closure: Closure::new(*definition, environment),
+                }
+            }
+            Expression::NamelessLetRec {
+                definitions: _,
+                body,
+            } => {
+                let environment = self.environments.bind_group(environment, closure.term);
+                Control::Evaluate {
+                    closure: Closure::new(*body, environment),
+                }
+            }
+            _ => Control::Return { value: closure },
+        };
+        Option::Some(control)
+    }
+
+    /// Returns `value` to `frame`. Returns `None` if `frame` applies a value
+    /// that is not an abstraction, leaving the machine unchanged.
+    fn resume(&mut self, frame: Frame, value: Closure) -> Option<Control> {
+        let expressions = self.environments.expressions();
+        let control = match frame {
+            Frame::Argument { argument } => {
+                self.continuation.push(Frame::Function { function: value });
+                Control::Evaluate { closure: argument }
+            }
+            Frame::Function { function } => match expressions[function.term] {
+                Expression::NamelessAbstraction {
+                    annotation: _,
+                    body,
+                } => {
+                    let environment = self.environments.bind_value(function.environment, value);
+                    Control::Evaluate {
+                        closure: Closure::new(body, environment),
+                    }
+                }
+                _ => {
+                    self.continuation.push(frame);
+                    return Option::None;
+                }
+            },
+            Frame::Let { body } => {
+                let environment = self.environments.bind_value(body.environment, value);
+                Control::Evaluate {
+                    closure: Closure::new(body.term, environment),
+                }
+            }
+        };
+        Option::Some(control)
+    }
+
+    /// Constructs in `destination` the locally nameless expression
+    /// represented by the state, that is the control with its environment
+    /// substituted in, plugged into the continuation.
+    pub fn unload(&self, destination: &mut ExpressionArena) -> ExpressionId {
+        let (Control::Evaluate { closure } | Control::Return { value: closure }) = self.control;
+        let mut expression = self.environments.unload(closure, 0, destination);
+        for frame in self.continuation.iter().rev() {
+            expression = match *frame {
+                Frame::Argument { argument } => {
+                    let argument = self.environments.unload(argument, 0, destination);
+                    destination.application(expression, vec![argument])
+                }
+                Frame::Function { function } => {
+                    let function = self.environments.unload(function, 0, destination);
+                    destination.application(function, vec![expression])
+                }
+                Frame::Let { body } => {
+                    let body = self.environments.unload(body, 1, destination);
+                    destination.nameless_let_binding(expression, body)
+                }
+            };
+        }
+        expression
+    }
+}
+
+/// Steps of unloading closures.
+enum Unload {
+    /// Unloads `term` in `environment`, where `term` occurs under `binders`
+    /// binders of its closure, and the closure itself under `outer` binders.
+    Visit {
+        term: ExpressionId,
+        environment: Environment,
+        binders: usize,
+        outer: usize,
+    },
+    /// Constructs the expression for `term` from its unloaded sub-expressions.
+    Build { term: ExpressionId },
+    /// Constructs the nameless variable `index`.
+    Index { index: DeBruijnIndex },
+}
+
+impl Environments<'_, Closure> {
+    /// The closure bound to `index` in `environment`, binding the group of
+    /// recursive definitions anew.
+    fn lookup_closure(
+        &mut self,
+        environment: Environment,
+        index: DeBruijnIndex,
+    ) -> Option<Closure> {
+        match self.lookup(environment, index) {
+            Lookup::Bound(bound) => Option::Some(self.closure(bound)),
+            Lookup::Loose { index: _ } => Option::None,
+        }
+    }
+
+    fn closure(&mut self, bound: Bound<Closure>) -> Closure {
+        match bound {
+            Bound::Value(closure) => closure,
+            Bound::Recursive {
+                group,
+                index,
+                environment,
+            } => {
+                let environment = self.bind_group(environment, group);
+                let definition = self.expressions()[group].children()[index];
+                Closure::new(definition, environment)
+            }
+        }
+    }
+
+    /// Constructs in `destination` the expression of `closure`, whose term
+    /// occurs under `binders` binders, by substituting the expressions of the
+    /// closures of its environment for its nameless variables. Recursive
+    /// definitions are unloaded as recursive let-bindings.
+    fn unload(
+        &self,
+        closure: Closure,
+        binders: usize,
+        destination: &mut ExpressionArena,
+    ) -> ExpressionId {
+        let expressions = self.expressions();
+        let mut steps = vec![Unload::Visit {
+            term: closure.term,
+            environment: closure.environment,
+            binders,
+            outer: 0,
+        }];
+        let mut results = Vec::new();
+        while let Option::Some(step) = steps.pop() {
+            match step {
+                Unload::Visit {
+                    term,
+                    environment,
+                    binders,
+                    outer,
+                } => match &expressions[term] {
+                    Expression::NamelessVariable { index } if index.into_usize() <= binders => {
+                        results.push(destination.nameless_variable(*index));
+                    }
+                    Expression::NamelessVariable { index } => {
+                        let index = DeBruijnIndex::new(index.into_usize() - binders);
+                        match self.lookup(environment, index) {
+                            Lookup::Bound(Bound::Value(closure)) => steps.push(Unload::Visit {
+                                term: closure.term,
+                                environment: closure.environment,
+                                binders: 0,
+                                outer: outer + binders,
+                            }),
+                            Lookup::Bound(Bound::Recursive {
+                                group,
+                                index,
+                                environment,
+                            }) => {
+                                // Unloads `letrec ... in` the `index`-th definition
+                                let count = expressions[group].child_binders(0).len();
+                                steps.push(Unload::Build { term: group });
+                                steps.push(Unload::Index {
+                                    index: DeBruijnIndex::new(count - index),
+                                });
+                                for &definition in
+                                    expressions[group].children()[..count].iter().rev()
+                                {
+                                    steps.push(Unload::Visit {
+                                        term: definition,
+                                        environment,
+                                        binders: count,
+                                        outer: outer + binders,
+                                    });
+                                }
+                            }
+                            Lookup::Loose { index } => {
+                                let index = DeBruijnIndex::new(index + binders + outer);
+                                results.push(destination.nameless_variable(index));
+                            }
+                        }
+                    }
+                    node => {
+                        steps.push(Unload::Build { term });
+                        for (child, &id) in node.children().iter().enumerate().rev() {
+                            steps.push(Unload::Visit {
+                                term: id,
+                                environment,
+                                binders: binders + node.child_binders(child).len(),
+                                outer,
+                            });
+                        }
+                    }
+                },
+                Unload::Index { index } => results.push(destination.nameless_variable(index)),
+                Unload::Build { term } => {
+                    let node = &expressions[term];
+                    let count = node.children().len();
+                    let children = results.split_off(results.len() - count);
+                    results.push(destination.add(node.with_children(&children)));
+                }
+            }
+        }
+        results.pop().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{referencing_environment::ReferencingEnvironment, strings::StringArena};
+
+    use super::*;
+
+    /// Parses `input`, and converts it to locally nameless representation in
+    /// `expressions`.
+    fn load(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        input: &str,
+    ) -> ExpressionId {
+        let mut parsed_expressions = ExpressionArena::new();
+        let parsed =
+            Expression::parse_mixed_expression(strings, &mut parsed_expressions, input.as_bytes())
+                .unwrap();
+        Expression::convert_to_locally_nameless(
+            (
+                Rc::new(ReferencingEnvironment::new()),
+                &parsed_expressions,
+                parsed,
+            ),
+            expressions,
+        )
+    }
+
+    fn check_unloaded(
+        strings: &mut StringArena,
+        expressions: &ExpressionArena,
+        unloaded: ExpressionId,
+        expected: &str,
+    ) {
+        let mut expected_expressions = ExpressionArena::new();
+        let expected = Expression::parse_mixed_expression(
+            strings,
+            &mut expected_expressions,
+            expected.as_bytes(),
+        )
+        .unwrap();
+        let environment = Rc::new(ReferencingEnvironment::new());
+        assert!(Expression::alpha_equivalent(
+            (environment.clone(), expressions, unloaded),
+            (environment, &expected_expressions, expected)
+        ));
+    }
+
+    fn check_krivine(input: &str, fuel: usize, expected: Option<&str>) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+
+        let expression = load(&mut strings, &mut expressions, input);
+        let mut machine = KrivineMachine::new(&expressions, expression);
+        let halted = machine.run(fuel);
+        match expected {
+            Option::Some(expected) => {
+                assert!(halted);
+                let unloaded = machine.unload(&mut destination);
+                check_unloaded(&mut strings, &destination, unloaded, expected);
+            }
+            Option::None => {
+                assert!(!halted);
+                assert_eq!(machine.steps(), fuel);
+            }
+        }
+    }
+
+    fn check_cek(input: &str, fuel: usize, expected: Option<&str>) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+
+        let expression = load(&mut strings, &mut expressions, input);
+        let mut machine = CekMachine::new(&expressions, expression);
+        let halted = machine.run(fuel);
+        match expected {
+            Option::Some(expected) => {
+                assert!(halted);
+                let value = machine.value().unwrap();
+                let unloaded = machine.unload_closure(value, &mut destination);
+                check_unloaded(&mut strings, &destination, unloaded, expected);
+            }
+            Option::None => {
+                assert!(!halted);
+                assert_eq!(machine.steps(), fuel);
+            }
+        }
+    }
+
+    const OMEGA: &str = "((λx. x x) (λx. x x))";
+
+    #[test]
+    fn krivine_machine_evaluates_to_weak_head_normal_forms() {
+        check_krivine("(λx. x) y", 10, Option::Some("y"));
+        check_krivine(&format!("(λx y. x) a {}", OMEGA), 10, Option::Some("a"));
+        check_krivine("(λx y. x) (λz. z)", 10, Option::Some("λy z. z"));
+        check_krivine("λz. (λx. x) z", 10, Option::Some("λz. (λx. x) z"));
+        check_krivine("f ((λx. x) a)", 10, Option::Some("f ((λx. x) a)"));
+        check_krivine("(λx. x 2) a", 10, Option::Some("a 1"));
+        check_krivine("let id = λx. x in id a", 10, Option::Some("a"));
+        check_krivine("letrec f = λn. n in f a", 10, Option::Some("a"));
+        check_krivine(
+            "letrec f = λn. f n in f",
+            10,
+            Option::Some("λn. (letrec f = λm. f m in f) n"),
+        );
+        check_krivine(OMEGA, 100, Option::None);
+    }
+
+    #[test]
+    fn cek_machine_evaluates_to_values() {
+        check_cek("(λx. x) ((λy. y) (λz. z))", 20, Option::Some("λz. z"));
+        check_cek("λz. (λx. x) z", 20, Option::Some("λz. (λx. x) z"));
+        check_cek("let id = λx. x in id (λz. z)", 20, Option::Some("λz. z"));
+        check_cek("letrec f = λn. n in f (λz. z)", 20, Option::Some("λz. z"));
+        check_cek("(λx y. x) a b", 20, Option::Some("a"));
+        check_cek(&format!("(λx y. x) a {}", OMEGA), 100, Option::None);
+    }
+
+    #[test]
+    fn cek_machine_gets_stuck_applying_non_abstractions() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+
+        let expression = load(&mut strings, &mut expressions, "g (f ((λx. x) a))");
+        let mut machine = CekMachine::new(&expressions, expression);
+        assert!(machine.run(100));
+        assert_eq!(machine.value(), Option::None);
+        assert!(matches!(
+            machine.continuation().last(),
+            Option::Some(Frame::Function { function: _ })
+        ));
+        let unloaded = machine.unload(&mut destination);
+        check_unloaded(&mut strings, &destination, unloaded, "g (f a)");
+    }
+
+    #[test]
+    fn machines_expose_their_states() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression = load(&mut strings, &mut expressions, "(λx. x) y");
+        let (function, argument) = match &expressions[expression] {
+            Expression::Application {
+                function,
+                arguments,
+            } => (*function, arguments[0]),
+            _ => unreachable!(),
+        };
+
+        let mut krivine = KrivineMachine::new(&expressions, expression);
+        assert!(krivine.step());
+        assert_eq!(krivine.term(), function);
+        assert_eq!(krivine.stack(), &[Closure::new(argument, Option::None)]);
+        assert!(krivine.step());
+        assert!(krivine.environment().is_some());
+        assert!(krivine.stack().is_empty());
+        assert!(krivine.step());
+        assert_eq!(krivine.term(), argument);
+        assert!(!krivine.step());
+        assert_eq!(krivine.steps(), 3);
+
+        let mut cek = CekMachine::new(&expressions, expression);
+        assert!(cek.step());
+        assert_eq!(
+            cek.control(),
+            Control::Evaluate {
+                closure: Closure::new(function, Option::None)
+            }
+        );
+        assert_eq!(
+            cek.continuation(),
+            &[Frame::Argument {
+                argument: Closure::new(argument, Option::None)
+            }]
+        );
+        assert!(cek.run(10));
+        assert_eq!(cek.steps(), 6);
+        assert_eq!(cek.value().unwrap().term(), argument);
+    }
+}
@@ -8,6 +8,7 @@ pub trait AdmissibleVariableNameGenerator {
     ) -> StringId;
 }
 
+#[derive(Clone)]
 pub struct VariableNameGenerator {
     bases: Vec<Box<[u8]>>,
 }
@@ -64,6 +65,8 @@ impl AdmissibleVariableNameGenerator for VariableNameGenerator {
 mod tests {
     use std::collections::HashSet;
 
+    use crate::parser::is_name;
+
     use super::*;
 
     #[test]
@@ -93,4 +96,23 @@ mod tests {
             claimed.insert(actual);
         }
     }
+
+    #[test]
+    fn generates_parseable_names() {
+        let mut strings = StringArena::new();
+        let mut generator = VariableNameGenerator::with_bases(vec![
+            Box::from("x'".as_bytes()),
+            Box::from("α".as_bytes()),
+            Box::from("f_inner".as_bytes()),
+            Box::from("my-var".as_bytes()),
+            Box::from("x₁".as_bytes()),
+        ]);
+        let mut claimed = HashSet::new();
+        for _ in 0..20 {
+            let actual = generator
+                .generate_admissible_name(&mut strings, |string| !claimed.contains(&string));
+            assert!(is_name(&strings[actual]));
+            claimed.insert(actual);
+        }
+    }
 }
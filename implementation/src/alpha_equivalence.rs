@@ -28,6 +28,24 @@ impl Expression {
     }
 }
 
+/// Steps of the comparison.
+enum Step {
+    Compare {
+        e1: ExpressionId,
+        e2: ExpressionId,
+    },
+    Bind {
+        e1: ExpressionId,
+        e2: ExpressionId,
+        child: usize,
+    },
+    Unbind {
+        e1: ExpressionId,
+        e2: ExpressionId,
+        child: usize,
+    },
+}
+
 struct AlphaEquivalence<'a> {
     expressions1: &'a ExpressionArena,
     environment1: &'a mut ReferencingEnvironment,
@@ -51,7 +69,50 @@ impl<'a> AlphaEquivalence<'a> {
     }
 
     fn alpha_equivalent(&mut self, e1: ExpressionId, e2: ExpressionId) -> bool {
-        match (&self.expressions1[e1], &self.expressions2[e2]) {
+        let (expressions1, expressions2) = (self.expressions1, self.expressions2);
+        let mut steps = vec![Step::Compare { e1, e2 }];
+        while let Option::Some(step) = steps.pop() {
+            match step {
+                Step::Compare { e1, e2 } => {
+                    let (n1, n2) = (&expressions1[e1], &expressions2[e2]);
+                    if !self.alpha_equivalent_nodes(n1, n2) {
+                        return false;
+                    }
+                    let children = n1.children().into_iter().zip(n2.children());
+                    for (child, (c1, c2)) in children.enumerate().rev() {
+                        // Equivalent nodes bind as many parameters over their children
+                        let binds = !n1.child_binders(child).is_empty();
+                        if binds {
+                            steps.push(Step::Unbind { e1, e2, child });
+                        }
+                        steps.push(Step::Compare { e1: c1, e2: c2 });
+                        if binds {
+                            steps.push(Step::Bind { e1, e2, child });
+                        }
+                    }
+                }
+                Step::Bind { e1, e2, child } => {
+                    self.environment1
+                        .bind_parameters(&expressions1[e1].child_binders(child));
+                    self.environment2
+                        .bind_parameters(&expressions2[e2].child_binders(child));
+                }
+                Step::Unbind { e1, e2, child } => {
+                    self.environment2
+                        .unbind_parameters(&expressions2[e2].child_binders(child));
+                    self.environment1
+                        .unbind_parameters(&expressions1[e1].child_binders(child));
+                }
+            }
+        }
+        true
+    }
+
+    /// Checks the alpha-equivalence of the expressions `e1` and `e2` without
+    /// their sub-expressions, which must then be equal in number. Named and
+    /// nameless binding constructs of the same kind are equivalent.
+    fn alpha_equivalent_nodes(&self, e1: &Expression, e2: &Expression) -> bool {
+        match (e1, e2) {
             (Expression::Variable { identifier: i1 }, Expression::Variable { identifier: i2 }) => {
                 match (
                     self.environment1.lookup_index(*i1),
@@ -62,6 +123,9 @@ impl<'a> AlphaEquivalence<'a> {
                     _ => false,
                 }
             }
+            (Expression::Constant { constant: c1 }, Expression::Constant { constant: c2 }) => {
+                c1 == c2
+            }
             (
                 Expression::Variable { identifier: i1 },
                 Expression::NamelessVariable { index: i2 },
@@ -82,126 +146,110 @@ impl<'a> AlphaEquivalence<'a> {
             ) => i1 == i2,
             (
                 Expression::Abstraction {
-                    parameter: param1,
-                    body: b1,
+                    parameter: _,
+                    annotation: t1,
+                    body: _,
+                }
+                | Expression::NamelessAbstraction {
+                    annotation: t1,
+                    body: _,
                 },
                 Expression::Abstraction {
-                    parameter: param2,
-                    body: b2,
-                },
-            ) => match (param1, param2) {
-                (Option::Some(param1), Option::Some(param2)) => {
-                    self.environment1.bind(*param1);
-                    self.environment2.bind(*param2);
-                    let r = self.alpha_equivalent(*b1, *b2);
-                    self.environment2.unbind(*param2);
-                    self.environment1.unbind(*param1);
-                    r
-                }
-                (Option::Some(param1), Option::None) => {
-                    self.environment1.bind(*param1);
-                    self.environment2.shift();
-                    let r = self.alpha_equivalent(*b1, *b2);
-                    self.environment2.unshift();
-                    self.environment1.unbind(*param1);
-                    r
-                }
-                (Option::None, Option::Some(param2)) => {
-                    self.environment1.shift();
-                    self.environment2.bind(*param2);
-                    let r = self.alpha_equivalent(*b1, *b2);
-                    self.environment2.unbind(*param2);
-                    self.environment1.unshift();
-                    r
-                }
-                (Option::None, Option::None) => {
-                    self.environment1.shift();
-                    self.environment2.shift();
-                    let r = self.alpha_equivalent(*b1, *b2);
-                    self.environment2.unshift();
-                    self.environment1.unshift();
-                    r
+                    parameter: _,
+                    annotation: t2,
+                    body: _,
                 }
-            },
+                | Expression::NamelessAbstraction {
+                    annotation: t2,
+                    body: _,
+                },
+            ) => t1.is_some() == t2.is_some(),
             (
-                Expression::Abstraction {
-                    parameter: param1,
-                    body: b1,
+                Expression::Application {
+                    function: _,
+                    arguments: as1,
+                },
+                Expression::Application {
+                    function: _,
+                    arguments: as2,
                 },
-                Expression::NamelessAbstraction { body: b2 },
-            ) => match param1 {
-                Option::Some(param1) => {
-                    self.environment1.bind(*param1);
-                    self.environment2.shift();
-                    let r = self.alpha_equivalent(*b1, *b2);
-                    self.environment2.unshift();
-                    self.environment1.unbind(*param1);
-                    r
+            ) => as1.len() == as2.len(),
+            (
+                Expression::Let {
+                    parameter: _,
+                    definition: _,
+                    body: _,
                 }
-                Option::None => {
-                    self.environment1.shift();
-                    self.environment2.shift();
-                    let r = self.alpha_equivalent(*b1, *b2);
-                    self.environment2.unshift();
-                    self.environment1.unshift();
-                    r
+                | Expression::NamelessLet {
+                    definition: _,
+                    body: _,
+                },
+                Expression::Let {
+                    parameter: _,
+                    definition: _,
+                    body: _,
                 }
-            },
-            (
-                Expression::NamelessAbstraction { body: b1 },
-                Expression::Abstraction {
-                    parameter: param2,
-                    body: b2,
+                | Expression::NamelessLet {
+                    definition: _,
+                    body: _,
                 },
-            ) => match param2 {
-                Option::Some(param2) => {
-                    self.environment1.shift();
-                    self.environment2.bind(*param2);
-                    let r = self.alpha_equivalent(*b1, *b2);
-                    self.environment2.unbind(*param2);
-                    self.environment1.unshift();
-                    r
+            ) => true,
+            (
+                Expression::LetRec {
+                    parameters: _,
+                    definitions: ds1,
+                    body: _,
                 }
-                Option::None => {
-                    self.environment1.shift();
-                    self.environment2.shift();
-                    let r = self.alpha_equivalent(*b1, *b2);
-                    self.environment2.unshift();
-                    self.environment1.unshift();
-                    r
+                | Expression::NamelessLetRec {
+                    definitions: ds1,
+                    body: _,
+                },
+                Expression::LetRec {
+                    parameters: _,
+                    definitions: ds2,
+                    body: _,
                 }
-            },
+                | Expression::NamelessLetRec {
+                    definitions: ds2,
+                    body: _,
+                },
+            ) => ds1.len() == ds2.len(),
             (
-                Expression::NamelessAbstraction { body: b1 },
-                Expression::NamelessAbstraction { body: b2 },
-            ) => {
-                self.environment1.shift();
-                self.environment2.shift();
-                let r = self.alpha_equivalent(*b1, *b2);
-                self.environment2.unshift();
-                self.environment1.unshift();
-                r
-            }
+                Expression::Pi {
+                    parameter: _,
+                    domain: _,
+                    codomain: _,
+                }
+                | Expression::NamelessPi {
+                    domain: _,
+                    codomain: _,
+                },
+                Expression::Pi {
+                    parameter: _,
+                    domain: _,
+                    codomain: _,
+                }
+                | Expression::NamelessPi {
+                    domain: _,
+                    codomain: _,
+                },
+            ) => true,
             (
-                Expression::Application {
-                    function: f1,
-                    arguments: as1,
+                Expression::Case {
+                    scrutinee: _,
+                    branches: bs1,
                 },
-                Expression::Application {
-                    function: f2,
-                    arguments: as2,
+                Expression::Case {
+                    scrutinee: _,
+                    branches: bs2,
                 },
             ) => {
-                if as1.len() != as2.len() || !self.alpha_equivalent(*f1, *f2) {
-                    false
-                } else {
-                    for (&a1, &a2) in as1.iter().zip(as2.iter()) {
-                        if !self.alpha_equivalent(a1, a2) {
-                            return false;
-                        }
-                    }
-                    true
-                }
+                // Branches must match the same constructors with the same
+                // number of pattern variables
+                bs1.len() == bs2.len()
+                    && bs1.iter().zip(bs2.iter()).all(|(b1, b2)| {
+                        b1.constructor() == b2.constructor() && b1.arity() == b2.arity()
+                    })
             }
             _ => false,
         }
@@ -251,6 +299,56 @@ mod tests {
         check_alpha_equivalence("λf. λx. f x", "λg. λy. g y", true);
         check_alpha_equivalence("λ. λ. 1", "λx. λx. x", true);
         check_alpha_equivalence("λy. λy. y", "λx. λx. x", true);
+        check_alpha_equivalence("let x = y in x", "let z = y in z", true);
+        check_alpha_equivalence("let x = y in x", "let = y in 1", true);
+        check_alpha_equivalence("let _ = y in λx. x", "let = y in λ. 1", true);
+        check_alpha_equivalence("λx. let x = x in x", "λ. let = 1 in 1", true);
+        check_alpha_equivalence("λx. let y = x in x", "λ. let = 1 in 2", true);
+        check_alpha_equivalence("λx : T. x", "λ : T. 1", true);
+        check_alpha_equivalence("λx. λy : x. y", "λ. λ : 1. 1", true);
+        check_alpha_equivalence("λx. λx : x. x", "λy. λz : y. z", true);
+        check_alpha_equivalence("Π(x : A). x", "Π(y : A). y", true);
+        check_alpha_equivalence("Π(x : A). B", "A → B", true);
+        check_alpha_equivalence("Π(_ : A). B", "A → B", true);
+        check_alpha_equivalence("A → B", "Π(: A). B", true);
+        check_alpha_equivalence("Π(A : Type). A → A", "Π(: Type). Π(: 1). 2", true);
+        check_alpha_equivalence("case p of {}", "case p of {}", true);
+        check_alpha_equivalence(
+            "letrec f = g and g = f in f g",
+            "letrec = 1 and = 2 in 2 1",
+            true,
+        );
+        check_alpha_equivalence(
+            "letrec even = λn. odd n and odd = λn. even n in even",
+            "letrec e = λm. o m and o = λm. e m in e",
+            true,
+        );
+        check_alpha_equivalence(
+            "letrec f = g and g = g in g",
+            "letrec = 1 and = 1 in 1",
+            true,
+        );
+        check_alpha_equivalence("λx. x #1 \"x\"", "λ. 1 #1 \"x\"", true);
+        check_alpha_equivalence(
+            "case p of { pair a b => a }",
+            "case p of { pair x y => x }",
+            true,
+        );
+        check_alpha_equivalence(
+            "case p of { pair a b => a b }",
+            "case p of { pair/2 => 2 1 }",
+            true,
+        );
+        check_alpha_equivalence(
+            "case p of { pair a a => a }",
+            "case p of { pair _ b => b }",
+            true,
+        );
+        check_alpha_equivalence(
+            "λp. case p of { c x => p x }",
+            "λ. case 1 of { c/1 => 2 1 }",
+            true,
+        );
 
         check_alpha_equivalence("x", "y", false);
         check_alpha_equivalence("λx. λf. f x", "λg. λy. g y", false);
@@ -261,5 +359,43 @@ mod tests {
         check_alpha_equivalence("(λx. x) z", "(λy. y) w", false);
         check_alpha_equivalence("λx. x", "w", false);
         check_alpha_equivalence("λ_. λx. x", "λ. λ. 2", false);
+        check_alpha_equivalence("let x = y in x", "let x = z in x", false);
+        check_alpha_equivalence("let x = x in x", "let = 1 in 1", false);
+        check_alpha_equivalence("λx. let x = x in x", "λ. let = 1 in 2", false);
+        check_alpha_equivalence("let x = y in x", "(λx. x) y", false);
+        check_alpha_equivalence("λx : T. x", "λx. x", false);
+        check_alpha_equivalence("λx : T. x", "λx : U. x", false);
+        check_alpha_equivalence("λx. λx : x. x", "λ. λ : 1. 2", false);
+        check_alpha_equivalence("Π(x : A). x", "Π(x : B). x", false);
+        check_alpha_equivalence("Π(x : x). x", "Π(: 1). 1", false);
+        check_alpha_equivalence("Π(x : A). x", "λx : A. x", false);
+        check_alpha_equivalence("case p of {}", "case q of {}", false);
+        check_alpha_equivalence("letrec f = f in f", "let f = f in f", false);
+        check_alpha_equivalence(
+            "letrec f = g and g = f in f",
+            "letrec = 1 and = 2 in 1",
+            false,
+        );
+        check_alpha_equivalence("letrec f = f in f", "letrec f = f and g = g in f", false);
+        check_alpha_equivalence("λx. \"x\"", "λx. x", false);
+        check_alpha_equivalence("#1", "#2", false);
+        check_alpha_equivalence("true", "false", false);
+        check_alpha_equivalence(
+            "case p of { pair a b => a }",
+            "case p of { pair a b => b }",
+            false,
+        );
+        check_alpha_equivalence(
+            "case p of { pair a b => a }",
+            "case p of { cons a b => a }",
+            false,
+        );
+        check_alpha_equivalence("case p of { c a => a }", "case p of { c/2 => 1 }", false);
+        check_alpha_equivalence(
+            "case p of { c => x }",
+            "case p of { c => x | d => x }",
+            false,
+        );
+        check_alpha_equivalence("case a of { c a => a }", "case a of { c/1 => 2 }", false);
     }
 }
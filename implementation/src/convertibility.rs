@@ -0,0 +1,184 @@
+use std::rc::Rc;
+
+use crate::{
+    expression::{Expression, ExpressionArena, ExpressionId},
+    referencing_environment::ReferencingEnvironment,
+};
+
+/// The outcome of checking whether two expressions are beta-eta convertible
+/// with a limited amount of fuel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Convertibility {
+    /// The expressions have alpha-equivalent beta-eta normal forms.
+    Convertible,
+    /// The expressions have beta-eta normal forms that are not
+    /// alpha-equivalent.
+    Inconvertible,
+    /// The fuel ran out while normalizing one of the expressions, which may
+    /// not have a normal form.
+    Unknown,
+}
+
+impl Expression {
+    /// Decides whether `e1` and `e2` are beta-eta convertible by normalizing
+    /// both using at most `fuel` steps each, eta-reducing their normal forms,
+    /// and checking that the results are alpha-equivalent. Alpha-equivalent
+    /// expressions are convertible without being normalized.
+    pub fn convertible(
+        (environment1, expressions1, e1): (
+            Rc<ReferencingEnvironment>,
+            &ExpressionArena,
+            ExpressionId,
+        ),
+        (environment2, expressions2, e2): (
+            Rc<ReferencingEnvironment>,
+            &ExpressionArena,
+            ExpressionId,
+        ),
+        fuel: usize,
+    ) -> Convertibility {
+        if Expression::alpha_equivalent(
+            (environment1.clone(), expressions1, e1),
+            (environment2.clone(), expressions2, e2),
+        ) {
+            return Convertibility::Convertible;
+        }
+        let mut normal_forms = ExpressionArena::new();
+        let n1 = beta_eta_normal_form(
+            (environment1.clone(), expressions1, e1),
+            &mut normal_forms,
+            fuel,
+        );
+        let n2 = beta_eta_normal_form(
+            (environment2.clone(), expressions2, e2),
+            &mut normal_forms,
+            fuel,
+        );
+        match (n1, n2) {
+            (Option::Some(n1), Option::Some(n2)) => {
+                if Expression::alpha_equivalent(
+                    (environment1, &normal_forms, n1),
+                    (environment2, &normal_forms, n2),
+                ) {
+                    Convertibility::Convertible
+                } else {
+                    Convertibility::Inconvertible
+                }
+            }
+            _ => Convertibility::Unknown,
+        }
+    }
+}
+
+/// Constructs the beta-eta normal form of `expression` in locally nameless
+/// representation in `destination`, or returns `None` if the fuel runs out.
+/// Eta-reducing a beta normal form creates no beta-redex.
+fn beta_eta_normal_form(
+    (environment, expressions, expression): (
+        Rc<ReferencingEnvironment>,
+        &ExpressionArena,
+        ExpressionId,
+    ),
+    destination: &mut ExpressionArena,
+    fuel: usize,
+) -> Option<ExpressionId> {
+    let mut locally_nameless_expressions = ExpressionArena::new();
+    let locally_nameless = Expression::convert_to_locally_nameless(
+        (environment, expressions, expression),
+        &mut locally_nameless_expressions,
+    );
+    let normal_form = Expression::normalize(
+        &locally_nameless_expressions,
+        locally_nameless,
+        destination,
+        fuel,
+    )?;
+    Option::Some(Expression::eta_normalize(destination, normal_form))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strings::StringArena;
+
+    use super::*;
+
+    fn check_convertible(
+        environment: &[&str],
+        e1: &str,
+        e2: &str,
+        fuel: usize,
+        expected: Convertibility,
+    ) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut environment_frame = ReferencingEnvironment::new();
+        for &name in environment {
+            environment_frame.bind(strings.intern(name.as_bytes()));
+        }
+        let environment = Rc::new(environment_frame);
+
+        let e1 = Expression::parse_mixed_expression(&mut strings, &mut expressions, e1.as_bytes())
+            .unwrap();
+        let e2 = Expression::parse_mixed_expression(&mut strings, &mut expressions, e2.as_bytes())
+            .unwrap();
+        assert_eq!(
+            Expression::convertible(
+                (environment.clone(), &expressions, e1),
+                (environment.clone(), &expressions, e2),
+                fuel
+            ),
+            expected
+        );
+        assert_eq!(
+            Expression::convertible(
+                (environment.clone(), &expressions, e2),
+                (environment.clone(), &expressions, e1),
+                fuel
+            ),
+            expected
+        );
+    }
+
+    const OMEGA: &str = "((λx. x x) (λx. x x))";
+
+    #[test]
+    fn convertible_decides_beta_eta_equivalence() {
+        let convertible = Convertibility::Convertible;
+        check_convertible(&[], "(λx. x) y", "y", 10, convertible);
+        check_convertible(&[], "λx. f x", "f", 10, convertible);
+        check_convertible(&[], "λx. (λy. y) f x", "f", 10, convertible);
+        check_convertible(&[], "λx y. x", "λa b. a", 10, convertible);
+        check_convertible(
+            &[],
+            "(λm n f x. m f (n f x)) (λf x. f (f x)) (λf x. f (f x))",
+            "λf x. f (f (f (f x)))",
+            20,
+            convertible,
+        );
+        check_convertible(&["x"], "x", "(λy. y) x", 10, convertible);
+        check_convertible(&[], OMEGA, OMEGA, 10, convertible);
+    }
+
+    #[test]
+    fn convertible_distinguishes_normal_forms() {
+        let inconvertible = Convertibility::Inconvertible;
+        check_convertible(&[], "λx y. x", "λx y. y", 10, inconvertible);
+        check_convertible(&[], "(λx. x) y", "z", 10, inconvertible);
+        check_convertible(&[], "λx. f x x", "f", 10, inconvertible);
+        check_convertible(&["x", "y"], "x", "(λz. z) y", 10, inconvertible);
+    }
+
+    #[test]
+    fn convertible_is_unknown_when_out_of_fuel() {
+        let unknown = Convertibility::Unknown;
+        check_convertible(&[], OMEGA, "y", 100, unknown);
+        check_convertible(&[], &format!("f {}", OMEGA), "f y", 100, unknown);
+        check_convertible(
+            &[],
+            "(λm n f x. m f (n f x)) (λf x. f (f x)) (λf x. f (f x))",
+            "λf x. f (f (f (f x)))",
+            1,
+            unknown,
+        );
+    }
+}
@@ -0,0 +1,455 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    expression::{DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+    referencing_environment::ReferencingEnvironment,
+    strings::StringId,
+    visitor::{Rewrite, Scoping, Visitor},
+};
+
+impl Expression {
+    /// Shifts by `amount` the nameless variables in `expression` whose index
+    /// exceeds `cutoff` plus the number of binders they occur under, that is
+    /// the nameless variables referring outside of `expression` past its
+    /// first `cutoff` enclosing binders. Unchanged sub-expressions are kept
+    /// as is.
+    ///
+    /// Panics if a shifted index falls below 1, as the variable would then
+    /// refer to one of the first `cutoff` binders.
+    pub fn shift(
+        expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+        amount: isize,
+        cutoff: usize,
+    ) -> ExpressionId {
+        Shift::new(amount, cutoff, false).rewrite_expression(expressions, expression)
+    }
+
+    /// Instantiates the outermost bound index of `body` with `term`, where
+    /// `body` is the body of a nameless abstraction. The nameless variables
+    /// in `body` referring to that abstraction are replaced with copies of
+    /// `term`, shifted past the binders they occur under, and the ones
+    /// referring outside of it are shifted down by one.
+    ///
+    /// The copies of `term` are not renamed, so a named binder of `body`
+    /// over the replaced variables must not bind a free variable of `term`
+    /// that variables of `body` refer to by name, which is checked in debug
+    /// builds. The parameters of binders only referred to by nameless
+    /// variables are merely hints.
+    pub fn open(
+        expressions: &mut ExpressionArena,
+        body: ExpressionId,
+        term: ExpressionId,
+    ) -> ExpressionId {
+        if cfg!(debug_assertions) {
+            let free_variables = Expression::free_variables(
+                Rc::new(ReferencingEnvironment::new()),
+                expressions,
+                term,
+            );
+            debug_assert!(
+                !Captures::new(expressions, free_variables).find(body),
+                "opening would capture a free variable of the term"
+            );
+        }
+        Open::new(term).rewrite_expression(expressions, body)
+    }
+
+    /// Abstracts the free occurrences of the variable `name` in `term` into
+    /// the outermost bound index, such that the result is the body of a
+    /// nameless abstraction binding `name`. This is the inverse of
+    /// [opening](Expression::open) with the variable `name`.
+    pub fn close(
+        expressions: &mut ExpressionArena,
+        term: ExpressionId,
+        name: StringId,
+    ) -> ExpressionId {
+        Close::new(name).rewrite_expression(expressions, term)
+    }
+}
+
+/// Computes the shifted index `index + amount`.
+fn shift_index(index: DeBruijnIndex, amount: isize) -> DeBruijnIndex {
+    let shifted = index.into_usize() as isize + amount;
+    assert!(
+        shifted > 0,
+        "shifting the index {} by {} leaves no binder to refer to",
+        index.into_usize(),
+        amount
+    );
+    DeBruijnIndex::new(shifted as usize)
+}
+
+struct Shift {
+    amount: isize,
+    cutoff: usize,
+    /// Whether to construct a copy of the expression even where it is
+    /// unchanged, so that the result shares no sub-expression with it.
+    copy: bool,
+}
+
+impl Shift {
+    fn new(amount: isize, cutoff: usize, copy: bool) -> Shift {
+        Shift {
+            amount,
+            cutoff,
+            copy,
+        }
+    }
+}
+
+impl Scoping for Shift {
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.cutoff += parameters.len();
+    }
+
+    fn exit_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.cutoff -= parameters.len();
+    }
+}
+
+impl Rewrite for Shift {
+    fn enter_expression(
+        &mut self,
+        expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+    ) -> Option<ExpressionId> {
+        match expressions[expression] {
+            Expression::NamelessVariable { index } if index.into_usize() > self.cutoff => {
+                Option::Some(expressions.nameless_variable(shift_index(index, self.amount)))
+            }
+            ref node if self.copy && node.children().is_empty() => {
+                let copy = node.with_children(&[]);
+                Option::Some(expressions.add(copy))
+            }
+            _ => Option::None,
+        }
+    }
+}
+
+struct Open {
+    term: ExpressionId,
+    binders: usize,
+}
+
+impl Open {
+    fn new(term: ExpressionId) -> Open {
+        Open { term, binders: 0 }
+    }
+}
+
+impl Scoping for Open {
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders += parameters.len();
+    }
+
+    fn exit_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders -= parameters.len();
+    }
+}
+
+impl Rewrite for Open {
+    fn enter_expression(
+        &mut self,
+        expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+    ) -> Option<ExpressionId> {
+        match expressions[expression] {
+            Expression::NamelessVariable { index } if index.into_usize() == self.binders + 1 => {
+                // Every occurrence gets its own copy of `term`
+                let mut shift = Shift::new(self.binders as isize, 0, true);
+                Option::Some(shift.rewrite_expression(expressions, self.term))
+            }
+            Expression::NamelessVariable { index } if index.into_usize() > self.binders + 1 => {
+                Option::Some(expressions.nameless_variable(shift_index(index, -1)))
+            }
+            _ => Option::None,
+        }
+    }
+}
+
+/// Finds whether opening a body with a term would capture a free variable
+/// of the term, that is whether a named binder over the replaced variables
+/// binds one of `free_variables` and is referred to by name.
+struct Captures<'a> {
+    expressions: &'a ExpressionArena,
+    free_variables: HashSet<StringId>,
+    binders: usize,
+    /// The binders in scope binding one of `free_variables`, from the
+    /// outermost to the innermost, as whether variables are replaced under
+    /// them and whether they are referred to by name.
+    scopes: Vec<(bool, bool)>,
+    /// The indices in `scopes` of the binders in scope per parameter.
+    parameters: HashMap<StringId, Vec<usize>>,
+    capture: bool,
+}
+
+impl<'a> Captures<'a> {
+    fn new(expressions: &'a ExpressionArena, free_variables: HashSet<StringId>) -> Captures<'a> {
+        Captures {
+            expressions,
+            free_variables,
+            binders: 0,
+            scopes: Vec::new(),
+            parameters: HashMap::new(),
+            capture: false,
+        }
+    }
+
+    fn find(mut self, body: ExpressionId) -> bool {
+        self.visit_expression(body);
+        self.capture
+    }
+
+    fn captured_parameters<'p>(
+        &'p self,
+        parameters: &'p [Option<StringId>],
+    ) -> impl Iterator<Item = StringId> + 'p {
+        parameters
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|parameter| self.free_variables.contains(parameter))
+    }
+}
+
+impl Scoping for Captures<'_> {
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders += parameters.len();
+        let captured: Vec<_> = self.captured_parameters(parameters).collect();
+        if !captured.is_empty() {
+            let scope = self.scopes.len();
+            self.scopes.push((false, false));
+            for parameter in captured {
+                self.parameters.entry(parameter).or_default().push(scope);
+            }
+        }
+    }
+
+    fn exit_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders -= parameters.len();
+        let captured: Vec<_> = self.captured_parameters(parameters).collect();
+        if !captured.is_empty() {
+            for parameter in captured {
+                self.parameters.get_mut(&parameter).unwrap().pop();
+            }
+            let (replaced, named) = self.scopes.pop().unwrap();
+            self.capture |= replaced && named;
+            // Variables replaced under the binder are also under the
+            // enclosing ones
+            if let Option::Some(enclosing) = self.scopes.last_mut() {
+                enclosing.0 |= replaced;
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Captures<'a> {
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn visit_variable(&mut self, _expression: ExpressionId, identifier: StringId) {
+        if let Option::Some(&scope) = self
+            .parameters
+            .get(&identifier)
+            .and_then(|scopes| scopes.last())
+        {
+            self.scopes[scope].1 = true;
+        }
+    }
+
+    fn visit_nameless_variable(&mut self, _expression: ExpressionId, index: DeBruijnIndex) {
+        if index.into_usize() == self.binders + 1 {
+            if let Option::Some(scope) = self.scopes.last_mut() {
+                scope.0 = true;
+            }
+        }
+    }
+}
+
+struct Close {
+    name: StringId,
+    binders: usize,
+    /// The number of binders in scope that shadow `name`.
+    shadows: usize,
+}
+
+impl Close {
+    fn new(name: StringId) -> Close {
+        Close {
+            name,
+            binders: 0,
+            shadows: 0,
+        }
+    }
+}
+
+impl Scoping for Close {
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders += parameters.len();
+        if parameters.contains(&Option::Some(self.name)) {
+            self.shadows += 1;
+        }
+    }
+
+    fn exit_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders -= parameters.len();
+        if parameters.contains(&Option::Some(self.name)) {
+            self.shadows -= 1;
+        }
+    }
+}
+
+impl Rewrite for Close {
+    fn enter_expression(
+        &mut self,
+        expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+    ) -> Option<ExpressionId> {
+        match expressions[expression] {
+            Expression::Variable { identifier } if identifier == self.name && self.shadows == 0 => {
+                Option::Some(expressions.nameless_variable(DeBruijnIndex::new(self.binders + 1)))
+            }
+            Expression::NamelessVariable { index } if index.into_usize() > self.binders => {
+                Option::Some(expressions.nameless_variable(shift_index(index, 1)))
+            }
+            _ => Option::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{strings::StringArena, validation::ValidationError};
+
+    use super::*;
+
+    fn parse(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        input: &str,
+    ) -> ExpressionId {
+        Expression::parse_mixed_expression(strings, expressions, input.as_bytes()).unwrap()
+    }
+
+    fn check_shift(input: &str, amount: isize, cutoff: usize, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression = parse(&mut strings, &mut expressions, input);
+        let expected = parse(&mut strings, &mut expressions, expected);
+
+        let shifted = Expression::shift(&mut expressions, expression, amount, cutoff);
+        assert!(Expression::equals(
+            (&expressions, shifted),
+            (&expressions, expected)
+        ));
+    }
+
+    fn check_open(body: &str, term: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let body = parse(&mut strings, &mut expressions, body);
+        let term = parse(&mut strings, &mut expressions, term);
+        let expected = parse(&mut strings, &mut expressions, expected);
+
+        let opened = Expression::open(&mut expressions, body, term);
+        assert!(Expression::equals(
+            (&expressions, opened),
+            (&expressions, expected)
+        ));
+        // Opened expressions may have free indices, but are still trees
+        assert!(Expression::validate(&expressions, opened)
+            .iter()
+            .all(|error| !matches!(error, ValidationError::SharedExpression { .. })));
+    }
+
+    fn check_close(term: &str, name: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let term = parse(&mut strings, &mut expressions, term);
+        let expected = parse(&mut strings, &mut expressions, expected);
+        let name = strings.intern(name.as_bytes());
+
+        let closed = Expression::close(&mut expressions, term, name);
+        assert!(Expression::equals(
+            (&expressions, closed),
+            (&expressions, expected)
+        ));
+
+        let variable = expressions.variable(name);
+        let reopened = Expression::open(&mut expressions, closed, variable);
+        assert!(Expression::equals(
+            (&expressions, reopened),
+            (&expressions, term)
+        ));
+    }
+
+    #[test]
+    fn shift_shifts_free_indices() {
+        check_shift("1", 2, 0, "3");
+        check_shift("1 2", 1, 1, "1 3");
+        check_shift("λ. 1 2 3", 1, 0, "λ. 1 3 4");
+        check_shift("λx. 1 2 x", 1, 0, "λx. 1 3 x");
+        check_shift("λ. 1 3", -1, 0, "λ. 1 2");
+        check_shift(
+            "case 1 of { c/2 => 3 | d x => 2 }",
+            1,
+            0,
+            "case 2 of { c/2 => 4 | d x => 3 }",
+        );
+    }
+
+    #[test]
+    fn shift_keeps_unchanged_expressions() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression = parse(&mut strings, &mut expressions, "λ. f 1 (λ. 2)");
+        let size = expressions.len();
+        assert_eq!(
+            Expression::shift(&mut expressions, expression, 1, 0),
+            expression
+        );
+        assert_eq!(expressions.len(), size);
+    }
+
+    #[test]
+    fn open_instantiates_outermost_index() {
+        check_open("1", "y", "y");
+        check_open("f 1 1", "λ. 1", "f (λ. 1) (λ. 1)");
+        check_open("λ. 1 2", "y", "λ. 1 y");
+        check_open("λ. 2 3", "1", "λ. 2 2");
+        check_open("λx. 2 x", "1", "λx. 2 x");
+        check_open("let = 1 in 2", "a", "let = a in a");
+        check_open("f 1 (λy. y)", "y", "f y (λy. y)");
+    }
+
+    #[test]
+    #[should_panic(expected = "opening would capture a free variable of the term")]
+    #[cfg(debug_assertions)]
+    fn open_checks_for_capture() {
+        check_open("λy. y 2", "y", "λy. y y");
+    }
+
+    #[test]
+    #[should_panic(expected = "shifting the index 1 by -1 leaves no binder to refer to")]
+    fn shift_rejects_indices_below_one() {
+        check_shift("1", -1, 0, "1");
+    }
+
+    #[test]
+    fn close_abstracts_free_names() {
+        check_close("x", "x", "1");
+        check_close("f x y", "x", "f 1 y");
+        check_close("λ. 1 x 2", "x", "λ. 1 2 3");
+        check_close("λx. x", "x", "λx. x");
+        check_close("let y = x in λ : x. y x", "x", "let y = 1 in λ : 2. y 3");
+    }
+}
@@ -0,0 +1,106 @@
+use crate::expression::{DeBruijnIndex, ExpressionArena, ExpressionId};
+
+/// Environment IDs as indices in the bindings of [Environments]. The empty
+/// environment has no ID.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EnvironmentId {
+    index: usize,
+}
+
+pub type Environment = Option<EnvironmentId>;
+
+/// Bindings of environments, as linked lists from the innermost binding.
+struct Binding<T> {
+    bound: Bound<T>,
+    parent: Environment,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Bound<T> {
+    Value(T),
+    /// The `index`-th definition of the recursive let-binding group `group`,
+    /// bound in `environment`.
+    Recursive {
+        group: ExpressionId,
+        index: usize,
+        environment: Environment,
+    },
+}
+
+pub(crate) enum Lookup<T> {
+    Bound(Bound<T>),
+    /// The variable refers to the `index`-th binder outside of the
+    /// environment.
+    Loose {
+        index: usize,
+    },
+}
+
+/// The environments binding the nameless variables of locally nameless
+/// `expressions` to values of type `T`, shared by evaluators.
+pub(crate) struct Environments<'a, T> {
+    expressions: &'a ExpressionArena,
+    bindings: Vec<Binding<T>>,
+}
+
+impl<'a, T: Copy> Environments<'a, T> {
+    pub(crate) fn new(expressions: &'a ExpressionArena) -> Environments<'a, T> {
+        Environments {
+            expressions,
+            bindings: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    pub(crate) fn bind(&mut self, environment: Environment, bound: Bound<T>) -> Environment {
+        let index = self.bindings.len();
+        self.bindings.push(Binding {
+            bound,
+            parent: environment,
+        });
+        Option::Some(EnvironmentId { index })
+    }
+
+    pub(crate) fn bind_value(&mut self, environment: Environment, value: T) -> Environment {
+        self.bind(environment, Bound::Value(value))
+    }
+
+    /// Binds the definitions of the recursive let-binding group `group` in
+    /// `environment`.
+    pub(crate) fn bind_group(
+        &mut self,
+        environment: Environment,
+        group: ExpressionId,
+    ) -> Environment {
+        let count = self.expressions[group].child_binders(0).len();
+        (0..count).fold(environment, |extended, index| {
+            self.bind(
+                extended,
+                Bound::Recursive {
+                    group,
+                    index,
+                    environment,
+                },
+            )
+        })
+    }
+
+    /// Looks up what `index` is bound to in `environment`.
+    pub(crate) fn lookup(&self, environment: Environment, index: DeBruijnIndex) -> Lookup<T> {
+        let mut environment = environment;
+        let mut remaining = index.into_usize();
+        while let Option::Some(id) = environment {
+            let binding = &self.bindings[id.index];
+            remaining -= 1;
+            if remaining == 0 {
+                return Lookup::Bound(binding.bound);
+            }
+            environment = binding.parent;
+        }
+        Lookup::Loose { index: remaining }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::expression::{Expression, ExpressionArena, ExpressionId};
+use crate::expression::{CaseBranch, Expression, ExpressionArena, ExpressionId};
 
 impl Expression {
     pub fn equals(
@@ -26,7 +26,21 @@ impl<'a> Equality<'a> {
     }
 
     fn equals(&self, e1: ExpressionId, e2: ExpressionId) -> bool {
-        match (&self.expressions1[e1], &self.expressions2[e2]) {
+        let mut pending = vec![(e1, e2)];
+        while let Option::Some((e1, e2)) = pending.pop() {
+            let (e1, e2) = (&self.expressions1[e1], &self.expressions2[e2]);
+            if !Equality::equals_nodes(e1, e2) {
+                return false;
+            }
+            pending.extend(e1.children().into_iter().zip(e2.children()).rev());
+        }
+        true
+    }
+
+    /// Compares the expressions `e1` and `e2` without their sub-expressions,
+    /// which must then be equal in number.
+    fn equals_nodes(e1: &Expression, e2: &Expression) -> bool {
+        match (e1, e2) {
             (Expression::Variable { identifier: i1 }, Expression::Variable { identifier: i2 }) => {
                 i1 == i2
             }
@@ -34,45 +48,157 @@ impl<'a> Equality<'a> {
                 Expression::NamelessVariable { index: i1 },
                 Expression::NamelessVariable { index: i2 },
             ) => i1 == i2,
+            (Expression::Constant { constant: c1 }, Expression::Constant { constant: c2 }) => {
+                c1 == c2
+            }
             (
                 Expression::Abstraction {
                     parameter: param1,
-                    body: b1,
+                    annotation: t1,
+                    body: _,
                 },
                 Expression::Abstraction {
                     parameter: param2,
-                    body: b2,
+                    annotation: t2,
+                    body: _,
                 },
-            ) => param1 == param2 && self.equals(*b1, *b2),
+            ) => param1 == param2 && t1.is_some() == t2.is_some(),
             (
-                Expression::NamelessAbstraction { body: b1 },
-                Expression::NamelessAbstraction { body: b2 },
-            ) => self.equals(*b1, *b2),
+                Expression::NamelessAbstraction {
+                    annotation: t1,
+                    body: _,
+                },
+                Expression::NamelessAbstraction {
+                    annotation: t2,
+                    body: _,
+                },
+            ) => t1.is_some() == t2.is_some(),
             (
                 Expression::Application {
-                    function: f1,
+                    function: _,
                     arguments: as1,
                 },
                 Expression::Application {
-                    function: f2,
+                    function: _,
                     arguments: as2,
                 },
+            ) => as1.len() == as2.len(),
+            (
+                Expression::Let {
+                    parameter: param1,
+                    definition: _,
+                    body: _,
+                },
+                Expression::Let {
+                    parameter: param2,
+                    definition: _,
+                    body: _,
+                },
+            ) => param1 == param2,
+            (
+                Expression::NamelessLet {
+                    definition: _,
+                    body: _,
+                },
+                Expression::NamelessLet {
+                    definition: _,
+                    body: _,
+                },
+            ) => true,
+            (
+                Expression::LetRec {
+                    parameters: params1,
+                    definitions: ds1,
+                    body: _,
+                },
+                Expression::LetRec {
+                    parameters: params2,
+                    definitions: ds2,
+                    body: _,
+                },
+            ) => params1 == params2 && ds1.len() == ds2.len(),
+            (
+                Expression::NamelessLetRec {
+                    definitions: ds1,
+                    body: _,
+                },
+                Expression::NamelessLetRec {
+                    definitions: ds2,
+                    body: _,
+                },
+            ) => ds1.len() == ds2.len(),
+            (
+                Expression::Pi {
+                    parameter: param1,
+                    domain: _,
+                    codomain: _,
+                },
+                Expression::Pi {
+                    parameter: param2,
+                    domain: _,
+                    codomain: _,
+                },
+            ) => param1 == param2,
+            (
+                Expression::NamelessPi {
+                    domain: _,
+                    codomain: _,
+                },
+                Expression::NamelessPi {
+                    domain: _,
+                    codomain: _,
+                },
+            ) => true,
+            (
+                Expression::Case {
+                    scrutinee: _,
+                    branches: bs1,
+                },
+                Expression::Case {
+                    scrutinee: _,
+                    branches: bs2,
+                },
             ) => {
-                if as1.len() != as2.len() || !self.equals(*f1, *f2) {
-                    false
-                } else {
-                    for (&a1, &a2) in as1.iter().zip(as2.iter()) {
-                        if !self.equals(a1, a2) {
-                            return false;
-                        }
-                    }
-                    true
-                }
+                bs1.len() == bs2.len()
+                    && bs1
+                        .iter()
+                        .zip(bs2.iter())
+                        .all(|(b1, b2)| Equality::equals_patterns(b1, b2))
             }
             _ => false,
         }
     }
 
+    fn equals_patterns(b1: &CaseBranch, b2: &CaseBranch) -> bool {
+        match (b1, b2) {
+            (
+                CaseBranch::Named {
+                    constructor: c1,
+                    parameters: params1,
+                    body: _,
+                },
+                CaseBranch::Named {
+                    constructor: c2,
+                    parameters: params2,
+                    body: _,
+                },
+            ) => c1 == c2 && params1 == params2,
+            (
+                CaseBranch::Nameless {
+                    constructor: c1,
+                    arity: a1,
+                    body: _,
+                },
+                CaseBranch::Nameless {
+                    constructor: c2,
+                    arity: a2,
+                    body: _,
+                },
+            ) => c1 == c2 && a1 == a2,
+            _ => false,
+        }
+    }
+
     pub fn check_equality(self, e1: ExpressionId, e2: ExpressionId) -> bool {
         self.equals(e1, e2)
     }
@@ -137,6 +263,30 @@ mod tests {
         check_expression_equality("λ. x", "λ. x", true);
         check_expression_equality("λ. λ. 2 1", "λ. λ. 2 1", true);
         check_expression_equality("λ. λ. λ. 3 1 (2 1)", "λ. λ. λ. 3 1 (2 1)", true);
+        check_expression_equality("let x = y in x", "let x = y in x", true);
+        check_expression_equality("let _ = y in x", "let _ = y in x", true);
+        check_expression_equality("let = y in 1", "let = y in 1", true);
+        check_expression_equality("λx : T. x", "λx : T. x", true);
+        check_expression_equality("λ : T. 1", "λ : T. 1", true);
+        check_expression_equality("A → B", "Π(_ : A). B", true);
+        check_expression_equality("Π(x : A). x", "Π(x : A). x", true);
+        check_expression_equality("Π(: A). 1", "Π(: A). 1", true);
+        check_expression_equality("case p of {}", "case p of {}", true);
+        check_expression_equality("#42", "#42", true);
+        check_expression_equality(
+            "letrec f = g 1 and g = f in f",
+            "letrec f = g 1 and g = f in f",
+            true,
+        );
+        check_expression_equality("letrec = 1 and = 2 in 1", "letrec = 1 and = 2 in 1", true);
+        check_expression_equality("#-3", "#-3", true);
+        check_expression_equality("\"a\\\"b\"", "\"a\\\"b\"", true);
+        check_expression_equality("f true false", "f true false", true);
+        check_expression_equality(
+            "case p of { pair a b => a | nil/0 => x }",
+            "case p of { pair a b => a | nil/0 => x }",
+            true,
+        );
 
         check_expression_equality("x", "y", false);
         check_expression_equality("x", "λf. y", false);
@@ -151,5 +301,31 @@ mod tests {
         check_expression_equality("λ. x", "λ. y", false);
         check_expression_equality("λ. λ. 2 1", "λ. λ. 1 2", false);
         check_expression_equality("λ. λ. λ. 3 1 (2 1)", "λ. λ. λ. 3 1 (1 2)", false);
+        check_expression_equality("let x = y in x", "let z = y in z", false);
+        check_expression_equality("let x = y in x", "let x = z in x", false);
+        check_expression_equality("let x = y in x", "(λx. x) y", false);
+        check_expression_equality("let = y in 1", "let _ = y in 1", false);
+        check_expression_equality("λx : T. x", "λx. x", false);
+        check_expression_equality("λx : T. x", "λx : U. x", false);
+        check_expression_equality("λ : T. 1", "λ. 1", false);
+        check_expression_equality("A → B", "B → A", false);
+        check_expression_equality("Π(x : A). x", "Π(y : A). y", false);
+        check_expression_equality("Π(: A). 1", "Π(_ : A). 1", false);
+        check_expression_equality("Π(x : A). B", "λx : A. B", false);
+        check_expression_equality("case p of {}", "case q of {}", false);
+        check_expression_equality("#1", "1", false);
+        check_expression_equality("letrec f = f in f", "letrec g = g in g", false);
+        check_expression_equality("letrec f = f in f", "letrec f = f and g = g in f", false);
+        check_expression_equality("letrec = 1 in 1", "letrec = 1 and = 1 in 1", false);
+        check_expression_equality("letrec f = x in f", "let f = x in f", false);
+        check_expression_equality("#1", "#2", false);
+        check_expression_equality("\"x\"", "x", false);
+        check_expression_equality("\"1\"", "#1", false);
+        check_expression_equality("true", "false", false);
+        check_expression_equality("case p of { c => x }", "case p of {}", false);
+        check_expression_equality("case p of { c => x }", "case p of { d => x }", false);
+        check_expression_equality("case p of { c a => a }", "case p of { c b => b }", false);
+        check_expression_equality("case p of { c a => 1 }", "case p of { c/1 => 1 }", false);
+        check_expression_equality("case p of { c/1 => 1 }", "case p of { c/2 => 1 }", false);
     }
 }
@@ -0,0 +1,291 @@
+use std::rc::Rc;
+
+use crate::{
+    admissible_variable_name_generator::AdmissibleVariableNameGenerator,
+    expression::{DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+    referencing_environment::ReferencingEnvironment,
+    strings::{StringArena, StringId},
+    visitor::{Rewrite, Scoping, Visitor},
+};
+
+impl Expression {
+    /// Constructs the eta-expansion `λx. e x` of `expression` as `e`, where
+    /// the parameter `x` is generated using `variable_name_generator` so as
+    /// not to capture the free variables of `e`. The nameless variables of
+    /// `e` referring outside of it are shifted past the new binder.
+    pub fn eta_expand<G: AdmissibleVariableNameGenerator>(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+        mut variable_name_generator: G,
+    ) -> ExpressionId {
+        let free_variables = Expression::free_variables(
+            Rc::new(ReferencingEnvironment::new()),
+            expressions,
+            expression,
+        );
+        let parameter = variable_name_generator
+            .generate_admissible_name(strings, |name| !free_variables.contains(&name));
+        let function = Expression::shift(expressions, expression, 1, 0);
+        let argument = expressions.variable(parameter);
+        let body = expressions.application(function, vec![argument]);
+        expressions.abstraction(Option::Some(parameter), body)
+    }
+
+    /// Contracts `expression` if it is an eta-redex `λx. f x`, where `x` does
+    /// not occur in `f`. The argument `x` may be named or nameless, and the
+    /// nameless variables of `f` referring outside of it are shifted back
+    /// past the removed binder. Returns `None` if `expression` is not an
+    /// eta-redex.
+    pub fn eta_reduce(
+        expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+    ) -> Option<ExpressionId> {
+        let (parameter, body) = match expressions[expression] {
+            Expression::Abstraction {
+                parameter,
+                annotation: _,
+                body,
+            } => (parameter, body),
+            Expression::NamelessAbstraction {
+                annotation: _,
+                body,
+            } => (Option::None, body),
+            _ => return Option::None,
+        };
+        let (function, arguments) = match &expressions[body] {
+            Expression::Application {
+                function,
+                arguments,
+            } => (*function, arguments.clone()),
+            _ => return Option::None,
+        };
+        let (&argument, remaining) = arguments.split_last()?;
+        let is_parameter = match expressions[argument] {
+            Expression::Variable { identifier } => parameter == Option::Some(identifier),
+            Expression::NamelessVariable { index } => index.into_usize() == 1,
+            _ => false,
+        };
+        if !is_parameter
+            || std::iter::once(&function)
+                .chain(remaining)
+                .any(|&e| Occurrence::new(expressions, parameter).occurs(e))
+        {
+            return Option::None;
+        }
+        let function = Expression::shift(expressions, function, -1, 0);
+        if remaining.is_empty() {
+            Option::Some(function)
+        } else {
+            let remaining = remaining
+                .iter()
+                .map(|&argument| Expression::shift(expressions, argument, -1, 0))
+                .collect();
+            Option::Some(expressions.application(function, remaining))
+        }
+    }
+
+    /// Contracts every eta-redex of `expression`, including the ones created
+    /// by contracting others, like `λx y. f x y` to `f`.
+    pub fn eta_normalize(
+        expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+    ) -> ExpressionId {
+        EtaNormalization.rewrite_expression(expressions, expression)
+    }
+}
+
+/// Contracts eta-redexes bottom-up. The sub-expressions of a contracted
+/// eta-redex are already normalized, so each expression is contracted at
+/// most once.
+struct EtaNormalization;
+
+impl Scoping for EtaNormalization {}
+
+impl Rewrite for EtaNormalization {
+    fn exit_expression(
+        &mut self,
+        expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+    ) -> ExpressionId {
+        Expression::eta_reduce(expressions, expression).unwrap_or(expression)
+    }
+}
+
+/// Checks whether the variable bound by an enclosing binder with `parameter`
+/// occurs in an expression, either by name or by index.
+struct Occurrence<'a> {
+    expressions: &'a ExpressionArena,
+    parameter: Option<StringId>,
+    binders: usize,
+    /// The number of binders in scope that shadow `parameter`.
+    shadows: usize,
+    occurs: bool,
+}
+
+impl<'a> Occurrence<'a> {
+    fn new(expressions: &'a ExpressionArena, parameter: Option<StringId>) -> Occurrence<'a> {
+        Occurrence {
+            expressions,
+            parameter,
+            binders: 0,
+            shadows: 0,
+            occurs: false,
+        }
+    }
+
+    fn occurs(mut self, expression: ExpressionId) -> bool {
+        self.visit_expression(expression);
+        self.occurs
+    }
+}
+
+impl Scoping for Occurrence<'_> {
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders += parameters.len();
+        if self.parameter.is_some() && parameters.contains(&self.parameter) {
+            self.shadows += 1;
+        }
+    }
+
+    fn exit_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders -= parameters.len();
+        if self.parameter.is_some() && parameters.contains(&self.parameter) {
+            self.shadows -= 1;
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Occurrence<'a> {
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn enter_expression(
+        &mut self,
+        _parent: Option<ExpressionId>,
+        _expression: ExpressionId,
+    ) -> bool {
+        !self.occurs
+    }
+
+    fn visit_variable(&mut self, _expression: ExpressionId, identifier: StringId) {
+        if self.parameter == Option::Some(identifier) && self.shadows == 0 {
+            self.occurs = true;
+        }
+    }
+
+    fn visit_nameless_variable(&mut self, _expression: ExpressionId, index: DeBruijnIndex) {
+        if index.into_usize() == self.binders + 1 {
+            self.occurs = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admissible_variable_name_generator::VariableNameGenerator;
+
+    use super::*;
+
+    fn parse(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        input: &str,
+    ) -> ExpressionId {
+        Expression::parse_mixed_expression(strings, expressions, input.as_bytes()).unwrap()
+    }
+
+    fn check_eta_expand(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression = parse(&mut strings, &mut expressions, input);
+        let expected = parse(&mut strings, &mut expressions, expected);
+
+        let expanded = Expression::eta_expand(
+            &mut strings,
+            &mut expressions,
+            expression,
+            VariableNameGenerator::new(),
+        );
+        assert!(Expression::equals(
+            (&expressions, expanded),
+            (&expressions, expected)
+        ));
+
+        let reduced = Expression::eta_reduce(&mut expressions, expanded).unwrap();
+        assert!(Expression::equals(
+            (&expressions, reduced),
+            (&expressions, expression)
+        ));
+    }
+
+    fn check_eta_normalize(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression = parse(&mut strings, &mut expressions, input);
+        let expected = parse(&mut strings, &mut expressions, expected);
+
+        let normalized = Expression::eta_normalize(&mut expressions, expression);
+        assert!(Expression::equals(
+            (&expressions, normalized),
+            (&expressions, expected)
+        ));
+    }
+
+    fn check_eta_reduce(input: &str, expected: Option<&str>) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression = parse(&mut strings, &mut expressions, input);
+        let reduced = Expression::eta_reduce(&mut expressions, expression);
+        match expected {
+            Option::Some(expected) => {
+                let expected = parse(&mut strings, &mut expressions, expected);
+                assert!(Expression::equals(
+                    (&expressions, reduced.unwrap()),
+                    (&expressions, expected)
+                ));
+            }
+            Option::None => assert_eq!(reduced, Option::None),
+        }
+    }
+
+    #[test]
+    fn eta_expand_avoids_free_variables() {
+        check_eta_expand("f", "λx. f x");
+        check_eta_expand("x", "λy. x y");
+        check_eta_expand("f x y", "λz. (f x y) z");
+        check_eta_expand("λx. x y", "λx. (λx. x y) x");
+        check_eta_expand("g 1", "λx. (g 2) x");
+        check_eta_expand("λ. 1 2", "λx. (λ. 1 3) x");
+    }
+
+    #[test]
+    fn eta_reduce_contracts_eta_redexes() {
+        check_eta_reduce("λx. f x", Option::Some("f"));
+        check_eta_reduce("λx. f y x", Option::Some("f y"));
+        check_eta_reduce("λx. f 1", Option::Some("f"));
+        check_eta_reduce("λ. f 1", Option::Some("f"));
+        check_eta_reduce("λ. 2 1", Option::Some("1"));
+        check_eta_reduce("λ : T. g 3 1", Option::Some("g 2"));
+        check_eta_reduce("λx. (λx. x) x", Option::Some("λx. x"));
+        check_eta_reduce("λx. (λ. 2) x", Option::None);
+        check_eta_reduce("λx. f x x", Option::None);
+        check_eta_reduce("λx. x x", Option::None);
+        check_eta_reduce("λ. f x", Option::None);
+        check_eta_reduce("λx. f", Option::None);
+        check_eta_reduce("f x", Option::None);
+    }
+
+    #[test]
+    fn eta_normalize_contracts_all_eta_redexes() {
+        check_eta_normalize("λx y. f x y", "f");
+        check_eta_normalize("λx. f (λy. g y) x", "f g");
+        check_eta_normalize("λx. λ. 3 2 1", "1");
+        check_eta_normalize("λx y. f y x", "λx y. f y x");
+        check_eta_normalize("g (λx. x)", "g (λx. x)");
+    }
+}
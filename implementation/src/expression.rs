@@ -25,7 +25,11 @@ pub struct ExpressionId {
 /// abstract syntax trees.
 ///
 /// These expressions may contain named (free or bound) and nameless variables,
-/// named and nameless lambda abstractions, and applications.
+/// literal constants, named and nameless lambda abstractions, applications,
+/// named and nameless let-bindings, named and nameless recursive let-bindings,
+/// named and nameless dependent function types, and case expressions. Lambda
+/// abstractions may have their parameter annotated with a type, which is an
+/// expression outside of the scope of that parameter.
 ///
 /// To support annotating expressions with auxiliary data, expressions are
 /// constructed and stored in [expression arenas](ExpressionArena). As such,
@@ -38,17 +42,85 @@ pub enum Expression {
     NamelessVariable {
         index: DeBruijnIndex,
     },
+    Constant {
+        constant: Constant,
+    },
     Abstraction {
         parameter: Option<StringId>,
+        annotation: Option<ExpressionId>,
         body: ExpressionId,
     },
     NamelessAbstraction {
+        annotation: Option<ExpressionId>,
         body: ExpressionId,
     },
     Application {
         function: ExpressionId,
         arguments: Vec<ExpressionId>,
     },
+    Let {
+        parameter: Option<StringId>,
+        definition: ExpressionId,
+        body: ExpressionId,
+    },
+    NamelessLet {
+        definition: ExpressionId,
+        body: ExpressionId,
+    },
+    LetRec {
+        parameters: Vec<Option<StringId>>,
+        definitions: Vec<ExpressionId>,
+        body: ExpressionId,
+    },
+    NamelessLetRec {
+        definitions: Vec<ExpressionId>,
+        body: ExpressionId,
+    },
+    Pi {
+        parameter: Option<StringId>,
+        domain: ExpressionId,
+        codomain: ExpressionId,
+    },
+    NamelessPi {
+        domain: ExpressionId,
+        codomain: ExpressionId,
+    },
+    Case {
+        scrutinee: ExpressionId,
+        branches: Vec<CaseBranch>,
+    },
+}
+
+/// Literal constants in [expressions](Expression). String literals are
+/// interned in [string arenas](crate::strings::StringArena), and never refer
+/// to variables.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Constant {
+    Integer(i64),
+    String(StringId),
+    Boolean(bool),
+}
+
+/// Branches of [case expressions](Expression::Case). A branch matches values
+/// constructed with its constructor, and binds the constructor's arguments in
+/// its body.
+///
+/// Pattern variables are bound in order, such that the last pattern variable
+/// is the innermost binder. In a nameless branch, the last pattern variable is
+/// therefore referred to by the de Bruijn index 1, and the first one by the
+/// index equal to the branch's arity.
+#[derive(Debug)]
+pub enum CaseBranch {
+    Named {
+        constructor: StringId,
+        parameters: Vec<Option<StringId>>,
+        body: ExpressionId,
+    },
+    Nameless {
+        constructor: StringId,
+        arity: usize,
+        body: ExpressionId,
+    },
 }
 
 impl DeBruijnIndex {
@@ -58,6 +130,13 @@ impl DeBruijnIndex {
         DeBruijnIndex { index }
     }
 
+    /// Constructs the index `index` without checking that it is positive, as
+    /// for indices parsed from input that is yet to be scope checked.
+    #[inline]
+    pub(crate) fn new_unchecked(index: usize) -> DeBruijnIndex {
+        DeBruijnIndex { index }
+    }
+
     #[inline]
     pub fn into_usize(self) -> usize {
         self.index
@@ -78,6 +157,42 @@ impl From<DeBruijnIndex> for usize {
     }
 }
 
+impl CaseBranch {
+    #[inline]
+    pub fn constructor(&self) -> StringId {
+        match self {
+            CaseBranch::Named { constructor, .. } => *constructor,
+            CaseBranch::Nameless { constructor, .. } => *constructor,
+        }
+    }
+
+    /// The number of pattern variables bound by this branch.
+    #[inline]
+    pub fn arity(&self) -> usize {
+        match self {
+            CaseBranch::Named { parameters, .. } => parameters.len(),
+            CaseBranch::Nameless { arity, .. } => *arity,
+        }
+    }
+
+    #[inline]
+    pub fn body(&self) -> ExpressionId {
+        match self {
+            CaseBranch::Named { body, .. } => *body,
+            CaseBranch::Nameless { body, .. } => *body,
+        }
+    }
+
+    /// The pattern variables bound by this branch, in binding order. The
+    /// pattern variables of a nameless branch are all omitted.
+    pub fn parameters(&self) -> Vec<Option<StringId>> {
+        match self {
+            CaseBranch::Named { parameters, .. } => parameters.clone(),
+            CaseBranch::Nameless { arity, .. } => vec![Option::None; *arity],
+        }
+    }
+}
+
 impl ExpressionId {
     #[inline]
     pub fn new(index: usize) -> ExpressionId {
@@ -153,18 +268,53 @@ impl ExpressionArena {
         self.add(Expression::NamelessVariable { index })
     }
 
+    /// Constructs a literal constant and adds it to the expression arena.
+    #[inline]
+    pub fn constant(&mut self, constant: Constant) -> ExpressionId {
+        self.add(Expression::Constant { constant })
+    }
+
     /// Constructs a lambda abstraction with the given parameter and body
     /// expression and adds it to the expression arena.
     #[inline]
     pub fn abstraction(&mut self, parameter: Option<StringId>, body: ExpressionId) -> ExpressionId {
-        self.add(Expression::Abstraction { parameter, body })
+        self.annotated_abstraction(parameter, Option::None, body)
+    }
+
+    /// Constructs a lambda abstraction with the given parameter, optional
+    /// parameter type annotation and body expression and adds it to the
+    /// expression arena.
+    #[inline]
+    pub fn annotated_abstraction(
+        &mut self,
+        parameter: Option<StringId>,
+        annotation: Option<ExpressionId>,
+        body: ExpressionId,
+    ) -> ExpressionId {
+        self.add(Expression::Abstraction {
+            parameter,
+            annotation,
+            body,
+        })
     }
 
     /// Constructs a nameless lambda abstraction with the given body expression
     /// and adds it to the expression arena.
     #[inline]
     pub fn nameless_abstraction(&mut self, body: ExpressionId) -> ExpressionId {
-        self.add(Expression::NamelessAbstraction { body })
+        self.annotated_nameless_abstraction(Option::None, body)
+    }
+
+    /// Constructs a nameless lambda abstraction with the given optional
+    /// parameter type annotation and body expression and adds it to the
+    /// expression arena.
+    #[inline]
+    pub fn annotated_nameless_abstraction(
+        &mut self,
+        annotation: Option<ExpressionId>,
+        body: ExpressionId,
+    ) -> ExpressionId {
+        self.add(Expression::NamelessAbstraction { annotation, body })
     }
 
     /// Constructs an application with the given function and arguments
@@ -181,6 +331,100 @@ impl ExpressionArena {
             arguments,
         })
     }
+
+    /// Constructs a let-binding with the given parameter, definition and body
+    /// expressions and adds it to the expression arena. The parameter is bound
+    /// in the body, but not in the definition.
+    #[inline]
+    pub fn let_binding(
+        &mut self,
+        parameter: Option<StringId>,
+        definition: ExpressionId,
+        body: ExpressionId,
+    ) -> ExpressionId {
+        self.add(Expression::Let {
+            parameter,
+            definition,
+            body,
+        })
+    }
+
+    /// Constructs a nameless let-binding with the given definition and body
+    /// expressions and adds it to the expression arena.
+    #[inline]
+    pub fn nameless_let_binding(
+        &mut self,
+        definition: ExpressionId,
+        body: ExpressionId,
+    ) -> ExpressionId {
+        self.add(Expression::NamelessLet { definition, body })
+    }
+
+    /// Constructs a recursive let-binding group with the given parameters,
+    /// definition expressions and body expression and adds it to the
+    /// expression arena. The parameters are bound in every definition and in
+    /// the body, with the last parameter as the innermost binder.
+    #[inline]
+    pub fn let_rec(
+        &mut self,
+        parameters: Vec<Option<StringId>>,
+        definitions: Vec<ExpressionId>,
+        body: ExpressionId,
+    ) -> ExpressionId {
+        debug_assert_eq!(parameters.len(), definitions.len());
+        self.add(Expression::LetRec {
+            parameters,
+            definitions,
+            body,
+        })
+    }
+
+    /// Constructs a nameless recursive let-binding group with the given
+    /// definition expressions and body expression and adds it to the
+    /// expression arena. The last definition is referred to by the de Bruijn
+    /// index 1 in every definition and in the body.
+    #[inline]
+    pub fn nameless_let_rec(
+        &mut self,
+        definitions: Vec<ExpressionId>,
+        body: ExpressionId,
+    ) -> ExpressionId {
+        self.add(Expression::NamelessLetRec { definitions, body })
+    }
+
+    /// Constructs a dependent function type with the given parameter, domain
+    /// and codomain expressions and adds it to the expression arena. The
+    /// parameter is bound in the codomain, but not in the domain.
+    #[inline]
+    pub fn pi(
+        &mut self,
+        parameter: Option<StringId>,
+        domain: ExpressionId,
+        codomain: ExpressionId,
+    ) -> ExpressionId {
+        self.add(Expression::Pi {
+            parameter,
+            domain,
+            codomain,
+        })
+    }
+
+    /// Constructs a nameless dependent function type with the given domain and
+    /// codomain expressions and adds it to the expression arena.
+    #[inline]
+    pub fn nameless_pi(&mut self, domain: ExpressionId, codomain: ExpressionId) -> ExpressionId {
+        self.add(Expression::NamelessPi { domain, codomain })
+    }
+
+    /// Constructs a case expression with the given scrutinee expression and
+    /// branches and adds it to the expression arena.
+    #[inline]
+    pub fn case(&mut self, scrutinee: ExpressionId, branches: Vec<CaseBranch>) -> ExpressionId {
+        self.add(Expression::Case {
+            scrutinee,
+            branches,
+        })
+    }
 }
 
 impl Default for ExpressionArena {
@@ -205,6 +449,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::len_zero)]
     fn arena_is_initially_empty() {
         let expressions = ExpressionArena::default();
         assert!(expressions.len() == 0);
@@ -261,11 +506,18 @@ mod tests {
             expressions[f],
             Expression::Abstraction {
                 parameter: _,
+                annotation: _,
                 body: _
             }
         ));
-        if let Expression::Abstraction { parameter, body } = expressions[f] {
+        if let Expression::Abstraction {
+            parameter,
+            annotation,
+            body,
+        } = expressions[f]
+        {
             assert!(parameter == Option::Some(x));
+            assert!(annotation.is_none());
             assert!(body == vx);
         }
     }
@@ -280,9 +532,46 @@ mod tests {
         assert!(expressions.has(f));
         assert!(matches!(
             expressions[f],
-            Expression::NamelessAbstraction { body: _ }
+            Expression::NamelessAbstraction {
+                annotation: _,
+                body: _
+            }
         ));
-        if let Expression::NamelessAbstraction { body } = expressions[f] {
+        if let Expression::NamelessAbstraction { annotation, body } = expressions[f] {
+            assert!(annotation.is_none());
+            assert!(body == vx);
+        }
+    }
+
+    #[test]
+    fn arena_annotated_abstraction_creates_annotated_abstraction() {
+        let mut strings = StringArena::new();
+        let x = strings.intern_str("x");
+        let t = strings.intern_str("T");
+
+        let mut expressions = ExpressionArena::new();
+
+        let vt = expressions.variable(t);
+        let vx = expressions.variable(x);
+        let f = expressions.annotated_abstraction(Option::Some(x), Option::Some(vt), vx);
+
+        assert!(expressions.has(f));
+        assert!(matches!(
+            expressions[f],
+            Expression::Abstraction {
+                parameter: _,
+                annotation: _,
+                body: _
+            }
+        ));
+        if let Expression::Abstraction {
+            parameter,
+            annotation,
+            body,
+        } = expressions[f]
+        {
+            assert!(parameter == Option::Some(x));
+            assert!(annotation == Option::Some(vt));
             assert!(body == vx);
         }
     }
@@ -318,4 +607,256 @@ mod tests {
             assert!(*arguments == vec![vx, vy]);
         }
     }
+
+    #[test]
+    fn arena_let_binding_creates_let_binding() {
+        let mut strings = StringArena::new();
+        let x = strings.intern_str("x");
+        let y = strings.intern_str("y");
+
+        let mut expressions = ExpressionArena::new();
+
+        let vy = expressions.variable(y);
+        let vx = expressions.variable(x);
+        let l = expressions.let_binding(Option::Some(x), vy, vx);
+
+        assert!(expressions.has(l));
+        assert!(matches!(
+            expressions[l],
+            Expression::Let {
+                parameter: _,
+                definition: _,
+                body: _
+            }
+        ));
+        if let Expression::Let {
+            parameter,
+            definition,
+            body,
+        } = expressions[l]
+        {
+            assert!(parameter == Option::Some(x));
+            assert!(definition == vy);
+            assert!(body == vx);
+        }
+    }
+
+    #[test]
+    fn arena_nameless_let_binding_creates_nameless_let_binding() {
+        let mut strings = StringArena::new();
+        let y = strings.intern_str("y");
+
+        let mut expressions = ExpressionArena::new();
+
+        let vy = expressions.variable(y);
+        let vx = expressions.nameless_variable(1.into());
+        let l = expressions.nameless_let_binding(vy, vx);
+
+        assert!(expressions.has(l));
+        assert!(matches!(
+            expressions[l],
+            Expression::NamelessLet {
+                definition: _,
+                body: _
+            }
+        ));
+        if let Expression::NamelessLet { definition, body } = expressions[l] {
+            assert!(definition == vy);
+            assert!(body == vx);
+        }
+    }
+
+    #[test]
+    fn arena_let_rec_creates_let_rec() {
+        let mut strings = StringArena::new();
+        let f = strings.intern_str("f");
+        let g = strings.intern_str("g");
+
+        let mut expressions = ExpressionArena::new();
+
+        let vf = expressions.variable(f);
+        let vg = expressions.variable(g);
+        let l = expressions.let_rec(vec![Option::Some(f), Option::Some(g)], vec![vg, vf], vf);
+
+        assert!(expressions.has(l));
+        if let Expression::LetRec {
+            ref parameters,
+            ref definitions,
+            body,
+        } = expressions[l]
+        {
+            assert!(*parameters == vec![Option::Some(f), Option::Some(g)]);
+            assert!(*definitions == vec![vg, vf]);
+            assert!(body == vf);
+        } else {
+            panic!("expected a recursive let-binding");
+        }
+    }
+
+    #[test]
+    fn arena_nameless_let_rec_creates_nameless_let_rec() {
+        let mut expressions = ExpressionArena::new();
+
+        let v1 = expressions.nameless_variable(1.into());
+        let v2 = expressions.nameless_variable(2.into());
+        let l = expressions.nameless_let_rec(vec![v1, v2], v2);
+
+        assert!(expressions.has(l));
+        if let Expression::NamelessLetRec {
+            ref definitions,
+            body,
+        } = expressions[l]
+        {
+            assert!(*definitions == vec![v1, v2]);
+            assert!(body == v2);
+        } else {
+            panic!("expected a nameless recursive let-binding");
+        }
+    }
+
+    #[test]
+    fn arena_pi_creates_pi() {
+        let mut strings = StringArena::new();
+        let x = strings.intern_str("x");
+        let a = strings.intern_str("A");
+
+        let mut expressions = ExpressionArena::new();
+
+        let va = expressions.variable(a);
+        let vx = expressions.variable(x);
+        let p = expressions.pi(Option::Some(x), va, vx);
+
+        assert!(expressions.has(p));
+        assert!(matches!(
+            expressions[p],
+            Expression::Pi {
+                parameter: _,
+                domain: _,
+                codomain: _
+            }
+        ));
+        if let Expression::Pi {
+            parameter,
+            domain,
+            codomain,
+        } = expressions[p]
+        {
+            assert!(parameter == Option::Some(x));
+            assert!(domain == va);
+            assert!(codomain == vx);
+        }
+    }
+
+    #[test]
+    fn arena_nameless_pi_creates_nameless_pi() {
+        let mut strings = StringArena::new();
+        let a = strings.intern_str("A");
+
+        let mut expressions = ExpressionArena::new();
+
+        let va = expressions.variable(a);
+        let vx = expressions.nameless_variable(1.into());
+        let p = expressions.nameless_pi(va, vx);
+
+        assert!(expressions.has(p));
+        assert!(matches!(
+            expressions[p],
+            Expression::NamelessPi {
+                domain: _,
+                codomain: _
+            }
+        ));
+        if let Expression::NamelessPi { domain, codomain } = expressions[p] {
+            assert!(domain == va);
+            assert!(codomain == vx);
+        }
+    }
+
+    #[test]
+    fn arena_case_creates_case() {
+        let mut strings = StringArena::new();
+        let pair = strings.intern_str("pair");
+        let nil = strings.intern_str("nil");
+        let a = strings.intern_str("a");
+        let p = strings.intern_str("p");
+
+        let mut expressions = ExpressionArena::new();
+
+        let vp = expressions.variable(p);
+        let va = expressions.variable(a);
+        let v1 = expressions.nameless_variable(1.into());
+        let c = expressions.case(
+            vp,
+            vec![
+                CaseBranch::Named {
+                    constructor: pair,
+                    parameters: vec![Option::Some(a), Option::None],
+                    body: va,
+                },
+                CaseBranch::Nameless {
+                    constructor: nil,
+                    arity: 1,
+                    body: v1,
+                },
+            ],
+        );
+
+        assert!(expressions.has(c));
+        assert!(matches!(
+            expressions[c],
+            Expression::Case {
+                scrutinee: _,
+                branches: _
+            }
+        ));
+        if let Expression::Case {
+            scrutinee,
+            ref branches,
+        } = expressions[c]
+        {
+            assert!(scrutinee == vp);
+            assert!(branches.len() == 2);
+            assert!(branches[0].constructor() == pair);
+            assert!(branches[0].parameters() == vec![Option::Some(a), Option::None]);
+            assert!(branches[0].body() == va);
+            assert!(branches[1].constructor() == nil);
+            assert!(branches[1].arity() == 1);
+            assert!(branches[1].parameters() == vec![Option::None]);
+            assert!(branches[1].body() == v1);
+        }
+    }
+
+    #[test]
+    fn arena_constant_creates_constant() {
+        let mut strings = StringArena::new();
+        let hello = strings.intern_str("hello");
+
+        let mut expressions = ExpressionArena::new();
+
+        let n = expressions.constant(Constant::Integer(-3));
+        let s = expressions.constant(Constant::String(hello));
+        let b = expressions.constant(Constant::Boolean(true));
+
+        assert!(expressions.has(n));
+        assert!(expressions.has(s));
+        assert!(expressions.has(b));
+        assert!(matches!(
+            expressions[n],
+            Expression::Constant {
+                constant: Constant::Integer(-3)
+            }
+        ));
+        assert!(matches!(
+            expressions[s],
+            Expression::Constant {
+                constant: Constant::String(string)
+            } if string == hello
+        ));
+        assert!(matches!(
+            expressions[b],
+            Expression::Constant {
+                constant: Constant::Boolean(true)
+            }
+        ));
+    }
 }
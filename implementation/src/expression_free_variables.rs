@@ -4,6 +4,7 @@ use crate::{
     expression::{Expression, ExpressionArena, ExpressionId},
     referencing_environment::ReferencingEnvironment,
     strings::StringId,
+    visitor::{Scoping, Visitor},
 };
 
 impl Expression {
@@ -35,49 +36,30 @@ impl<'a> FreeVariables<'a> {
         }
     }
 
-    fn visit(&mut self, expression: ExpressionId) {
-        match &self.expressions[expression] {
-            Expression::Variable { identifier } => {
-                if self.environment.lookup(*identifier).is_none() {
-                    self.free_variables.insert(*identifier);
-                }
-            }
-            Expression::NamelessVariable { index: _ } => {}
-            Expression::Abstraction { parameter, body } => match parameter {
-                Option::Some(parameter) => {
-                    self.environment.bind(*parameter);
-                    self.visit(*body);
-                    self.environment.unbind(*parameter);
-                }
-                Option::None => {
-                    self.environment.shift();
-                    self.visit(*body);
-                    self.environment.unshift();
-                }
-            },
-            Expression::NamelessAbstraction { body } => {
-                self.environment.shift();
-                self.visit(*body);
-                self.environment.unshift();
-            }
-            Expression::Application {
-                function,
-                arguments,
-            } => {
-                self.visit(*function);
-                for &argument in arguments {
-                    self.visit(argument);
-                }
-            }
-        }
-    }
-
     pub fn free_variables(mut self, expression: ExpressionId) -> HashSet<StringId> {
-        self.visit(expression);
+        self.visit_expression(expression);
         self.free_variables
     }
 }
 
+impl Scoping for FreeVariables<'_> {
+    fn environment(&mut self) -> Option<&mut ReferencingEnvironment> {
+        Option::Some(self.environment)
+    }
+}
+
+impl<'a> Visitor<'a> for FreeVariables<'a> {
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn visit_variable(&mut self, _expression: ExpressionId, identifier: StringId) {
+        if self.environment.lookup(identifier).is_none() {
+            self.free_variables.insert(identifier);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -112,5 +94,23 @@ mod tests {
         check_free_variables("λf. λx. f x", vec![]);
         check_free_variables("λx. λy. λz. x z (y z)", vec![]);
         check_free_variables("λf. x1 x2 x3", vec!["x1", "x2", "x3"]);
+        check_free_variables("let x = y in x", vec!["y"]);
+        check_free_variables("let x = x in x", vec!["x"]);
+        check_free_variables("let _ = x in y", vec!["x", "y"]);
+        check_free_variables("let = x in λ. 2 y", vec!["x", "y"]);
+        check_free_variables("λx : T. x", vec!["T"]);
+        check_free_variables("λx : x. x", vec!["x"]);
+        check_free_variables("λx. λy : x. y", vec![]);
+        check_free_variables("λ : T. λ : 1 U. 1", vec!["T", "U"]);
+        check_free_variables("A → B", vec!["A", "B"]);
+        check_free_variables("Π(x : x). x", vec!["x"]);
+        check_free_variables("Π(A : Type). A → A", vec!["Type"]);
+        check_free_variables("case p of {}", vec!["p"]);
+        check_free_variables("letrec f = g x and g = f y in f z", vec!["x", "y", "z"]);
+        check_free_variables("letrec = 1 2 x and = 2 in y", vec!["x", "y"]);
+        check_free_variables("f #1 \"x\" true", vec!["f"]);
+        check_free_variables("case p of { pair a b => f a b c }", vec!["p", "f", "c"]);
+        check_free_variables("λp. case p of { pair/2 => 1 2 3 | nil => p }", vec![]);
+        check_free_variables("case a of { pair a _ => a b }", vec!["a", "b"]);
     }
 }
@@ -1,4 +1,7 @@
-use crate::expression::{Expression, ExpressionArena, ExpressionId};
+use crate::{
+    expression::{Expression, ExpressionArena, ExpressionId},
+    visitor::{Fold, Scoping},
+};
 
 impl Expression {
     pub fn height(expressions: &ExpressionArena, expression: ExpressionId) -> usize {
@@ -15,29 +18,41 @@ impl<'a> ExpressionHeight<'a> {
         ExpressionHeight { expressions }
     }
 
-    fn height(&self, expression: ExpressionId) -> usize {
+    pub fn compute_expression_height(mut self, expression: ExpressionId) -> usize {
+        self.fold_expression(expression)
+    }
+}
+
+impl Scoping for ExpressionHeight<'_> {}
+
+impl<'a> Fold<'a> for ExpressionHeight<'a> {
+    type Output = usize;
+
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn combine(&mut self, expression: ExpressionId, children: Vec<usize>) -> usize {
         match &self.expressions[expression] {
             Expression::Variable { identifier: _ } => 0,
+            Expression::Constant { constant: _ } => 0,
             Expression::NamelessVariable { index: _ } => 0,
-            Expression::Abstraction { parameter: _, body } => 1 + self.height(*body),
-            Expression::NamelessAbstraction { body } => 1 + self.height(*body),
+            // Applications add no level, and neither do case expressions over
+            // their scrutinee
             Expression::Application {
-                function,
-                arguments,
-            } => {
-                let mut s = 0;
-                s = std::cmp::max(s, self.height(*function));
-                for &argument in arguments {
-                    s = std::cmp::max(s, self.height(argument));
-                }
-                s
-            }
+                function: _,
+                arguments: _,
+            } => children.into_iter().max().unwrap_or(0),
+            Expression::Case {
+                scrutinee: _,
+                branches: _,
+            } => children[1..]
+                .iter()
+                .map(|height| 1 + height)
+                .fold(children[0], std::cmp::max),
+            _ => 1 + children.into_iter().max().unwrap_or(0),
         }
     }
-
-    pub fn compute_expression_height(self, expression: ExpressionId) -> usize {
-        self.height(expression)
-    }
 }
 
 #[cfg(test)]
@@ -69,5 +84,16 @@ mod tests {
         check_expression_height("λ. x", 1);
         check_expression_height("λ. λ. 2 1", 2);
         check_expression_height("λ. λ. λ. 3 1 (2 1)", 3);
+        check_expression_height("let x = y in x", 1);
+        check_expression_height("let = λy. y in λ. 1", 2);
+        check_expression_height("λx : T. x", 1);
+        check_expression_height("λx : (λ. λ. 1) T. x", 3);
+        check_expression_height("A → B", 1);
+        check_expression_height("Π(: Type). Π(: 1). 2", 2);
+        check_expression_height("case p of {}", 0);
+        check_expression_height("letrec f = λx. x and g = f in g", 2);
+        check_expression_height("letrec = 1 in 1", 1);
+        check_expression_height("λx. #1", 1);
+        check_expression_height("case p of { pair a b => a | nil => λx. x }", 2);
     }
 }
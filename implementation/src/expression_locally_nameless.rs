@@ -1,4 +1,7 @@
-use crate::expression::{Expression, ExpressionArena, ExpressionId};
+use crate::{
+    expression::{CaseBranch, Expression, ExpressionArena, ExpressionId},
+    visitor::{Scoping, Visitor},
+};
 
 impl Expression {
     pub fn is_locally_nameless(expressions: &ExpressionArena, expression: ExpressionId) -> bool {
@@ -8,42 +11,71 @@ impl Expression {
 
 struct IsLocallyNameless<'a> {
     expressions: &'a ExpressionArena,
+    is_locally_nameless: bool,
 }
 
 impl<'a> IsLocallyNameless<'a> {
     pub fn new(expressions: &'a ExpressionArena) -> IsLocallyNameless<'a> {
-        IsLocallyNameless { expressions }
+        IsLocallyNameless {
+            expressions,
+            is_locally_nameless: true,
+        }
+    }
+
+    pub fn check_is_locally_nameless(mut self, expression: ExpressionId) -> bool {
+        self.visit_expression(expression);
+        self.is_locally_nameless
     }
+}
+
+impl Scoping for IsLocallyNameless<'_> {}
 
-    fn is_locally_nameless(&self, expression: ExpressionId) -> bool {
+impl<'a> Visitor<'a> for IsLocallyNameless<'a> {
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn enter_expression(
+        &mut self,
+        _parent: Option<ExpressionId>,
+        expression: ExpressionId,
+    ) -> bool {
+        if !self.is_locally_nameless {
+            return false;
+        }
         match &self.expressions[expression] {
-            Expression::Variable { identifier: _ } => true,
-            Expression::NamelessVariable { index: _ } => true,
             Expression::Abstraction {
                 parameter: _,
+                annotation: _,
                 body: _,
-            } => false,
-            Expression::NamelessAbstraction { body } => self.is_locally_nameless(*body),
-            Expression::Application {
-                function,
-                arguments,
-            } => {
-                if !self.is_locally_nameless(*function) {
-                    false
-                } else {
-                    for &argument in arguments {
-                        if !self.is_locally_nameless(argument) {
-                            return false;
-                        }
-                    }
-                    true
-                }
             }
+            | Expression::Let {
+                parameter: _,
+                definition: _,
+                body: _,
+            }
+            | Expression::LetRec {
+                parameters: _,
+                definitions: _,
+                body: _,
+            }
+            | Expression::Pi {
+                parameter: _,
+                domain: _,
+                codomain: _,
+            } => self.is_locally_nameless = false,
+            Expression::Case {
+                scrutinee: _,
+                branches,
+            } if branches
+                .iter()
+                .any(|branch| matches!(branch, CaseBranch::Named { .. })) =>
+            {
+                self.is_locally_nameless = false
+            }
+            _ => {}
         }
-    }
-
-    pub fn check_is_locally_nameless(self, expression: ExpressionId) -> bool {
-        self.is_locally_nameless(expression)
+        self.is_locally_nameless
     }
 }
 
@@ -1,4 +1,7 @@
-use crate::expression::{Expression, ExpressionArena, ExpressionId};
+use crate::{
+    expression::{CaseBranch, Expression, ExpressionArena, ExpressionId},
+    visitor::{Scoping, Visitor},
+};
 
 impl Expression {
     pub fn is_named(expressions: &ExpressionArena, expression: ExpressionId) -> bool {
@@ -8,38 +11,67 @@ impl Expression {
 
 struct IsNamed<'a> {
     expressions: &'a ExpressionArena,
+    is_named: bool,
 }
 
 impl<'a> IsNamed<'a> {
     pub fn new(expressions: &'a ExpressionArena) -> IsNamed<'a> {
-        IsNamed { expressions }
+        IsNamed {
+            expressions,
+            is_named: true,
+        }
+    }
+
+    pub fn check_is_named(mut self, expression: ExpressionId) -> bool {
+        self.visit_expression(expression);
+        self.is_named
+    }
+}
+
+impl Scoping for IsNamed<'_> {}
+
+impl<'a> Visitor<'a> for IsNamed<'a> {
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
     }
 
-    fn is_named(&self, expression: ExpressionId) -> bool {
+    fn enter_expression(
+        &mut self,
+        _parent: Option<ExpressionId>,
+        expression: ExpressionId,
+    ) -> bool {
+        if !self.is_named {
+            return false;
+        }
         match &self.expressions[expression] {
-            Expression::Variable { identifier: _ } => true,
-            Expression::NamelessVariable { index: _ } => false,
-            Expression::Abstraction { parameter: _, body } => self.is_named(*body),
-            Expression::NamelessAbstraction { body: _ } => false,
-            Expression::Application {
-                function,
-                arguments,
-            } => {
-                if !self.is_named(*function) {
-                    false
-                } else {
-                    for &argument in arguments {
-                        if !self.is_named(argument) {
-                            return false;
-                        }
-                    }
-                    true
-                }
+            Expression::NamelessVariable { index: _ }
+            | Expression::NamelessAbstraction {
+                annotation: _,
+                body: _,
+            }
+            | Expression::NamelessLet {
+                definition: _,
+                body: _,
+            }
+            | Expression::NamelessLetRec {
+                definitions: _,
+                body: _,
             }
+            | Expression::NamelessPi {
+                domain: _,
+                codomain: _,
+            } => self.is_named = false,
+            Expression::Case {
+                scrutinee: _,
+                branches,
+            } if branches
+                .iter()
+                .any(|branch| matches!(branch, CaseBranch::Nameless { .. })) =>
+            {
+                self.is_named = false
+            }
+            _ => {}
         }
-    }
-
-    pub fn check_is_named(self, expression: ExpressionId) -> bool {
-        self.is_named(expression)
+        self.is_named
     }
 }
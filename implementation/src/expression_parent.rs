@@ -1,4 +1,7 @@
-use crate::expression::{Expression, ExpressionArena, ExpressionId};
+use crate::{
+    expression::{Expression, ExpressionArena, ExpressionId},
+    visitor::{Scoping, Visitor},
+};
 
 impl Expression {
     pub fn parent_expressions(
@@ -22,46 +25,28 @@ impl<'a> Parent<'a> {
         }
     }
 
-    #[inline]
-    fn set_parent(&mut self, expression: ExpressionId, parent: ExpressionId) {
-        self.parent[expression.into_usize()] = Option::Some(parent);
-    }
-
-    fn visit(&mut self, expression: ExpressionId) {
-        match &self.expressions[expression] {
-            Expression::Variable { identifier: _ } => {}
-            Expression::NamelessVariable { index: _ } => {}
-            Expression::Abstraction { parameter: _, body } => {
-                self.set_parent(*body, expression);
-                self.visit(*body);
-            }
-            Expression::NamelessAbstraction { body } => {
-                self.set_parent(*body, expression);
-                self.visit(*body);
-            }
-            Expression::Application {
-                function,
-                arguments,
-            } => {
-                self.set_parent(*function, expression);
-                self.visit(*function);
-                for &argument in arguments {
-                    self.set_parent(argument, expression);
-                    self.visit(argument);
-                }
-            }
-        }
-    }
-
     pub fn compute_parent_expressions(
         mut self,
         expression: ExpressionId,
     ) -> Vec<Option<ExpressionId>> {
-        self.visit(expression);
+        self.visit_expression(expression);
         self.parent
     }
 }
 
+impl Scoping for Parent<'_> {}
+
+impl<'a> Visitor<'a> for Parent<'a> {
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn enter_expression(&mut self, parent: Option<ExpressionId>, expression: ExpressionId) -> bool {
+        self.parent[expression.into_usize()] = parent;
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -78,16 +63,22 @@ mod tests {
         assert_eq!(parents[expression.into_usize()], parent);
         match &expressions[expression] {
             Expression::Variable { identifier: _ } => {}
+            Expression::Constant { constant: _ } => {}
             Expression::NamelessVariable { index: _ } => {}
-            Expression::Abstraction { parameter: _, body } => {
-                perform_check_parent_expressions(
-                    expressions,
-                    *body,
-                    parents,
-                    Option::Some(expression),
-                );
+            Expression::Abstraction {
+                parameter: _,
+                annotation,
+                body,
             }
-            Expression::NamelessAbstraction { body } => {
+            | Expression::NamelessAbstraction { annotation, body } => {
+                if let Option::Some(annotation) = annotation {
+                    perform_check_parent_expressions(
+                        expressions,
+                        *annotation,
+                        parents,
+                        Option::Some(expression),
+                    );
+                }
                 perform_check_parent_expressions(
                     expressions,
                     *body,
@@ -114,6 +105,74 @@ mod tests {
                     );
                 }
             }
+            Expression::Let {
+                parameter: _,
+                definition,
+                body,
+            }
+            | Expression::NamelessLet { definition, body }
+            | Expression::Pi {
+                parameter: _,
+                domain: definition,
+                codomain: body,
+            }
+            | Expression::NamelessPi {
+                domain: definition,
+                codomain: body,
+            } => {
+                perform_check_parent_expressions(
+                    expressions,
+                    *definition,
+                    parents,
+                    Option::Some(expression),
+                );
+                perform_check_parent_expressions(
+                    expressions,
+                    *body,
+                    parents,
+                    Option::Some(expression),
+                );
+            }
+            Expression::LetRec {
+                parameters: _,
+                definitions,
+                body,
+            }
+            | Expression::NamelessLetRec { definitions, body } => {
+                for definition in definitions {
+                    perform_check_parent_expressions(
+                        expressions,
+                        *definition,
+                        parents,
+                        Option::Some(expression),
+                    );
+                }
+                perform_check_parent_expressions(
+                    expressions,
+                    *body,
+                    parents,
+                    Option::Some(expression),
+                );
+            }
+            Expression::Case {
+                scrutinee,
+                branches,
+            } => {
+                perform_check_parent_expressions(
+                    expressions,
+                    *scrutinee,
+                    parents,
+                    Option::Some(expression),
+                );
+                for branch in branches {
+                    perform_check_parent_expressions(
+                        expressions,
+                        branch.body(),
+                        parents,
+                        Option::Some(expression),
+                    );
+                }
+            }
         }
     }
 
@@ -139,5 +198,15 @@ mod tests {
         check_parent_expressions("λ. x");
         check_parent_expressions("λ. λ. 2 1");
         check_parent_expressions("λ. λ. λ. 3 1 (2 1)");
+        check_parent_expressions("let x = y in x");
+        check_parent_expressions("let = λy. y in λ. 1 2");
+        check_parent_expressions("λx : T. x");
+        check_parent_expressions("λ : A B. λ : 1. 2");
+        check_parent_expressions("A → B");
+        check_parent_expressions("Π(: Type). Π(: 1). 2");
+        check_parent_expressions("case p of { pair a b => a | nil/0 => λx. x }");
+        check_parent_expressions("letrec f = g and g = λx. f x in f");
+        check_parent_expressions("letrec = 2 and = 1 in λ. 3");
+        check_parent_expressions("λ. f #1 \"s\" true 1");
     }
 }
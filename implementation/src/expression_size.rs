@@ -1,4 +1,7 @@
-use crate::expression::{Expression, ExpressionArena, ExpressionId};
+use crate::{
+    expression::{Expression, ExpressionArena, ExpressionId},
+    visitor::{Fold, Scoping},
+};
 
 impl Expression {
     pub fn size(expressions: &ExpressionArena, expression: ExpressionId) -> usize {
@@ -15,28 +18,22 @@ impl<'a> ExpressionSize<'a> {
         ExpressionSize { expressions }
     }
 
-    fn size(&self, expression: ExpressionId) -> usize {
-        match &self.expressions[expression] {
-            Expression::Variable { identifier: _ } => 1,
-            Expression::NamelessVariable { index: _ } => 1,
-            Expression::Abstraction { parameter: _, body } => 1 + self.size(*body),
-            Expression::NamelessAbstraction { body } => 1 + self.size(*body),
-            Expression::Application {
-                function,
-                arguments,
-            } => {
-                let mut s = 1;
-                s += self.size(*function);
-                for &argument in arguments {
-                    s += self.size(argument);
-                }
-                s
-            }
-        }
+    pub fn compute_expression_size(mut self, expression: ExpressionId) -> usize {
+        self.fold_expression(expression)
+    }
+}
+
+impl Scoping for ExpressionSize<'_> {}
+
+impl<'a> Fold<'a> for ExpressionSize<'a> {
+    type Output = usize;
+
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
     }
 
-    pub fn compute_expression_size(self, expression: ExpressionId) -> usize {
-        self.size(expression)
+    fn combine(&mut self, _expression: ExpressionId, children: Vec<usize>) -> usize {
+        1 + children.into_iter().sum::<usize>()
     }
 }
 
@@ -69,5 +66,16 @@ mod tests {
         check_expression_size("λ. x", 2);
         check_expression_size("λ. λ. 2 1", 5);
         check_expression_size("λ. λ. λ. 3 1 (2 1)", 9);
+        check_expression_size("let x = y in x", 3);
+        check_expression_size("let = λy. y in λ. 1", 5);
+        check_expression_size("λx : T. x", 3);
+        check_expression_size("λ : A B. 1", 5);
+        check_expression_size("A → B", 3);
+        check_expression_size("Π(: Type). Π(: 1). 2", 5);
+        check_expression_size("case p of {}", 2);
+        check_expression_size("letrec f = λx. x and g = f in g", 5);
+        check_expression_size("letrec = 1 in 1", 3);
+        check_expression_size("f #1 \"s\" false", 5);
+        check_expression_size("case p of { pair a b => a | nil => λx. x }", 5);
     }
 }
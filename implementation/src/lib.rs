@@ -2,6 +2,7 @@ pub mod strings;
 
 pub mod expression;
 
+pub mod abstract_machines;
 pub mod admissible_variable_name_generator;
 pub mod expression_free_variables;
 pub mod expression_height;
@@ -9,12 +10,25 @@ pub mod expression_locally_nameless;
 pub mod expression_named;
 pub mod expression_parent;
 pub mod expression_size;
+pub mod normalization;
 pub mod parser;
 pub mod pretty_print;
+pub mod program;
 pub mod random_expressions;
+pub mod reduction;
+pub mod span;
 
 pub mod alpha_equivalence;
+pub mod convertibility;
+pub mod de_bruijn;
+pub(crate) mod environments;
 pub mod equality;
+pub mod eta;
 pub mod referencing_environment;
+pub mod scope_check;
+pub mod substitution;
 pub mod to_locally_nameless;
 pub mod to_named;
+pub mod trace;
+pub mod validation;
+pub mod visitor;
@@ -0,0 +1,837 @@
+use crate::{
+    environments::{Bound, Environment, Environments, Lookup},
+    expression::{CaseBranch, Constant, DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+    strings::StringId,
+};
+
+impl Expression {
+    /// Normalizes the locally nameless `expression` by evaluation, and
+    /// constructs its normal form in locally nameless representation in
+    /// `destination`. The expression is evaluated into closures and neutral
+    /// terms, which are then read back using de Bruijn levels. Arguments and
+    /// let definitions are evaluated lazily, once their variables are needed,
+    /// so the arguments discarded by functions need no normal form.
+    ///
+    /// Evaluation takes at most `fuel` steps, each applying a closure or
+    /// unfolding a recursive definition. Returns `None` if the fuel runs out,
+    /// as it does for expressions without a normal form.
+    pub fn normalize(
+        expressions: &ExpressionArena,
+        expression: ExpressionId,
+        destination: &mut ExpressionArena,
+        fuel: usize,
+    ) -> Option<ExpressionId> {
+        debug_assert!(Expression::is_locally_nameless(expressions, expression));
+        Normalization::new(expressions, destination, fuel).normalize(expression)
+    }
+}
+
+/// Value IDs as indices in the values of a [Normalization].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct ValueId {
+    index: usize,
+}
+
+/// The semantic domain of evaluation. Abstractions and dependent function
+/// types evaluate to closures, and variables that cannot be evaluated further
+/// are neutral terms, possibly applied or scrutinized. Arguments and let
+/// definitions are bound to thunks, which are evaluated at most once.
+enum Value {
+    /// The unevaluated `expression` in `environment`.
+    Thunk {
+        expression: ExpressionId,
+        environment: Environment,
+    },
+    /// A thunk evaluated to `value`.
+    Forced {
+        value: ValueId,
+    },
+    Closure {
+        annotation: Option<ValueId>,
+        environment: Environment,
+        body: ExpressionId,
+    },
+    Pi {
+        domain: ValueId,
+        environment: Environment,
+        codomain: ExpressionId,
+    },
+    Constant {
+        constant: Constant,
+    },
+    /// The variable bound by the `level`-th enclosing binder, counting from
+    /// the outermost binder, during read back.
+    Level {
+        level: usize,
+    },
+    /// The nameless variable referring to the `index`-th binder outside of the
+    /// normalized expression.
+    Loose {
+        index: usize,
+    },
+    Free {
+        identifier: StringId,
+    },
+    Application {
+        function: ValueId,
+        arguments: Vec<ValueId>,
+    },
+    /// The case expression `case` scrutinizing a neutral term, with its
+    /// branches closed over `environment`.
+    Case {
+        scrutinee: ValueId,
+        environment: Environment,
+        case: ExpressionId,
+    },
+}
+
+/// Tasks of the evaluation. Evaluated values are kept on a separate stack.
+enum Task {
+    Evaluate {
+        expression: ExpressionId,
+        environment: Environment,
+    },
+    /// Applies the function value on top of the stack to `arguments`, in
+    /// reverse order.
+    Apply { arguments: Vec<ValueId> },
+    /// Memoizes the value on top of the stack as the value of `thunk`.
+    Force { thunk: ValueId },
+    /// Pops the annotation value of an abstraction.
+    Closure {
+        environment: Environment,
+        body: ExpressionId,
+    },
+    /// Pops the domain value of a dependent function type.
+    Pi {
+        environment: Environment,
+        codomain: ExpressionId,
+    },
+    /// Pops the scrutinee value of a case expression.
+    Case {
+        environment: Environment,
+        case: ExpressionId,
+    },
+}
+
+/// Steps of the read back.
+enum ReadBack {
+    ReadBack {
+        value: ValueId,
+        level: usize,
+    },
+    /// Constructs the expression for `value` from its read back parts.
+    Build {
+        value: ValueId,
+    },
+}
+
+struct Normalization<'a> {
+    expressions: &'a ExpressionArena,
+    destination: &'a mut ExpressionArena,
+    values: Vec<Value>,
+    environments: Environments<'a, ValueId>,
+    fuel: usize,
+}
+
+impl<'a> Normalization<'a> {
+    fn new(
+        expressions: &'a ExpressionArena,
+        destination: &'a mut ExpressionArena,
+        fuel: usize,
+    ) -> Normalization<'a> {
+        Normalization {
+            expressions,
+            destination,
+            values: Vec::new(),
+            environments: Environments::new(expressions),
+            fuel,
+        }
+    }
+
+    fn normalize(mut self, expression: ExpressionId) -> Option<ExpressionId> {
+        let value = self.evaluate(expression, Option::None)?;
+        self.read_back(value)
+    }
+
+    fn value(&mut self, value: Value) -> ValueId {
+        let index = self.values.len();
+        self.values.push(value);
+        ValueId { index }
+    }
+
+    /// Consumes one unit of fuel. Returns `None` if there is none left.
+    fn consume_fuel(&mut self) -> Option<()> {
+        self.fuel = self.fuel.checked_sub(1)?;
+        Option::Some(())
+    }
+
+    /// Evaluates `expression` in `environment`.
+    fn evaluate(&mut self, expression: ExpressionId, environment: Environment) -> Option<ValueId> {
+        let expressions = self.expressions;
+        let mut tasks = vec![Task::Evaluate {
+            expression,
+            environment,
+        }];
+        let mut results = Vec::new();
+        while let Option::Some(task) = tasks.pop() {
+            match task {
+                Task::Evaluate {
+                    expression,
+                    environment,
+                } => match &expressions[expression] {
+                    Expression::Variable { identifier } => {
+                        let value = self.value(Value::Free {
+                            identifier: *identifier,
+                        });
+                        results.push(value);
+                    }
+                    Expression::NamelessVariable { index } => {
+                        match self.lookup(environment, *index) {
+                            Result::Ok(value) => match self.values[value.index] {
+                                Value::Thunk {
+                                    expression,
+                                    environment,
+                                } => {
+                                    tasks.push(Task::Force { thunk: value });
+                                    tasks.push(Task::Evaluate {
+                                        expression,
+                                        environment,
+                                    });
+                                }
+                                Value::Forced { value } => results.push(value),
+                                _ => results.push(value),
+                            },
+                            Result::Err((group, index, environment)) => {
+                                // Unfolds the recursive definition
+                                self.consume_fuel()?;
+                                let definition = expressions[group].children()[index];
+                                let environment = self.environments.bind_group(environment, group);
+                                tasks.push(Task::Evaluate {
+                                    expression: definition,
+                                    environment,
+                                });
+                            }
+                        }
+                    }
+                    Expression::Constant { constant } => {
+                        let value = self.value(Value::Constant {
+                            constant: *constant,
+                        });
+                        results.push(value);
+                    }
+                    Expression::NamelessAbstraction { annotation, body } => match annotation {
+                        Option::Some(annotation) => {
+                            tasks.push(Task::Closure {
+                                environment,
+                                body: *body,
+                            });
+                            tasks.push(Task::Evaluate {
+                                expression: *annotation,
+                                environment,
+                            });
+                        }
+                        Option::None => {
+                            let value = self.value(Value::Closure {
+                                annotation: Option::None,
+                                environment,
+                                body: *body,
+                            });
+                            results.push(value);
+                        }
+                    },
+                    Expression::Application {
+                        function,
+                        arguments,
+                    } => {
+                        let arguments = arguments
+                            .iter()
+                            .rev()
+                            .map(|&expression| self.thunk(expression, environment))
+                            .collect();
+                        tasks.push(Task::Apply { arguments });
+                        tasks.push(Task::Evaluate {
+                            expression: *function,
+                            environment,
+                        });
+                    }
+                    Expression::NamelessLet { definition, body } => {
+                        let definition = self.thunk(*definition, environment);
+                        let environment = self.environments.bind_value(environment, definition);
+                        tasks.push(Task::Evaluate {
+                            expression: *body,
+                            environment,
+                        });
+                    }
+                    Expression::NamelessLetRec {
+                        definitions: _,
+                        body,
+                    } => {
+                        let environment = self.environments.bind_group(environment, expression);
+                        tasks.push(Task::Evaluate {
+                            expression: *body,
+                            environment,
+                        });
+                    }
+                    Expression::NamelessPi { domain, codomain } => {
+                        tasks.push(Task::Pi {
+                            environment,
+                            codomain: *codomain,
+                        });
+                        tasks.push(Task::Evaluate {
+                            expression: *domain,
+                            environment,
+                        });
+                    }
+                    Expression::Case {
+                        scrutinee,
+                        branches: _,
+                    } => {
+                        tasks.push(Task::Case {
+                            environment,
+                            case: expression,
+                        });
+                        tasks.push(Task::Evaluate {
+                            expression: *scrutinee,
+                            environment,
+                        });
+                    }
+                    Expression::Abstraction {
+                        parameter: _,
+                        annotation: _,
+                        body: _,
+                    }
+                    | Expression::Let {
+                        parameter: _,
+                        definition: _,
+                        body: _,
+                    }
+                    | Expression::LetRec {
+                        parameters: _,
+                        definitions: _,
+                        body: _,
+                    }
+                    | Expression::Pi {
+                        parameter: _,
+                        domain: _,
+                        codomain: _,
+                    } => unreachable!("named binders are not locally nameless"),
+                },
+                Task::Apply { mut arguments } => {
+                    let Option::Some(argument) = arguments.pop() else {
+                        continue;
+                    };
+                    let function = results.pop().unwrap();
+                    match &self.values[function.index] {
+                        Value::Closure {
+                            annotation: _,
+                            environment,
+                            body,
+                        } => {
+                            let (environment, body) = (*environment, *body);
+                            self.consume_fuel()?;
+                            let environment = self.environments.bind_value(environment, argument);
+                            tasks.push(Task::Apply { arguments });
+                            tasks.push(Task::Evaluate {
+                                expression: body,
+                                environment,
+                            });
+                        }
+                        Value::Application {
+                            function,
+                            arguments: applied,
+                        } => {
+                            // Neutral applications are kept flat
+                            let function = *function;
+                            let mut applied = applied.clone();
+                            applied.push(argument);
+                            let value = self.value(Value::Application {
+                                function,
+                                arguments: applied,
+                            });
+                            results.push(value);
+                            tasks.push(Task::Apply { arguments });
+                        }
+                        _ => {
+                            let value = self.value(Value::Application {
+                                function,
+                                arguments: vec![argument],
+                            });
+                            results.push(value);
+                            tasks.push(Task::Apply { arguments });
+                        }
+                    }
+                }
+                Task::Force { thunk } => {
+                    let value = *results.last().unwrap();
+                    self.values[thunk.index] = Value::Forced { value };
+                }
+                Task::Closure { environment, body } => {
+                    let annotation = results.pop();
+                    let value = self.value(Value::Closure {
+                        annotation,
+                        environment,
+                        body,
+                    });
+                    results.push(value);
+                }
+                Task::Pi {
+                    environment,
+                    codomain,
+                } => {
+                    let domain = results.pop().unwrap();
+                    let value = self.value(Value::Pi {
+                        domain,
+                        environment,
+                        codomain,
+                    });
+                    results.push(value);
+                }
+                Task::Case { environment, case } => {
+                    let scrutinee = results.pop().unwrap();
+                    let value = self.value(Value::Case {
+                        scrutinee,
+                        environment,
+                        case,
+                    });
+                    results.push(value);
+                }
+            }
+        }
+        results.pop()
+    }
+
+    /// Delays the evaluation of `expression` in `environment`.
+    fn thunk(&mut self, expression: ExpressionId, environment: Environment) -> ValueId {
+        self.value(Value::Thunk {
+            expression,
+            environment,
+        })
+    }
+
+    /// Evaluates `value` if it is a thunk, at most once.
+    fn force(&mut self, value: ValueId) -> Option<ValueId> {
+        match self.values[value.index] {
+            Value::Thunk {
+                expression,
+                environment,
+            } => {
+                let forced = self.evaluate(expression, environment)?;
+                self.values[value.index] = Value::Forced { value: forced };
+                Option::Some(forced)
+            }
+            Value::Forced { value } => Option::Some(value),
+            _ => Option::Some(value),
+        }
+    }
+
+    /// Looks up the value bound to `index` in `environment`. Returns the
+    /// recursive definition to unfold if `index` refers to one.
+    fn lookup(
+        &mut self,
+        environment: Environment,
+        index: DeBruijnIndex,
+    ) -> Result<ValueId, (ExpressionId, usize, Environment)> {
+        match self.environments.lookup(environment, index) {
+            Lookup::Bound(Bound::Value(value)) => Result::Ok(value),
+            Lookup::Bound(Bound::Recursive {
+                group,
+                index,
+                environment,
+            }) => Result::Err((group, index, environment)),
+            Lookup::Loose { index } => Result::Ok(self.value(Value::Loose { index })),
+        }
+    }
+
+    /// Evaluates `body` in `environment` extended with `count` fresh
+    /// variables, bound from level `level` on.
+    fn instantiate(
+        &mut self,
+        body: ExpressionId,
+        environment: Environment,
+        level: usize,
+        count: usize,
+    ) -> Option<ValueId> {
+        let mut environment = environment;
+        for offset in 0..count {
+            let variable = self.value(Value::Level {
+                level: level + offset,
+            });
+            environment = self.environments.bind_value(environment, variable);
+        }
+        self.evaluate(body, environment)
+    }
+
+    /// Reads back `value` into a locally nameless expression in the
+    /// destination.
+    fn read_back(&mut self, value: ValueId) -> Option<ExpressionId> {
+        let mut steps = vec![ReadBack::ReadBack { value, level: 0 }];
+        let mut results = Vec::new();
+        while let Option::Some(step) = steps.pop() {
+            match step {
+                ReadBack::ReadBack { value, level } => {
+                    let value = self.force(value)?;
+                    match &self.values[value.index] {
+                        Value::Closure {
+                            annotation,
+                            environment,
+                            body,
+                        } => {
+                            let annotation = *annotation;
+                            let body = self.instantiate(*body, *environment, level, 1)?;
+                            steps.push(ReadBack::Build { value });
+                            steps.push(ReadBack::ReadBack {
+                                value: body,
+                                level: level + 1,
+                            });
+                            if let Option::Some(annotation) = annotation {
+                                steps.push(ReadBack::ReadBack {
+                                    value: annotation,
+                                    level,
+                                });
+                            }
+                        }
+                        Value::Pi {
+                            domain,
+                            environment,
+                            codomain,
+                        } => {
+                            let domain = *domain;
+                            let codomain = self.instantiate(*codomain, *environment, level, 1)?;
+                            steps.push(ReadBack::Build { value });
+                            steps.push(ReadBack::ReadBack {
+                                value: codomain,
+                                level: level + 1,
+                            });
+                            steps.push(ReadBack::ReadBack {
+                                value: domain,
+                                level,
+                            });
+                        }
+                        Value::Constant { constant } => {
+                            let expression = self.destination.constant(*constant);
+                            results.push(expression);
+                        }
+                        Value::Level { level: bound } => {
+                            let index = DeBruijnIndex::new(level - bound);
+                            results.push(self.destination.nameless_variable(index));
+                        }
+                        Value::Loose { index } => {
+                            let index = DeBruijnIndex::new(index + level);
+                            results.push(self.destination.nameless_variable(index));
+                        }
+                        Value::Free { identifier } => {
+                            let expression = self.destination.variable(*identifier);
+                            results.push(expression);
+                        }
+                        Value::Application {
+                            function,
+                            arguments,
+                        } => {
+                            steps.push(ReadBack::Build { value });
+                            for &value in arguments.iter().rev().chain([function]) {
+                                steps.push(ReadBack::ReadBack { value, level });
+                            }
+                        }
+                        Value::Case {
+                            scrutinee,
+                            environment,
+                            case,
+                        } => {
+                            let (scrutinee, environment) = (*scrutinee, *environment);
+                            let Expression::Case {
+                                scrutinee: _,
+                                branches,
+                            } = &self.expressions[*case]
+                            else {
+                                unreachable!()
+                            };
+                            steps.push(ReadBack::Build { value });
+                            for branch in branches.iter().rev() {
+                                let arity = branch.arity();
+                                let body =
+                                    self.instantiate(branch.body(), environment, level, arity)?;
+                                steps.push(ReadBack::ReadBack {
+                                    value: body,
+                                    level: level + arity,
+                                });
+                            }
+                            steps.push(ReadBack::ReadBack {
+                                value: scrutinee,
+                                level,
+                            });
+                        }
+                        Value::Thunk {
+                            expression: _,
+                            environment: _,
+                        }
+                        | Value::Forced { value: _ } => unreachable!("thunks are forced"),
+                    }
+                }
+                ReadBack::Build { value } => {
+                    let expression = self.build(value, &mut results);
+                    results.push(expression);
+                }
+            }
+        }
+        results.pop()
+    }
+
+    /// Constructs the expression for `value` from its read back parts on top
+    /// of `results`.
+    fn build(&mut self, value: ValueId, results: &mut Vec<ExpressionId>) -> ExpressionId {
+        match &self.values[value.index] {
+            Value::Closure {
+                annotation,
+                environment: _,
+                body: _,
+            } => {
+                let body = results.pop().unwrap();
+                let annotation = annotation.map(|_| results.pop().unwrap());
+                self.destination
+                    .annotated_nameless_abstraction(annotation, body)
+            }
+            Value::Pi {
+                domain: _,
+                environment: _,
+                codomain: _,
+            } => {
+                let codomain = results.pop().unwrap();
+                let domain = results.pop().unwrap();
+                self.destination.nameless_pi(domain, codomain)
+            }
+            Value::Application {
+                function: _,
+                arguments,
+            } => {
+                let arguments = results.split_off(results.len() - arguments.len());
+                let function = results.pop().unwrap();
+                self.destination.application(function, arguments)
+            }
+            Value::Case {
+                scrutinee: _,
+                environment: _,
+                case,
+            } => {
+                let Expression::Case {
+                    scrutinee: _,
+                    branches,
+                } = &self.expressions[*case]
+                else {
+                    unreachable!()
+                };
+                let bodies = results.split_off(results.len() - branches.len());
+                let scrutinee = results.pop().unwrap();
+                let branches = branches
+                    .iter()
+                    .zip(bodies)
+                    .map(|(branch, body)| CaseBranch::Nameless {
+                        constructor: branch.constructor(),
+                        arity: branch.arity(),
+                        body,
+                    })
+                    .collect();
+                self.destination.case(scrutinee, branches)
+            }
+            Value::Thunk {
+                expression: _,
+                environment: _,
+            }
+            | Value::Forced { value: _ }
+            | Value::Constant { constant: _ }
+            | Value::Level { level: _ }
+            | Value::Loose { index: _ }
+            | Value::Free { identifier: _ } => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        admissible_variable_name_generator::VariableNameGenerator,
+        referencing_environment::ReferencingEnvironment, strings::StringArena,
+    };
+
+    use super::*;
+
+    /// The Church numeral for `n`, in named representation.
+    fn church_numeral(n: usize) -> String {
+        format!("(λf x. {}x{})", "f (".repeat(n), ")".repeat(n))
+    }
+
+    fn check_normalize(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut nameless_expressions = ExpressionArena::new();
+        let mut normal_forms = ExpressionArena::new();
+        let mut named_expressions = ExpressionArena::new();
+        let environment = Rc::new(ReferencingEnvironment::new());
+
+        let expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+        let expected =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, expected.as_bytes())
+                .unwrap();
+
+        let nameless_expression = Expression::convert_to_locally_nameless(
+            (environment.clone(), &expressions, expression),
+            &mut nameless_expressions,
+        );
+        let normal_form = Expression::normalize(
+            &nameless_expressions,
+            nameless_expression,
+            &mut normal_forms,
+            1_000_000,
+        )
+        .unwrap();
+        assert!(Expression::is_locally_nameless(&normal_forms, normal_form));
+
+        let named_normal_form = Expression::convert_to_named(
+            &mut strings,
+            &normal_forms,
+            normal_form,
+            &mut named_expressions,
+            VariableNameGenerator::new(),
+        );
+        assert!(Expression::alpha_equivalent(
+            (environment.clone(), &named_expressions, named_normal_form),
+            (environment.clone(), &expressions, expected)
+        ));
+    }
+
+    fn check_out_of_fuel(input: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut normal_forms = ExpressionArena::new();
+
+        let expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+        assert_eq!(
+            Expression::normalize(&expressions, expression, &mut normal_forms, 10_000),
+            Option::None
+        );
+    }
+
+    #[test]
+    fn normalize_computes_normal_forms() {
+        check_normalize("(λx. x) y", "y");
+        check_normalize("(λx y. x) a b", "a");
+        check_normalize("λx. f ((λy. y) x)", "λx. f x");
+        check_normalize("λx. (λy z. y z) x", "λx z. x z");
+        check_normalize("λz. λ. 2 ((λ. 1) 1)", "λz y. z y");
+        check_normalize("λx : (λy. y) A. x", "λx : A. x");
+        check_normalize("Π(B : (λy. y) A). (λy. y) B", "Π(B : A). B");
+        check_normalize("let id = λx. x in id a", "a");
+        check_normalize("letrec f = λx. x and g = λy. f y in g a", "a");
+        check_normalize(
+            "λp. case p of { c a b => (λx. x) a | d => q }",
+            "λp. case p of { c a b => a | d => q }",
+        );
+    }
+
+    #[test]
+    fn normalize_computes_church_numerals() {
+        let plus = "(λm n f x. m f (n f x))";
+        let times = "(λm n f. m (n f))";
+        let power = "(λm n. n m)";
+        check_normalize(
+            &format!("{} {} {}", plus, church_numeral(2), church_numeral(3)),
+            &church_numeral(5),
+        );
+        check_normalize(
+            &format!("{} {} {}", times, church_numeral(3), church_numeral(4)),
+            &church_numeral(12),
+        );
+        check_normalize(
+            &format!("{} {} {}", power, church_numeral(2), church_numeral(5)),
+            &church_numeral(32),
+        );
+    }
+
+    #[test]
+    fn normalize_handles_deep_normal_forms() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut normal_forms = ExpressionArena::new();
+
+        let input = format!("(λ. λ. 1 2) {} {}", church_numeral(2), church_numeral(16));
+        let expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+        let expression = Expression::convert_to_locally_nameless(
+            (
+                Rc::new(ReferencingEnvironment::new()),
+                &expressions,
+                expression,
+            ),
+            &mut normal_forms,
+        );
+        let normal_form =
+            Expression::normalize(&normal_forms, expression, &mut ExpressionArena::new(), 0);
+        assert_eq!(normal_form, Option::None);
+
+        let mut destination = ExpressionArena::new();
+        let normal_form =
+            Expression::normalize(&normal_forms, expression, &mut destination, 1_000_000).unwrap();
+
+        // λ. λ. 2 (2 (… (2 1)))
+        let mut body = expressions.nameless_variable(DeBruijnIndex::new(1));
+        for _ in 0..1 << 16 {
+            let function = expressions.nameless_variable(DeBruijnIndex::new(2));
+            body = expressions.application(function, vec![body]);
+        }
+        let abstraction = expressions.nameless_abstraction(body);
+        let expected = expressions.nameless_abstraction(abstraction);
+        assert!(Expression::equals(
+            (&destination, normal_form),
+            (&expressions, expected)
+        ));
+    }
+
+    #[test]
+    fn normalize_keeps_loose_indices() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+
+        let expression = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            "λ. 3 ((λ. 3 1) 1)".as_bytes(),
+        )
+        .unwrap();
+        let expected = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            "λ. 3 (2 1)".as_bytes(),
+        )
+        .unwrap();
+        let normal_form =
+            Expression::normalize(&expressions, expression, &mut destination, 10).unwrap();
+        assert!(Expression::equals(
+            (&destination, normal_form),
+            (&expressions, expected)
+        ));
+    }
+
+    #[test]
+    fn normalize_runs_out_of_fuel_without_normal_forms() {
+        check_out_of_fuel("(λ. 1 1) (λ. 1 1)");
+        check_out_of_fuel("letrec = 1 in 1");
+        check_out_of_fuel("λ. (λ. 1 1 1) (λ. 1 1 1)");
+        check_out_of_fuel("f ((λ. 1 1) (λ. 1 1))");
+        check_out_of_fuel("let = (λ. 1 1) (λ. 1 1) in 1");
+    }
+
+    #[test]
+    fn normalize_discards_unused_arguments_without_evaluating_them() {
+        let omega = "((λx. x x) (λx. x x))";
+        check_normalize(&format!("(λx y. x) a {}", omega), "a");
+        check_normalize(&format!("let x = {} in a", omega), "a");
+        check_normalize("(λx. a) (letrec f = f in f)", "a");
+    }
+}
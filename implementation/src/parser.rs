@@ -1,20 +1,72 @@
 extern crate nom;
 
+use std::rc::Rc;
+
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{alpha1, alphanumeric0, multispace0, multispace1, u32},
-    combinator::{eof, map, opt, recognize},
-    multi::separated_list1,
-    sequence::{delimited, pair, terminated},
-    Finish, IResult,
+    bytes::complete::{is_not, tag},
+    character::complete::{i64, multispace0, u32},
+    combinator::{cut, eof, map, opt, recognize, value, verify},
+    error::ErrorKind,
+    multi::many0,
+    sequence::{delimited, pair, preceded, terminated},
+    Finish, InputLength,
 };
 
 use crate::{
     expression::{self, ExpressionArena, ExpressionId},
+    program::Program,
+    referencing_environment::ReferencingEnvironment,
+    scope_check::ScopeError,
+    span::{LineIndex, Position, SourceMap, Span},
     strings::StringArena,
 };
 
+type IResult<I, O, E = Failure<I>> = nom::IResult<I, O, E>;
+
+/// Descriptions of what the parser expected to find where it failed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Expected {
+    /// A literal token, like `λ` or `in`.
+    Token(&'static str),
+    /// A class of tokens, like a name or an integer.
+    Description(&'static str),
+}
+
+/// Errors reported when parsing fails, located at the furthest point the
+/// parser reached in the input, or at an index that failed scope checking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    offset: usize,
+    position: Position,
+    token: Option<String>,
+    kind: ParseErrorKind,
+    source_line: String,
+}
+
+/// Reasons for which parsing fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseErrorKind {
+    /// The offending token is none of the expected alternatives.
+    Syntax(Vec<Expected>),
+    /// The offending de Bruijn index is not bound.
+    Scope(ScopeError),
+}
+
+/// Options for parsing expressions in mixed representation.
+#[derive(Default)]
+pub struct ParseOptions {
+    scope_check: Option<Rc<ReferencingEnvironment>>,
+}
+
+/// The furthest failure encountered while parsing, with the alternatives that
+/// were expected there.
+#[derive(Debug)]
+struct Failure<I> {
+    input: I,
+    expected: Vec<Expected>,
+}
+
 pub enum Expression {
     Variable {
         identifier: Box<[u8]>,
@@ -22,275 +74,2631 @@ pub enum Expression {
     NamelessVariable {
         index: usize,
     },
+    Constant {
+        constant: Constant,
+    },
     Abstraction {
         parameter: Option<Box<[u8]>>,
+        annotation: Option<Box<Expression>>,
         body: Box<Expression>,
     },
     NamelessAbstraction {
+        annotation: Option<Box<Expression>>,
         body: Box<Expression>,
     },
     Application {
         function: Box<Expression>,
         arguments: Vec<Expression>,
     },
+    Let {
+        parameter: Option<Box<[u8]>>,
+        definition: Box<Expression>,
+        body: Box<Expression>,
+    },
+    NamelessLet {
+        definition: Box<Expression>,
+        body: Box<Expression>,
+    },
+    LetRec {
+        parameters: Vec<Option<Box<[u8]>>>,
+        definitions: Vec<Expression>,
+        body: Box<Expression>,
+    },
+    NamelessLetRec {
+        definitions: Vec<Expression>,
+        body: Box<Expression>,
+    },
+    Pi {
+        parameter: Option<Box<[u8]>>,
+        domain: Box<Expression>,
+        codomain: Box<Expression>,
+    },
+    NamelessPi {
+        domain: Box<Expression>,
+        codomain: Box<Expression>,
+    },
+    Case {
+        scrutinee: Box<Expression>,
+        branches: Vec<CaseBranch>,
+    },
+    /// The expression parsed between `start` and `end`, which are recorded as
+    /// the lengths of the input remaining at those points. They are converted
+    /// to byte offsets when lowering.
+    Located {
+        start: usize,
+        end: usize,
+        expression: Box<Expression>,
+    },
+}
+
+pub enum Constant {
+    Integer(i64),
+    String(Box<[u8]>),
+    Boolean(bool),
+}
+
+pub enum CaseBranch {
+    Named {
+        constructor: Box<[u8]>,
+        parameters: Vec<Option<Box<[u8]>>>,
+        body: Expression,
+    },
+    Nameless {
+        constructor: Box<[u8]>,
+        arity: usize,
+        body: Expression,
+    },
+}
+
+impl<I: InputLength> nom::error::ParseError<I> for Failure<I> {
+    fn from_error_kind(input: I, _kind: ErrorKind) -> Failure<I> {
+        Failure {
+            input,
+            expected: Vec::new(),
+        }
+    }
+
+    fn append(_input: I, _kind: ErrorKind, other: Failure<I>) -> Failure<I> {
+        other
+    }
+
+    fn or(mut self, other: Failure<I>) -> Failure<I> {
+        // Keep the failure furthest into the input, merging alternatives that
+        // failed at the same point
+        match self.input.input_len().cmp(&other.input.input_len()) {
+            std::cmp::Ordering::Less => self,
+            std::cmp::Ordering::Greater => other,
+            std::cmp::Ordering::Equal => {
+                self.expected.extend(other.expected);
+                self
+            }
+        }
+    }
+}
+
+/// Reports a failure of `parser` as expecting `expected`.
+fn expect<'a, O, F>(
+    expected: Expected,
+    mut parser: F,
+) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], O>
+where
+    F: FnMut(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    move |input: &'a [u8]| {
+        parser(input).map_err(|error| {
+            error.map(|_| Failure {
+                input,
+                expected: vec![expected],
+            })
+        })
+    }
 }
 
+const KEYWORDS: [&[u8]; 11] = [
+    b"let", b"letrec", b"and", b"in", b"case", b"of", b"true", b"false", b"fun", b"forall", b"def",
+];
+
 fn lambda(input: &[u8]) -> IResult<&[u8], &[u8]> {
-    tag("λ")(input)
+    alt((symbol("λ"), backslash))(input)
+}
+
+fn pi(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    alt((symbol("Π"), forall_keyword))(input)
+}
+
+fn arrow(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    alt((symbol("→"), symbol("->")))(input)
 }
 
 fn dot(input: &[u8]) -> IResult<&[u8], &[u8]> {
-    tag(".")(input)
+    symbol(".")(input)
+}
+
+fn colon(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol(":")(input)
+}
+
+fn equals(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol("=")(input)
+}
+
+fn fat_arrow(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol("=>")(input)
+}
+
+fn slash(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol("/")(input)
+}
+
+fn semicolon(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol(";")(input)
+}
+
+fn bar(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol("|")(input)
+}
+
+fn left_brace(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol("{")(input)
+}
+
+fn right_brace(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol("}")(input)
+}
+
+fn hash(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol("#")(input)
+}
+
+fn quote(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol("\"")(input)
+}
+
+fn backslash(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    symbol("\\")(input)
 }
 
 fn underscore(input: &[u8]) -> IResult<&[u8], &[u8]> {
-    tag("_")(input)
+    symbol("_")(input)
 }
 
 fn left_parenthesis(input: &[u8]) -> IResult<&[u8], &[u8]> {
-    tag("(")(input)
+    symbol("(")(input)
 }
 
 fn right_parenthesis(input: &[u8]) -> IResult<&[u8], &[u8]> {
-    tag(")")(input)
+    symbol(")")(input)
 }
 
-fn name(input: &[u8]) -> IResult<&[u8], &[u8]> {
-    recognize(pair(alpha1, alphanumeric0))(input)
+fn symbol<'a>(symbol: &'static str) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    expect(Expected::Token(symbol), tag(symbol))
 }
 
-/// <expression> ::=
-///   | <expression1>
-///
-/// <expression1> ::=
-///   | `λ` <name> `.` <expression1>
-///   | `λ` `_` `.` <expression1>
-///   | <expression2>
-///
-/// <expression2> ::=
-///   | <expression3>+
-///
-/// <expression3> ::=
-///   | <name>
-///   | `(` <expression1> `)`
-fn expression(input: &[u8]) -> IResult<&[u8], Expression> {
-    expression1(input)
+/// Parses a line comment, from `--` to the end of the line.
+fn line_comment(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    recognize(pair(symbol("--"), opt(is_not("\n"))))(input)
+}
+
+/// Parses a block comment delimited by `{-` and `-}`, which may be nested.
+fn block_comment(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let (mut remaining, _) = symbol("{-")(input)?;
+    let mut depth = 1;
+    while depth > 0 {
+        if remaining.starts_with(b"{-") {
+            depth += 1;
+            remaining = &remaining[2..];
+        } else if remaining.starts_with(b"-}") {
+            depth -= 1;
+            remaining = &remaining[2..];
+        } else if remaining.is_empty() {
+            symbol("-}")(remaining)?;
+        } else {
+            remaining = &remaining[1..];
+        }
+    }
+    IResult::Ok((remaining, &input[..input.len() - remaining.len()]))
+}
+
+fn comment(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    alt((line_comment, block_comment))(input)
+}
+
+/// Parses whitespace and comments, which may occur between any two tokens.
+/// Comments are only attempted where one starts, since trivia follows every
+/// token and the failures of the attempts would be discarded anyway.
+fn trivia0(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let (mut remaining, _) = multispace0(input)?;
+    while remaining.starts_with(b"--") || remaining.starts_with(b"{-") {
+        match comment(remaining) {
+            IResult::Ok((rest, _)) => (remaining, _) = multispace0(rest)?,
+            IResult::Err(_) => break,
+        }
+    }
+    IResult::Ok((remaining, &input[..input.len() - remaining.len()]))
+}
+
+/// Parses non-empty whitespace and comments, as required between the terms
+/// of an application.
+fn trivia1(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    verify(trivia0, |trivia: &[u8]| !trivia.is_empty())(input)
+}
+
+/// Computes the spans of the comments in `input`, skipping over the contents
+/// of string literals.
+fn comment_spans(input: &[u8]) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut remaining = input;
+    while !remaining.is_empty() {
+        if let IResult::Ok((rest, _)) = string_literal(remaining) {
+            remaining = rest;
+        } else if let IResult::Ok((rest, _)) = comment(remaining) {
+            spans.push(Span::new(
+                input.len() - remaining.len(),
+                input.len() - rest.len(),
+            ));
+            remaining = rest;
+        } else {
+            // Only quotes and the openings of comments start spans to skip
+            let next = remaining[1..]
+                .iter()
+                .position(|byte| b"\"-{".contains(byte))
+                .map_or(remaining.len(), |position| position + 1);
+            remaining = &remaining[next..];
+        }
+    }
+    spans
+}
+
+/// Determines whether `c` may start an identifier. The letters `λ` and `Π`
+/// are reserved as symbols.
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() && c != 'λ' && c != 'Π'
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    is_identifier_start(c) || c.is_numeric()
+}
+
+/// Parses an identifier, which starts with a letter, and continues with
+/// letters, digits (including subscripts like `₁`) and primes. Underscores and
+/// hyphens may occur inside identifiers, as in `f_inner` and `my-var`, but
+/// not at their end.
+fn identifier(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let mut end = match first_character(input) {
+        Option::Some((c, length)) if is_identifier_start(c) => length,
+        _ => {
+            return IResult::Err(nom::Err::Error(nom::error::ParseError::from_error_kind(
+                input,
+                ErrorKind::Alpha,
+            )))
+        }
+    };
+    while let Option::Some((c, length)) = first_character(&input[end..]) {
+        let accepted = match c {
+            '\'' => true,
+            '_' | '-' => matches!(
+                first_character(&input[end + length..]),
+                Option::Some((next, _)) if is_identifier_continue(next)
+            ),
+            c => is_identifier_continue(c),
+        };
+        if !accepted {
+            break;
+        }
+        end += length;
+    }
+    IResult::Ok((&input[end..], &input[..end]))
+}
+
+/// Decodes the UTF-8 character at the start of `input`, and returns it with
+/// its length in bytes. Only the bytes of that character are inspected.
+fn first_character(input: &[u8]) -> Option<(char, usize)> {
+    let prefix = &input[..input.len().min(4)];
+    let text = match std::str::from_utf8(prefix) {
+        Result::Ok(text) => text,
+        Result::Err(error) => std::str::from_utf8(&prefix[..error.valid_up_to()]).unwrap(),
+    };
+    text.chars().next().map(|c| (c, c.len_utf8()))
+}
+
+fn keyword<'a>(keyword: &'static str) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
+    expect(
+        Expected::Token(keyword),
+        verify(identifier, move |n: &[u8]| n == keyword.as_bytes()),
+    )
+}
+
+fn let_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("let")(input)
+}
+
+fn letrec_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("letrec")(input)
+}
+
+fn and_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("and")(input)
+}
+
+fn in_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("in")(input)
+}
+
+fn case_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("case")(input)
 }
 
-fn expression1(input: &[u8]) -> IResult<&[u8], Expression> {
-    fn lambda_expression(input: &[u8]) -> IResult<&[u8], Expression> {
-        let (input, _) = terminated(lambda, multispace0)(input)?;
-        let (input, parameter) = terminated(
+fn of_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("of")(input)
+}
+
+fn true_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("true")(input)
+}
+
+fn false_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("false")(input)
+}
+
+fn fun_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("fun")(input)
+}
+
+fn forall_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("forall")(input)
+}
+
+fn def_keyword(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    keyword("def")(input)
+}
+
+/// Parses an integer literal. Integer literals are prefixed with `#` to
+/// distinguish them from de Bruijn indices, as in `#42` and `#-3`.
+fn integer_literal(input: &[u8]) -> IResult<&[u8], i64> {
+    preceded(hash, expect(Expected::Description("an integer"), i64))(input)
+}
+
+/// Parses a double-quoted string literal, with escape sequences `\\`, `\"`,
+/// `\n` and `\t`.
+fn string_literal(input: &[u8]) -> IResult<&[u8], Box<[u8]>> {
+    let (input, _) = quote(input)?;
+    let (input, chunks) = many0(alt((
+        is_not("\\\""),
+        preceded(
+            backslash,
             alt((
-                map(underscore, |_| Option::None),
-                map(name, |n| Option::Some(n.to_vec().into_boxed_slice())),
+                value(&b"\\"[..], backslash),
+                value(&b"\""[..], quote),
+                value(&b"\n"[..], symbol("n")),
+                value(&b"\t"[..], symbol("t")),
             )),
-            multispace0,
-        )(input)?;
-        let (input, _) = terminated(dot, multispace0)(input)?;
-        let (input, body) = expression1(input)?;
-        IResult::Ok((
-            input,
-            Expression::Abstraction {
+        ),
+    )))(input)?;
+    let (input, _) = quote(input)?;
+    IResult::Ok((input, chunks.concat().into_boxed_slice()))
+}
+
+fn boolean_literal(input: &[u8]) -> IResult<&[u8], bool> {
+    alt((value(true, true_keyword), value(false, false_keyword)))(input)
+}
+
+fn constant_expression(input: &[u8]) -> IResult<&[u8], Expression> {
+    map(
+        alt((
+            map(integer_literal, Constant::Integer),
+            map(string_literal, Constant::String),
+            map(boolean_literal, Constant::Boolean),
+        )),
+        |constant| Expression::Constant { constant },
+    )(input)
+}
+
+/// A parameter name, or `_` for an explicitly unnamed parameter.
+type Parameter = Option<Box<[u8]>>;
+
+/// A lambda abstraction parameter, with its optional type annotation, and the
+/// length of the input remaining at its start.
+type LambdaParameter = (usize, Parameter, Option<Expression>);
+
+fn parameter(input: &[u8]) -> IResult<&[u8], Parameter> {
+    alt((
+        map(name, |n| Option::Some(n.to_vec().into_boxed_slice())),
+        map(underscore, |_| Option::None),
+    ))(input)
+}
+
+/// Parser for the separator between the parameters and the body of a lambda
+/// abstraction.
+type Separator = for<'a> fn(&'a [u8]) -> IResult<&'a [u8], &'a [u8]>;
+
+/// Parses the introduction of a lambda abstraction, which is either `λ` or `\`
+/// with parameters separated from the body by `.`, or `fun` with parameters
+/// separated from the body by `=>`.
+fn lambda_introduction(input: &[u8]) -> IResult<&[u8], Separator> {
+    alt((
+        value(dot as Separator, terminated(lambda, trivia0)),
+        value(fat_arrow as Separator, terminated(fun_keyword, trivia0)),
+    ))(input)
+}
+
+/// Parses a parameter followed by the colon of its annotation, as in `x :`,
+/// and returns it with the input at the colon.
+fn annotated_parameter(input: &[u8]) -> IResult<&[u8], (Parameter, &[u8])> {
+    let (input, parameter) = terminated(parameter, trivia0)(input)?;
+    let (rest, _) = terminated(colon, trivia0)(input)?;
+    IResult::Ok((rest, (parameter, input)))
+}
+
+/// Parses a parenthesized parameter up to its annotation, as in `(x :`.
+fn parenthesized_parameter(input: &[u8]) -> IResult<&[u8], Parameter> {
+    let (input, _) = terminated(left_parenthesis, trivia0)(input)?;
+    let (input, parameter) = terminated(parameter, trivia0)(input)?;
+    let (input, _) = terminated(colon, trivia0)(input)?;
+    IResult::Ok((input, parameter))
+}
+
+/// Constructs the nested lambda abstractions binding `parameters` in order
+/// over `body`. The inner abstractions span from their parameter to `end`,
+/// whereas the outermost one is left to be located by the caller.
+fn abstractions(parameters: Vec<LambdaParameter>, body: Expression, end: usize) -> Expression {
+    parameters.into_iter().enumerate().rev().fold(
+        body,
+        |body, (position, (start, parameter, annotation))| {
+            let abstraction = Expression::Abstraction {
                 parameter,
+                annotation: annotation.map(Box::new),
                 body: Box::new(body),
-            },
-        ))
+            };
+            if position == 0 {
+                abstraction
+            } else {
+                locate(start, end, abstraction)
+            }
+        },
+    )
+}
+
+#[inline]
+fn locate(start: usize, end: usize, expression: Expression) -> Expression {
+    Expression::Located {
+        start,
+        end,
+        expression: Box::new(expression),
     }
+}
 
-    alt((lambda_expression, expression2))(input)
+/// Records the location of the expression parsed by `parser`.
+fn located<'a, F>(mut parser: F) -> impl FnMut(&'a [u8]) -> IResult<&'a [u8], Expression>
+where
+    F: FnMut(&'a [u8]) -> IResult<&'a [u8], Expression>,
+{
+    move |input: &'a [u8]| {
+        let (remaining, expression) = parser(input)?;
+        IResult::Ok((remaining, locate(input.len(), remaining.len(), expression)))
+    }
 }
 
-fn expression2(input: &[u8]) -> IResult<&[u8], Expression> {
-    let (input, mut terms) = separated_list1(multispace1, expression3)(input)?;
-    if terms.len() == 1 {
-        Result::Ok((input, terms.remove(0)))
-    } else {
-        let function = terms.remove(0);
-        Result::Ok((
-            input,
-            Expression::Application {
-                function: Box::new(function),
-                arguments: terms,
+/// The binding part of an expression whose body extends as far as possible,
+/// like `λx y.` or `let x = y in`.
+enum Prefix {
+    Abstractions(Vec<LambdaParameter>),
+    NamelessAbstraction {
+        annotation: Option<Expression>,
+    },
+    Let {
+        parameter: Parameter,
+        definition: Expression,
+    },
+    NamelessLet {
+        definition: Expression,
+    },
+    LetRec {
+        parameters: Vec<Parameter>,
+        definitions: Vec<Expression>,
+    },
+    NamelessLetRec {
+        definitions: Vec<Expression>,
+    },
+    Pi {
+        parameter: Parameter,
+        domain: Expression,
+    },
+    NamelessPi {
+        domain: Expression,
+    },
+}
+
+impl Prefix {
+    /// Constructs the expression with this prefix and `body`, located from
+    /// `start` to `end`.
+    fn complete(self, body: Expression, start: usize, end: usize) -> Expression {
+        let body = Box::new(body);
+        let expression = match self {
+            Prefix::Abstractions(parameters) => abstractions(parameters, *body, end),
+            Prefix::NamelessAbstraction { annotation } => Expression::NamelessAbstraction {
+                annotation: annotation.map(Box::new),
+                body,
+            },
+            Prefix::Let {
+                parameter,
+                definition,
+            } => Expression::Let {
+                parameter,
+                definition: Box::new(definition),
+                body,
+            },
+            Prefix::NamelessLet { definition } => Expression::NamelessLet {
+                definition: Box::new(definition),
+                body,
+            },
+            Prefix::LetRec {
+                parameters,
+                definitions,
+            } => Expression::LetRec {
+                parameters,
+                definitions,
+                body,
+            },
+            Prefix::NamelessLetRec { definitions } => {
+                Expression::NamelessLetRec { definitions, body }
+            }
+            Prefix::Pi { parameter, domain } => Expression::Pi {
+                parameter,
+                domain: Box::new(domain),
+                codomain: body,
             },
-        ))
+            Prefix::NamelessPi { domain } => Expression::NamelessPi {
+                domain: Box::new(domain),
+                codomain: body,
+            },
+        };
+        locate(start, end, expression)
     }
 }
 
-fn expression3(input: &[u8]) -> IResult<&[u8], Expression> {
-    fn variable_expression(input: &[u8]) -> IResult<&[u8], Expression> {
-        map(name, |n| Expression::Variable {
-            identifier: n.to_vec().into_boxed_slice(),
-        })(input)
-    }
+/// Determines whether `identifier` is reserved by the concrete syntax, in
+/// which case it cannot be used as a variable name.
+pub fn is_keyword(identifier: &[u8]) -> bool {
+    KEYWORDS.contains(&identifier)
+}
 
-    fn parenthesized_expression(input: &[u8]) -> IResult<&[u8], Expression> {
-        delimited(
-            left_parenthesis,
-            delimited(multispace0, expression1, multispace0),
-            right_parenthesis,
-        )(input)
-    }
+/// Determines whether `identifier` can be parsed as a variable name.
+pub fn is_name(identifier: &[u8]) -> bool {
+    matches!(name(identifier), IResult::Ok((remaining, _)) if remaining.is_empty())
+}
+
+fn name(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    expect(
+        Expected::Description("a name"),
+        verify(identifier, |n: &[u8]| !is_keyword(n)),
+    )(input)
+}
 
-    alt((variable_expression, parenthesized_expression))(input)
+/// Tokens may be separated by whitespace, line comments `-- ...` and nested
+/// block comments `{- ... -}`.
+///
+/// <expression> ::=
+///   | <expression1>
+///
+/// <expression1> ::=
+///   | <lambda> <parameters> `.` (<parameters> `.`)* <expression1>
+///   | `fun` <parameters> `=>` (<parameters> `=>`)* <expression1>
+///   | `letrec` <binding> (`and` <binding>)* `in` <expression1>
+///   | `let` <name> `=` <expression1> `in` <expression1>
+///   | `let` `_` `=` <expression1> `in` <expression1>
+///   | <pi> `(` <name> `:` <expression1> `)` `.` <expression1>
+///   | <pi> `(` `_` `:` <expression1> `)` `.` <expression1>
+///   | <expression2>
+///
+/// <expression2> ::=
+///   | <expression3> <arrow> <expression2>
+///   | <expression3>
+///
+/// <expression3> ::=
+///   | <expression4>+
+///
+/// <binding> ::=
+///   | (<name> | `_`) `=` <expression1>
+///
+/// <parameters> ::=
+///   | (<name> | `_`) `:` <expression2>
+///   | <parameter>+
+///
+/// <parameter> ::=
+///   | <name>
+///   | `_`
+///   | `(` (<name> | `_`) `:` <expression1> `)`
+///
+/// <expression4> ::=
+///   | <name>
+///   | <constant>
+///   | `(` <expression1> `)`
+///   | `case` <expression1> `of` `{` [<branch> (`|` <branch>)*] `}`
+///
+/// <branch> ::=
+///   | <name> (<name> | `_`)* `=>` <expression1>
+///
+/// <constant> ::=
+///   | `#` <integer>
+///   | <string>
+///   | `true`
+///   | `false`
+///
+/// <lambda> ::= `λ` | `\`
+///
+/// <pi> ::= `Π` | `forall`
+///
+/// <arrow> ::= `→` | `->`
+fn expression(input: &[u8]) -> IResult<&[u8], Expression> {
+    ExpressionParser::new(Syntax::Named).parse(input)
 }
 
 /// <mixed-expression> ::=
 ///   | <mixed-expression1>
 ///
 /// <mixed-expresion1> ::=
-///   | `λ` <name> `.` <mixed-expression1>
-///   | `λ` `_` `.` <mixed-expression1>
-///   | `λ` `.` <mixed-expression1>
+///   | <lambda> <mixed-parameters> `.` (<mixed-parameters> `.`)* <mixed-expression1>
+///   | <lambda> [`:` <mixed-expression2>] `.` <mixed-expression1>
+///   | `fun` <mixed-parameters> `=>` (<mixed-parameters> `=>`)* <mixed-expression1>
+///   | `fun` [`:` <mixed-expression2>] `=>` <mixed-expression1>
+///   | `letrec` <mixed-binding> (`and` <mixed-binding>)* `in` <mixed-expression1>
+///   | `letrec` `=` <mixed-expression1> (`and` `=` <mixed-expression1>)* `in` <mixed-expression1>
+///   | `let` <name> `=` <mixed-expression1> `in` <mixed-expression1>
+///   | `let` `_` `=` <mixed-expression1> `in` <mixed-expression1>
+///   | `let` `=` <mixed-expression1> `in` <mixed-expression1>
+///   | <pi> `(` <name> `:` <mixed-expression1> `)` `.` <mixed-expression1>
+///   | <pi> `(` `_` `:` <mixed-expression1> `)` `.` <mixed-expression1>
+///   | <pi> `(` `:` <mixed-expression1> `)` `.` <mixed-expression1>
 ///   | <mixed-expression2>
 ///
 /// <mixed-expression2> ::=
-///   | <mixed-expression3>+
+///   | <mixed-expression3> <arrow> <mixed-expression2>
+///   | <mixed-expression3>
 ///
 /// <mixed-expression3> ::=
+///   | <mixed-expression4>+
+///
+/// <mixed-binding> ::=
+///   | (<name> | `_`) `=` <mixed-expression1>
+///
+/// <mixed-parameters> ::=
+///   | (<name> | `_`) `:` <mixed-expression2>
+///   | <mixed-parameter>+
+///
+/// <mixed-parameter> ::=
+///   | <name>
+///   | `_`
+///   | `(` (<name> | `_`) `:` <mixed-expression1> `)`
+///
+/// <mixed-expression4> ::=
 ///   | <name>
 ///   | <number>
+///   | <constant>
 ///   | `(` <mixed-expression1> `)`
+///   | `case` <mixed-expression1> `of` `{` [<mixed-branch> (`|` <mixed-branch>)*] `}`
+///
+/// <mixed-branch> ::=
+///   | <name> (<name> | `_`)* `=>` <mixed-expression1>
+///   | <name> `/` <number> `=>` <mixed-expression1>
 fn mixed_expression(input: &[u8]) -> IResult<&[u8], Expression> {
-    mixed_expression1(input)
+    ExpressionParser::new(Syntax::Mixed).parse(input)
 }
 
-fn mixed_expression1(input: &[u8]) -> IResult<&[u8], Expression> {
-    fn lambda_expression(input: &[u8]) -> IResult<&[u8], Expression> {
-        let (input, _) = terminated(lambda, multispace0)(input)?;
-        let (input, parameter) = opt(terminated(
-            alt((
-                map(underscore, |_| Option::None),
-                map(name, |n| Option::Some(n.to_vec().into_boxed_slice())),
-            )),
-            multispace0,
-        ))(input)?;
-        let (input, _) = terminated(dot, multispace0)(input)?;
-        let (input, body) = mixed_expression1(input)?;
-        match parameter {
-            Option::Some(parameter) => IResult::Ok((
-                input,
-                Expression::Abstraction {
-                    parameter,
-                    body: Box::new(body),
-                },
-            )),
-            Option::None => IResult::Ok((
-                input,
-                Expression::NamelessAbstraction {
-                    body: Box::new(body),
-                },
-            )),
+/// The grammars of expressions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Syntax {
+    /// The grammar of `<expression>`.
+    Named,
+    /// The grammar of `<mixed-expression>`.
+    Mixed,
+}
+
+impl Syntax {
+    /// Parses a term that nests no expression, that is a variable or a
+    /// constant. Variables are tried first since they are the most frequent,
+    /// and no constant or index starts like a name.
+    fn atom(self, input: &[u8]) -> IResult<&[u8], Expression> {
+        match self {
+            Syntax::Named => {
+                alt((located(variable_expression), located(constant_expression)))(input)
+            }
+            Syntax::Mixed => alt((
+                located(variable_expression),
+                located(nameless_variable_expression),
+                located(constant_expression),
+            ))(input),
         }
     }
 
-    alt((lambda_expression, mixed_expression2))(input)
+    fn branch_header(self, input: &[u8]) -> IResult<&[u8], BranchHeader> {
+        match self {
+            Syntax::Named => named_branch_header(input),
+            Syntax::Mixed => alt((nameless_branch_header, named_branch_header))(input),
+        }
+    }
 }
 
-fn mixed_expression2(input: &[u8]) -> IResult<&[u8], Expression> {
-    let (input, mut terms) = separated_list1(multispace1, mixed_expression3)(input)?;
-    if terms.len() == 1 {
-        Result::Ok((input, terms.remove(0)))
-    } else {
-        let function = terms.remove(0);
-        Result::Ok((
-            input,
-            Expression::Application {
-                function: Box::new(function),
-                arguments: terms,
-            },
-        ))
-    }
+fn variable_expression(input: &[u8]) -> IResult<&[u8], Expression> {
+    map(name, |n| Expression::Variable {
+        identifier: n.to_vec().into_boxed_slice(),
+    })(input)
 }
 
-fn mixed_expression3(input: &[u8]) -> IResult<&[u8], Expression> {
-    fn nameless_variable_expression(input: &[u8]) -> IResult<&[u8], Expression> {
-        map(u32, |n| Expression::NamelessVariable { index: n as usize })(input)
-    }
+fn nameless_variable_expression(input: &[u8]) -> IResult<&[u8], Expression> {
+    map(
+        expect(Expected::Description("a de Bruijn index"), u32),
+        |n| Expression::NamelessVariable { index: n as usize },
+    )(input)
+}
 
-    fn variable_expression(input: &[u8]) -> IResult<&[u8], Expression> {
-        map(name, |n| Expression::Variable {
-            identifier: n.to_vec().into_boxed_slice(),
-        })(input)
+/// The part of a case branch before its body.
+enum BranchHeader {
+    Named {
+        constructor: Box<[u8]>,
+        parameters: Vec<Parameter>,
+    },
+    Nameless {
+        constructor: Box<[u8]>,
+        arity: usize,
+    },
+}
+
+impl BranchHeader {
+    fn complete(self, body: Expression) -> CaseBranch {
+        match self {
+            BranchHeader::Named {
+                constructor,
+                parameters,
+            } => CaseBranch::Named {
+                constructor,
+                parameters,
+                body,
+            },
+            BranchHeader::Nameless { constructor, arity } => CaseBranch::Nameless {
+                constructor,
+                arity,
+                body,
+            },
+        }
     }
+}
 
-    fn parenthesized_expression(input: &[u8]) -> IResult<&[u8], Expression> {
-        delimited(
-            left_parenthesis,
-            delimited(multispace0, mixed_expression1, multispace0),
-            right_parenthesis,
-        )(input)
+/// Parses the header of a case branch `<name> (<name> | `_`)* =>`.
+fn named_branch_header(input: &[u8]) -> IResult<&[u8], BranchHeader> {
+    let (input, constructor) = terminated(name, trivia0)(input)?;
+    let (input, parameters) = many0(terminated(parameter, trivia0))(input)?;
+    let (input, _) = terminated(fat_arrow, trivia0)(input)?;
+    IResult::Ok((
+        input,
+        BranchHeader::Named {
+            constructor: constructor.to_vec().into_boxed_slice(),
+            parameters,
+        },
+    ))
+}
+
+/// Parses the header of a case branch `<name> / <number> =>`.
+fn nameless_branch_header(input: &[u8]) -> IResult<&[u8], BranchHeader> {
+    let (input, constructor) = terminated(name, trivia0)(input)?;
+    let (input, _) = terminated(slash, trivia0)(input)?;
+    let (input, arity) = terminated(u32, trivia0)(input)?;
+    let (input, _) = terminated(fat_arrow, trivia0)(input)?;
+    IResult::Ok((
+        input,
+        BranchHeader::Nameless {
+            constructor: constructor.to_vec().into_boxed_slice(),
+            arity: arity as usize,
+        },
+    ))
+}
+
+/// The parameters of lambda abstractions parsed so far, in groups ended by
+/// their separator.
+struct ParameterGroups<'a> {
+    separator: Separator,
+    /// The parameters of the complete groups.
+    parameters: Vec<LambdaParameter>,
+    /// The parameters of the group being parsed, which starts at `start`.
+    group: Vec<LambdaParameter>,
+    start: &'a [u8],
+}
+
+/// The bindings of a recursive let-binding group parsed so far, which are
+/// nameless if not `named`.
+struct Bindings {
+    named: bool,
+    parameters: Vec<Parameter>,
+    definitions: Vec<Expression>,
+}
+
+impl Bindings {
+    fn prefix(self) -> Prefix {
+        if self.named {
+            Prefix::LetRec {
+                parameters: self.parameters,
+                definitions: self.definitions,
+            }
+        } else {
+            Prefix::NamelessLetRec {
+                definitions: self.definitions,
+            }
+        }
+    }
+}
+
+/// The constructs being parsed, each awaiting the sub-expression parsed
+/// within it.
+enum Frame<'a> {
+    /// The failure of an alternative that was not taken, merged into the
+    /// failure of the one that was, if it fails.
+    Alternative(Failed<'a>),
+    /// The prefixes of an expression, with the next prefix being parsed from
+    /// `attempt`, or else its body.
+    Prefixes {
+        prefixes: Vec<(usize, Prefix)>,
+        attempt: Option<&'a [u8]>,
+    },
+    /// The annotation of the single parameter of `λx : A.`, starting at
+    /// `start`.
+    Annotation {
+        separator: Separator,
+        start: usize,
+        parameter: Parameter,
+    },
+    /// The annotation of the parenthesized parameter `(x : A)` at `start`,
+    /// which failed to parse as an unannotated parameter for `failure`.
+    ParenthesizedParameter {
+        groups: ParameterGroups<'a>,
+        start: &'a [u8],
+        parameter: Parameter,
+        failure: Failure<&'a [u8]>,
+    },
+    /// The annotation of the nameless abstraction `λ : A.`, whose colon is at
+    /// `colon`.
+    NamelessAnnotation {
+        separator: Separator,
+        colon: &'a [u8],
+    },
+    /// The definition of a let-binding, which is nameless without a
+    /// `parameter`.
+    Let {
+        parameter: Option<Parameter>,
+    },
+    /// The definition of `parameter` in a recursive let-binding group, which
+    /// follows the `and` at `and`, if any.
+    LetRec {
+        bindings: Bindings,
+        parameter: Option<Parameter>,
+        and: Option<&'a [u8]>,
+    },
+    /// The domain of a dependent function type, which is nameless without a
+    /// `parameter`.
+    Pi {
+        parameter: Option<Parameter>,
+    },
+    /// A chain of arrows from `start`, with the domain after the arrow between
+    /// `arrow` being parsed, if any.
+    Arrows {
+        start: usize,
+        domains: Vec<(usize, Expression)>,
+        codomain: Option<Expression>,
+        arrow: Option<(&'a [u8], &'a [u8])>,
+    },
+    /// The terms of an application from `start`, with the term after the
+    /// trivia at `separator` being parsed, if any.
+    Application {
+        start: usize,
+        terms: Vec<Expression>,
+        separator: Option<&'a [u8]>,
+    },
+    Parenthesized,
+    /// The scrutinee of a case expression from `start`.
+    Scrutinee {
+        start: usize,
+    },
+    /// The body of a case branch, which is dropped along with the rest of the
+    /// input from `restore` if it fails to parse.
+    Branch {
+        start: usize,
+        scrutinee: Expression,
+        branches: Vec<CaseBranch>,
+        header: BranchHeader,
+        restore: &'a [u8],
+    },
+}
+
+/// States of the parser of expressions.
+enum State<'a> {
+    /// Parses an `<expression1>`.
+    Expression(&'a [u8]),
+    /// Parses an `<expression2>`.
+    Arrows(&'a [u8]),
+    /// Parses an `<expression3>`.
+    Application(&'a [u8]),
+    /// Parses an `<expression4>`.
+    Term(&'a [u8]),
+    /// Hands a parsed expression to the innermost frame.
+    Parsed(&'a [u8], Expression),
+    /// Hands a parsed prefix to the innermost frame, which parses prefixes.
+    ParsedPrefix(&'a [u8], Prefix),
+    /// Unwinds the innermost frame.
+    Failed(Failed<'a>),
+}
+
+/// The failures of the parser of expressions. Those of prefixes, atoms and
+/// terms are frequent and mostly discarded, so where a glance at the input
+/// shows that they fail, they are only constructed when needed.
+enum Failed<'a> {
+    /// A failure constructed as it occurred.
+    Failure(Failure<&'a [u8]>),
+    /// No prefix can be parsed at the start of the input.
+    Prefix(&'a [u8]),
+    /// No atom can be parsed at the start of the input.
+    Atom(&'a [u8]),
+    /// No term can be parsed at the start of the input.
+    Term(&'a [u8]),
+}
+
+type Step<'a> = Result<State<'a>, nom::Err<Failure<&'a [u8]>>>;
+
+/// Parses expressions of `syntax`, keeping the constructs being parsed as
+/// frames on an explicit stack rather than on the call stack, so that the
+/// nesting of expressions is bounded only by heap memory. A failure unwinds
+/// the frames until one recovers from it, as the optional parts of the
+/// grammar do.
+struct ExpressionParser<'a> {
+    syntax: Syntax,
+    frames: Vec<Frame<'a>>,
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn new(syntax: Syntax) -> ExpressionParser<'a> {
+        ExpressionParser {
+            syntax,
+            frames: Vec::new(),
+        }
+    }
+
+    fn parse(mut self, input: &'a [u8]) -> IResult<&'a [u8], Expression> {
+        let mut state = State::Expression(input);
+        loop {
+            let step = match state {
+                State::Expression(input) => self.prefixes(Vec::new(), input),
+                State::Arrows(input) => {
+                    self.frames.push(Frame::Arrows {
+                        start: input.len(),
+                        domains: Vec::new(),
+                        codomain: Option::None,
+                        arrow: Option::None,
+                    });
+                    Result::Ok(State::Application(input))
+                }
+                State::Application(input) => {
+                    self.frames.push(Frame::Application {
+                        start: input.len(),
+                        terms: Vec::new(),
+                        separator: Option::None,
+                    });
+                    Result::Ok(State::Term(input))
+                }
+                State::Term(input) => self.term(input),
+                State::Parsed(input, expression) => match self.pop() {
+                    Option::Some(frame) => self.resume(frame, input, expression),
+                    Option::None => return IResult::Ok((input, expression)),
+                },
+                State::ParsedPrefix(input, prefix) => match self.pop() {
+                    Option::Some(Frame::Prefixes {
+                        mut prefixes,
+                        attempt: Option::Some(start),
+                    }) => {
+                        prefixes.push((start.len(), prefix));
+                        self.prefixes(prefixes, input)
+                    }
+                    _ => unreachable!("prefixes are parsed within expressions"),
+                },
+                State::Failed(failed) => match self.frames.pop() {
+                    Option::Some(Frame::Alternative(alternative)) => {
+                        let failure = nom::error::ParseError::or(
+                            self.failure(failed),
+                            self.failure(alternative),
+                        );
+                        Result::Ok(State::Failed(Failed::Failure(failure)))
+                    }
+                    Option::Some(frame) => self
+                        .recover(frame, failed)
+                        .unwrap_or_else(|failed| Result::Ok(State::Failed(failed))),
+                    Option::None => return IResult::Err(nom::Err::Error(self.failure(failed))),
+                },
+            };
+            state = match step {
+                Result::Ok(state) => state,
+                Result::Err(nom::Err::Error(failure)) => State::Failed(Failed::Failure(failure)),
+                Result::Err(error) => return IResult::Err(error),
+            };
+        }
+    }
+
+    /// Pops the innermost frame awaiting a parsed expression or prefix. The
+    /// failures of the alternatives not taken are discarded along the way.
+    fn pop(&mut self) -> Option<Frame<'a>> {
+        while let Option::Some(frame) = self.frames.pop() {
+            if !matches!(frame, Frame::Alternative(_)) {
+                return Option::Some(frame);
+            }
+        }
+        Option::None
+    }
+
+    /// Records the failure of an alternative that is not taken, as `result`.
+    fn alternative<O>(&mut self, result: IResult<&'a [u8], O>) {
+        if let IResult::Err(nom::Err::Error(failure)) = result {
+            self.frames
+                .push(Frame::Alternative(Failed::Failure(failure)));
+        }
+    }
+
+    /// Constructs the failure `failed`, if it was deferred.
+    fn failure(&self, failed: Failed<'a>) -> Failure<&'a [u8]> {
+        match failed {
+            Failed::Failure(failure) => failure,
+            Failed::Prefix(input) => match introduction(input) {
+                IResult::Err(nom::Err::Error(failure)) => failure,
+                _ => unreachable!("failures are deferred where no prefix starts"),
+            },
+            Failed::Atom(input) => match self.syntax.atom(input) {
+                IResult::Err(nom::Err::Error(failure)) => failure,
+                _ => unreachable!("failures are deferred where no atom starts"),
+            },
+            Failed::Term(input) => match (self.syntax.atom(input), opening(input)) {
+                (IResult::Err(nom::Err::Error(failure)), IResult::Err(nom::Err::Error(other))) => {
+                    nom::error::ParseError::or(failure, other)
+                }
+                _ => unreachable!("failures are deferred where no term starts"),
+            },
+        }
+    }
+
+    /// Parses the prefixes of an expression from `input`, after `prefixes`.
+    fn prefixes(&mut self, prefixes: Vec<(usize, Prefix)>, input: &'a [u8]) -> Step<'a> {
+        if may_introduce_prefix(input) {
+            self.frames.push(Frame::Prefixes {
+                prefixes,
+                attempt: Option::Some(input),
+            });
+            self.prefix(input)
+        } else {
+            self.frames.push(Frame::Prefixes {
+                prefixes,
+                attempt: Option::None,
+            });
+            self.frames.push(Frame::Alternative(Failed::Prefix(input)));
+            Result::Ok(State::Arrows(input))
+        }
+    }
+
+    /// Parses the prefix at the start of `input`.
+    fn prefix(&mut self, input: &'a [u8]) -> Step<'a> {
+        let (input, introduction) = introduction(input)?;
+        match introduction {
+            Introduction::Lambda(separator) => self.abstractions(separator, input),
+            Introduction::Let => {
+                let (input, parameter) = match self.syntax {
+                    Syntax::Named => map(terminated(parameter, trivia0), Option::Some)(input)?,
+                    Syntax::Mixed => opt(terminated(parameter, trivia0))(input)?,
+                };
+                let (input, _) = terminated(equals, trivia0)(input)?;
+                self.frames.push(Frame::Let { parameter });
+                Result::Ok(State::Expression(input))
+            }
+            Introduction::LetRec => {
+                let named = match self.syntax {
+                    Syntax::Named => true,
+                    Syntax::Mixed => match terminated(parameter, trivia0)(input) {
+                        IResult::Ok(_) => {
+                            self.alternative(terminated(equals, trivia0)(input));
+                            true
+                        }
+                        IResult::Err(nom::Err::Error(failure)) => {
+                            self.frames
+                                .push(Frame::Alternative(Failed::Failure(failure)));
+                            false
+                        }
+                        IResult::Err(error) => return Result::Err(error),
+                    },
+                };
+                let bindings = Bindings {
+                    named,
+                    parameters: Vec::new(),
+                    definitions: Vec::new(),
+                };
+                self.binding(bindings, Option::None, input)
+            }
+            Introduction::Pi => {
+                let (input, _) = terminated(left_parenthesis, trivia0)(input)?;
+                let (input, parameter) = match self.syntax {
+                    Syntax::Named => map(terminated(parameter, trivia0), Option::Some)(input)?,
+                    Syntax::Mixed => opt(terminated(parameter, trivia0))(input)?,
+                };
+                let (input, _) = terminated(colon, trivia0)(input)?;
+                self.frames.push(Frame::Pi { parameter });
+                Result::Ok(State::Expression(input))
+            }
+        }
+    }
+
+    /// Parses the parameters of lambda abstractions after their
+    /// introduction, up to and including the last `separator` before their
+    /// body. Several parameters may be listed at once, as in `λx y _ z.`, and
+    /// the list may be continued after a separator, as in `λx. y.`. A single
+    /// parameter may be annotated, as in `λx : T.`, and parenthesized
+    /// parameters are annotated, as in `λ(x : T) y.`.
+    fn abstractions(&mut self, separator: Separator, input: &'a [u8]) -> Step<'a> {
+        if self.syntax == Syntax::Mixed {
+            // Nameless abstractions have no parameter, but may be annotated
+            match alt((value((), parameter), value((), left_parenthesis)))(input) {
+                IResult::Ok(_) => self.alternative(separator(input)),
+                IResult::Err(nom::Err::Error(failure)) => {
+                    self.frames
+                        .push(Frame::Alternative(Failed::Failure(failure)));
+                    return match terminated(colon, trivia0)(input) {
+                        IResult::Ok((annotation, _)) => {
+                            self.frames.push(Frame::NamelessAnnotation {
+                                separator,
+                                colon: input,
+                            });
+                            Result::Ok(State::Arrows(annotation))
+                        }
+                        IResult::Err(nom::Err::Error(_)) => {
+                            nameless_abstraction(separator, Option::None, input)
+                        }
+                        IResult::Err(error) => Result::Err(error),
+                    };
+                }
+                IResult::Err(error) => return Result::Err(error),
+            }
+        }
+        match annotated_parameter(input) {
+            IResult::Ok((annotation, (parameter, colon))) => {
+                // A group of unannotated parameters would end at the colon
+                self.alternative(separator(colon));
+                self.frames.push(Frame::Annotation {
+                    separator,
+                    start: input.len(),
+                    parameter,
+                });
+                Result::Ok(State::Arrows(annotation))
+            }
+            IResult::Err(nom::Err::Error(failure)) => {
+                self.frames
+                    .push(Frame::Alternative(Failed::Failure(failure)));
+                let groups = ParameterGroups {
+                    separator,
+                    parameters: Vec::new(),
+                    group: Vec::new(),
+                    start: input,
+                };
+                self.parameter_groups(groups, input, Option::None)
+            }
+            IResult::Err(error) => Result::Err(error),
+        }
+    }
+
+    /// Parses the parameters of `groups` from `input`, unless no further
+    /// parameter of the current group can be parsed there for `failure`. The
+    /// first group must be complete, whereas the parameters of the others
+    /// are dropped if they are not followed by a separator.
+    fn parameter_groups(
+        &mut self,
+        mut groups: ParameterGroups<'a>,
+        mut input: &'a [u8],
+        mut failure: Option<Failure<&'a [u8]>>,
+    ) -> Step<'a> {
+        loop {
+            let parameter_failure = match failure.take() {
+                Option::Some(failure) => failure,
+                Option::None => match parameter(input) {
+                    IResult::Ok((rest, parameter)) => {
+                        groups.group.push((input.len(), parameter, Option::None));
+                        let (rest, _) = trivia0(rest)?;
+                        input = rest;
+                        continue;
+                    }
+                    IResult::Err(nom::Err::Error(parameter_failure)) => {
+                        match parenthesized_parameter(input) {
+                            IResult::Ok((annotation, parameter)) => {
+                                self.frames.push(Frame::ParenthesizedParameter {
+                                    groups,
+                                    start: input,
+                                    parameter,
+                                    failure: parameter_failure,
+                                });
+                                return Result::Ok(State::Expression(annotation));
+                            }
+                            IResult::Err(nom::Err::Error(failure)) => {
+                                nom::error::ParseError::or(parameter_failure, failure)
+                            }
+                            IResult::Err(error) => return Result::Err(error),
+                        }
+                    }
+                    IResult::Err(error) => return Result::Err(error),
+                },
+            };
+            let separated = if groups.group.is_empty() {
+                IResult::Err(nom::Err::Error(parameter_failure))
+            } else {
+                terminated(groups.separator, trivia0)(input)
+            };
+            match separated {
+                IResult::Ok((rest, _)) => {
+                    groups.parameters.append(&mut groups.group);
+                    groups.start = rest;
+                    input = rest;
+                }
+                IResult::Err(nom::Err::Error(failure)) if groups.parameters.is_empty() => {
+                    return Result::Err(nom::Err::Error(failure));
+                }
+                IResult::Err(nom::Err::Error(_)) => {
+                    let prefix = Prefix::Abstractions(groups.parameters);
+                    return Result::Ok(State::ParsedPrefix(groups.start, prefix));
+                }
+                IResult::Err(error) => return Result::Err(error),
+            }
+        }
     }
 
+    /// Parses a binding of a recursive let-binding group from `input`, which
+    /// follows the `and` at `and`, if any.
+    fn binding(&mut self, bindings: Bindings, and: Option<&'a [u8]>, input: &'a [u8]) -> Step<'a> {
+        let named = bindings.named;
+        let binding = |input: &'a [u8]| -> IResult<&'a [u8], Option<Parameter>> {
+            let (input, parameter) = if named {
+                map(terminated(parameter, trivia0), Option::Some)(input)?
+            } else {
+                (input, Option::None)
+            };
+            let (input, _) = terminated(equals, trivia0)(input)?;
+            IResult::Ok((input, parameter))
+        };
+        match binding(input) {
+            IResult::Ok((definition, parameter)) => {
+                self.frames.push(Frame::LetRec {
+                    bindings,
+                    parameter,
+                    and,
+                });
+                Result::Ok(State::Expression(definition))
+            }
+            IResult::Err(nom::Err::Error(failure)) => match and {
+                Option::Some(and) => bindings_end(bindings, and),
+                Option::None => Result::Err(nom::Err::Error(failure)),
+            },
+            IResult::Err(error) => Result::Err(error),
+        }
+    }
+
+    /// Parses the term at the start of `input`.
+    fn term(&mut self, input: &'a [u8]) -> Step<'a> {
+        if !may_start_term(input) {
+            return Result::Ok(State::Failed(Failed::Term(input)));
+        }
+        let failed = if may_open_term(input) {
+            Failed::Atom(input)
+        } else {
+            match self.syntax.atom(input) {
+                IResult::Ok((rest, term)) => return Result::Ok(State::Parsed(rest, term)),
+                IResult::Err(nom::Err::Error(failure)) => Failed::Failure(failure),
+                IResult::Err(error) => return Result::Err(error),
+            }
+        };
+        match opening(input) {
+            IResult::Ok((rest, parenthesized)) => {
+                self.frames.push(Frame::Alternative(failed));
+                self.frames.push(if parenthesized {
+                    Frame::Parenthesized
+                } else {
+                    Frame::Scrutinee { start: input.len() }
+                });
+                Result::Ok(State::Expression(rest))
+            }
+            IResult::Err(nom::Err::Error(other)) => Result::Err(nom::Err::Error(
+                nom::error::ParseError::or(self.failure(failed), other),
+            )),
+            IResult::Err(error) => Result::Err(error),
+        }
+    }
+
+    /// Parses a case branch from `input`. The branches end at `restore` if it
+    /// fails to parse.
+    fn branch(
+        &mut self,
+        start: usize,
+        scrutinee: Expression,
+        branches: Vec<CaseBranch>,
+        restore: &'a [u8],
+        input: &'a [u8],
+    ) -> Step<'a> {
+        match self.syntax.branch_header(input) {
+            IResult::Ok((body, header)) => {
+                self.frames.push(Frame::Branch {
+                    start,
+                    scrutinee,
+                    branches,
+                    header,
+                    restore,
+                });
+                Result::Ok(State::Expression(body))
+            }
+            IResult::Err(nom::Err::Error(_)) => case_end(start, scrutinee, branches, restore),
+            IResult::Err(error) => Result::Err(error),
+        }
+    }
+
+    /// Continues parsing the construct of `frame` after the `expression`
+    /// parsed within it, which ends at `input`.
+    fn resume(&mut self, frame: Frame<'a>, input: &'a [u8], expression: Expression) -> Step<'a> {
+        match frame {
+            Frame::Alternative(_) => unreachable!("alternatives await no expression"),
+            Frame::Prefixes {
+                prefixes,
+                attempt: _,
+            } => {
+                let mut expression = expression;
+                for (start, prefix) in prefixes.into_iter().rev() {
+                    expression = prefix.complete(expression, start, input.len());
+                }
+                Result::Ok(State::Parsed(input, expression))
+            }
+            Frame::Annotation {
+                separator,
+                start,
+                parameter,
+            } => {
+                let (input, _) = trivia0(input)?;
+                let (input, _) = terminated(separator, trivia0)(input)?;
+                let groups = ParameterGroups {
+                    separator,
+                    parameters: vec![(start, parameter, Option::Some(expression))],
+                    group: Vec::new(),
+                    start: input,
+                };
+                self.parameter_groups(groups, input, Option::None)
+            }
+            Frame::ParenthesizedParameter {
+                mut groups,
+                start,
+                parameter,
+                failure,
+            } => {
+                let closed = preceded(trivia0, right_parenthesis)(input);
+                match closed {
+                    IResult::Ok((rest, _)) => {
+                        groups
+                            .group
+                            .push((start.len(), parameter, Option::Some(expression)));
+                        let (rest, _) = trivia0(rest)?;
+                        self.parameter_groups(groups, rest, Option::None)
+                    }
+                    IResult::Err(nom::Err::Error(other)) => {
+                        let failure = nom::error::ParseError::or(failure, other);
+                        self.parameter_groups(groups, start, Option::Some(failure))
+                    }
+                    IResult::Err(error) => Result::Err(error),
+                }
+            }
+            Frame::NamelessAnnotation {
+                separator,
+                colon: _,
+            } => {
+                let (input, _) = trivia0(input)?;
+                nameless_abstraction(separator, Option::Some(expression), input)
+            }
+            Frame::Let { parameter } => {
+                let (input, _) = trivia0(input)?;
+                let (input, _) = terminated(in_keyword, trivia0)(input)?;
+                let prefix = match parameter {
+                    Option::Some(parameter) => Prefix::Let {
+                        parameter,
+                        definition: expression,
+                    },
+                    Option::None => Prefix::NamelessLet {
+                        definition: expression,
+                    },
+                };
+                Result::Ok(State::ParsedPrefix(input, prefix))
+            }
+            Frame::LetRec {
+                mut bindings,
+                parameter,
+                and: _,
+            } => {
+                bindings.parameters.extend(parameter);
+                bindings.definitions.push(expression);
+                let (input, _) = trivia0(input)?;
+                match terminated(and_keyword, trivia0)(input) {
+                    IResult::Ok((rest, _)) => self.binding(bindings, Option::Some(input), rest),
+                    IResult::Err(nom::Err::Error(_)) => bindings_end(bindings, input),
+                    IResult::Err(error) => Result::Err(error),
+                }
+            }
+            Frame::Pi { parameter } => {
+                let (input, _) = trivia0(input)?;
+                let (input, _) = terminated(right_parenthesis, trivia0)(input)?;
+                let (input, _) = terminated(dot, trivia0)(input)?;
+                let prefix = match parameter {
+                    Option::Some(parameter) => Prefix::Pi {
+                        parameter,
+                        domain: expression,
+                    },
+                    Option::None => Prefix::NamelessPi { domain: expression },
+                };
+                Result::Ok(State::ParsedPrefix(input, prefix))
+            }
+            Frame::Arrows {
+                mut start,
+                mut domains,
+                codomain,
+                arrow: parsed_arrow,
+            } => {
+                if let (Option::Some(domain), Option::Some((_, after))) = (codomain, parsed_arrow) {
+                    domains.push((start, domain));
+                    start = after.len();
+                }
+                match delimited(trivia0, arrow, trivia0)(input) {
+                    IResult::Ok((rest, _)) => {
+                        self.frames.push(Frame::Arrows {
+                            start,
+                            domains,
+                            codomain: Option::Some(expression),
+                            arrow: Option::Some((input, rest)),
+                        });
+                        Result::Ok(State::Application(rest))
+                    }
+                    IResult::Err(nom::Err::Error(_)) => {
+                        Result::Ok(arrows_end(domains, expression, input))
+                    }
+                    IResult::Err(error) => Result::Err(error),
+                }
+            }
+            Frame::Application {
+                start,
+                mut terms,
+                separator: _,
+            } => {
+                terms.push(expression);
+                match trivia1(input) {
+                    IResult::Ok((rest, _)) => {
+                        self.frames.push(Frame::Application {
+                            start,
+                            terms,
+                            separator: Option::Some(input),
+                        });
+                        Result::Ok(State::Term(rest))
+                    }
+                    IResult::Err(nom::Err::Error(_)) => {
+                        Result::Ok(application_end(start, terms, input))
+                    }
+                    IResult::Err(error) => Result::Err(error),
+                }
+            }
+            Frame::Parenthesized => {
+                let (input, _) = trivia0(input)?;
+                let (input, _) = right_parenthesis(input)?;
+                Result::Ok(State::Parsed(input, expression))
+            }
+            Frame::Scrutinee { start } => {
+                let (input, _) = trivia0(input)?;
+                let (input, _) = terminated(of_keyword, trivia0)(input)?;
+                let (input, _) = terminated(left_brace, trivia0)(input)?;
+                self.branch(start, expression, Vec::new(), input, input)
+            }
+            Frame::Branch {
+                start,
+                scrutinee,
+                mut branches,
+                header,
+                restore: _,
+            } => {
+                branches.push(header.complete(expression));
+                match delimited(trivia0, bar, trivia0)(input) {
+                    IResult::Ok((rest, _)) => self.branch(start, scrutinee, branches, input, rest),
+                    IResult::Err(nom::Err::Error(_)) => case_end(start, scrutinee, branches, input),
+                    IResult::Err(error) => Result::Err(error),
+                }
+            }
+        }
+    }
+
+    /// Recovers from `failed` within the construct of `frame`, if it is
+    /// optional there. Otherwise returns `failed` to unwind further.
+    fn recover(&mut self, frame: Frame<'a>, failed: Failed<'a>) -> Result<Step<'a>, Failed<'a>> {
+        match frame {
+            Frame::Prefixes {
+                prefixes,
+                attempt: Option::Some(start),
+            } => {
+                // The body starts where no further prefix could be parsed
+                self.frames.push(Frame::Prefixes {
+                    prefixes,
+                    attempt: Option::None,
+                });
+                self.frames.push(Frame::Alternative(failed));
+                Result::Ok(Result::Ok(State::Arrows(start)))
+            }
+            Frame::ParenthesizedParameter {
+                groups,
+                start,
+                parameter: _,
+                failure: parameter_failure,
+            } => {
+                let failure = nom::error::ParseError::or(parameter_failure, self.failure(failed));
+                Result::Ok(self.parameter_groups(groups, start, Option::Some(failure)))
+            }
+            Frame::NamelessAnnotation { separator, colon } => {
+                Result::Ok(nameless_abstraction(separator, Option::None, colon))
+            }
+            Frame::LetRec {
+                bindings,
+                parameter: _,
+                and: Option::Some(and),
+            } => Result::Ok(bindings_end(bindings, and)),
+            Frame::Arrows {
+                start: _,
+                domains,
+                codomain: Option::Some(codomain),
+                arrow: Option::Some((before, _)),
+            } => Result::Ok(Result::Ok(arrows_end(domains, codomain, before))),
+            Frame::Application {
+                start,
+                terms,
+                separator: Option::Some(separator),
+            } => Result::Ok(Result::Ok(application_end(start, terms, separator))),
+            Frame::Branch {
+                start,
+                scrutinee,
+                branches,
+                header: _,
+                restore,
+            } => Result::Ok(case_end(start, scrutinee, branches, restore)),
+            _ => Result::Err(failed),
+        }
+    }
+}
+
+/// The introduction of a prefix.
+#[derive(Clone)]
+enum Introduction {
+    Lambda(Separator),
+    Let,
+    LetRec,
+    Pi,
+}
+
+/// Parses the introduction of a prefix.
+fn introduction(input: &[u8]) -> IResult<&[u8], Introduction> {
     alt((
-        nameless_variable_expression,
-        variable_expression,
-        parenthesized_expression,
+        map(lambda_introduction, Introduction::Lambda),
+        value(Introduction::Let, terminated(let_keyword, trivia0)),
+        value(Introduction::LetRec, terminated(letrec_keyword, trivia0)),
+        value(Introduction::Pi, terminated(pi, trivia0)),
     ))(input)
 }
 
+/// Whether `input` may start with the introduction of a prefix. Otherwise
+/// every alternative of `introduction` fails at its start.
+fn may_introduce_prefix(input: &[u8]) -> bool {
+    match identifier(input) {
+        IResult::Ok((_, identifier)) => {
+            [&b"fun"[..], b"let", b"letrec", b"forall"].contains(&identifier)
+        }
+        IResult::Err(_) => ["λ", "\\", "Π"]
+            .iter()
+            .any(|token| input.starts_with(token.as_bytes())),
+    }
+}
+
+/// Parses the opening of a term that nests an expression, which is `(` for a
+/// parenthesized expression, or else `case` for a scrutinee.
+fn opening(input: &[u8]) -> IResult<&[u8], bool> {
+    alt((
+        value(true, terminated(left_parenthesis, trivia0)),
+        value(false, terminated(case_keyword, trivia0)),
+    ))(input)
+}
+
+/// Whether `input` starts with the opening of a term that nests an
+/// expression. Then every alternative of atoms fails at its start.
+fn may_open_term(input: &[u8]) -> bool {
+    match identifier(input) {
+        IResult::Ok((_, identifier)) => identifier == b"case",
+        IResult::Err(_) => input.starts_with(b"("),
+    }
+}
+
+/// Whether `input` may start with a term. Otherwise every alternative of
+/// atoms and `opening` fails at its start.
+fn may_start_term(input: &[u8]) -> bool {
+    match identifier(input) {
+        IResult::Ok((_, identifier)) => {
+            !is_keyword(identifier) || [&b"true"[..], b"false", b"case"].contains(&identifier)
+        }
+        IResult::Err(_) => matches!(
+            input.first(),
+            Option::Some(b'0'..=b'9' | b'#' | b'"' | b'(')
+        ),
+    }
+}
+
+/// Parses the separator of a nameless abstraction, annotated by
+/// `annotation` if any.
+fn nameless_abstraction(
+    separator: Separator,
+    annotation: Option<Expression>,
+    input: &[u8],
+) -> Step<'_> {
+    let (input, _) = terminated(separator, trivia0)(input)?;
+    Result::Ok(State::ParsedPrefix(
+        input,
+        Prefix::NamelessAbstraction { annotation },
+    ))
+}
+
+/// Parses the `in` ending the bindings of a recursive let-binding group.
+fn bindings_end(bindings: Bindings, input: &[u8]) -> Step<'_> {
+    let (input, _) = terminated(in_keyword, trivia0)(input)?;
+    Result::Ok(State::ParsedPrefix(input, bindings.prefix()))
+}
+
+/// Constructs the non-dependent function types denoted by a chain of
+/// arrows ending at `input`, which associate to the right.
+fn arrows_end(domains: Vec<(usize, Expression)>, codomain: Expression, input: &[u8]) -> State<'_> {
+    let mut codomain = codomain;
+    for (start, domain) in domains.into_iter().rev() {
+        codomain = locate(
+            start,
+            input.len(),
+            Expression::Pi {
+                parameter: Option::None,
+                domain: Box::new(domain),
+                codomain: Box::new(codomain),
+            },
+        );
+    }
+    State::Parsed(input, codomain)
+}
+
+/// Constructs the application of `terms` ending at `input`, if there are
+/// several.
+fn application_end(start: usize, mut terms: Vec<Expression>, input: &[u8]) -> State<'_> {
+    if terms.len() == 1 {
+        State::Parsed(input, terms.remove(0))
+    } else {
+        let function = terms.remove(0);
+        let application = Expression::Application {
+            function: Box::new(function),
+            arguments: terms,
+        };
+        State::Parsed(input, locate(start, input.len(), application))
+    }
+}
+
+/// Parses the `}` ending the branches of a case expression.
+fn case_end(
+    start: usize,
+    scrutinee: Expression,
+    branches: Vec<CaseBranch>,
+    input: &[u8],
+) -> Step<'_> {
+    let (input, _) = trivia0(input)?;
+    let (input, _) = right_brace(input)?;
+    let case = Expression::Case {
+        scrutinee: Box::new(scrutinee),
+        branches,
+    };
+    Result::Ok(State::Parsed(input, locate(start, input.len(), case)))
+}
+
+/// A named definition in a program.
+type Definition = (Box<[u8]>, Expression);
+
+/// <program> ::=
+///   | <definition>*
+///
+/// <definition> ::=
+///   | `def` <name> `=` <expression1> `;`
+fn program(input: &[u8]) -> IResult<&[u8], Vec<Definition>> {
+    fn definition(input: &[u8]) -> IResult<&[u8], Definition> {
+        let (input, _) = terminated(def_keyword, trivia0)(input)?;
+        // A definition is unambiguous once `def` is read, so report failures from here on
+        cut(|input| {
+            let (input, name) = terminated(name, trivia0)(input)?;
+            let (input, _) = terminated(equals, trivia0)(input)?;
+            let (input, expression) = terminated(expression, trivia0)(input)?;
+            let (input, _) = semicolon(input)?;
+            IResult::Ok((input, (name.to_vec().into_boxed_slice(), expression)))
+        })(input)
+    }
+
+    let (input, definitions) = many0(terminated(definition, trivia0))(input)?;
+    // `definition` would have parsed a `def` here, which is matched only so
+    // that it is reported as expected along with the end of input
+    let (input, _) = alt((
+        expect(Expected::Description("end of input"), eof),
+        def_keyword,
+    ))(input)?;
+    IResult::Ok((input, definitions))
+}
+
+/// Steps of lowering.
+enum Lowering<'e> {
+    Lower(&'e Expression),
+    /// Allocates the expression from its lowered sub-expressions.
+    Build(&'e Expression),
+}
+
+/// Allocates `expression` in `expressions`, recording the spans of located
+/// expressions in `source_map`. The source text is `length` bytes long.
 fn lower(
+    strings: &mut StringArena,
+    expressions: &mut ExpressionArena,
+    source_map: &mut SourceMap,
+    length: usize,
+    expression: &Expression,
+) -> ExpressionId {
+    let mut steps = vec![Lowering::Lower(expression)];
+    let mut lowered = Vec::new();
+    while let Option::Some(step) = steps.pop() {
+        match step {
+            Lowering::Lower(expression) => {
+                steps.push(Lowering::Build(expression));
+                steps.extend(expression.children().into_iter().rev().map(Lowering::Lower));
+            }
+            Lowering::Build(Expression::Located {
+                start,
+                end,
+                expression: _,
+            }) => {
+                let lowered_expression = *lowered.last().unwrap();
+                source_map.set(lowered_expression, Span::new(length - start, length - end));
+            }
+            Lowering::Build(expression) => {
+                let count = expression.children().len();
+                let children = lowered.split_off(lowered.len() - count);
+                lowered.push(build(strings, expressions, expression, children));
+            }
+        }
+    }
+    lowered.pop().unwrap()
+}
+
+/// Allocates `expression` in `expressions`, given its lowered
+/// sub-expressions `children`.
+fn build(
     strings: &mut StringArena,
     expressions: &mut ExpressionArena,
     expression: &Expression,
+    mut children: Vec<ExpressionId>,
 ) -> ExpressionId {
     match expression {
         Expression::Variable { identifier } => {
             let lowered_identifier = strings.intern(identifier);
             expressions.variable(lowered_identifier)
         }
-        Expression::NamelessVariable { index } => expressions.nameless_variable((*index).into()),
-        Expression::Abstraction { parameter, body } => {
+        Expression::NamelessVariable { index } => {
+            // Zero indices are rejected by scope checking
+            expressions.nameless_variable(expression::DeBruijnIndex::new_unchecked(*index))
+        }
+        Expression::Constant { constant } => {
+            let lowered_constant = match constant {
+                Constant::Integer(integer) => expression::Constant::Integer(*integer),
+                Constant::String(string) => expression::Constant::String(strings.intern(string)),
+                Constant::Boolean(boolean) => expression::Constant::Boolean(*boolean),
+            };
+            expressions.constant(lowered_constant)
+        }
+        Expression::Abstraction {
+            parameter,
+            annotation,
+            body: _,
+        } => {
+            let lowered_body = children.pop().unwrap();
+            let lowered_annotation = annotation.as_ref().map(|_| children[0]);
             let lowered_parameter = parameter.as_ref().map(|n| strings.intern(n));
-            let lowered_body = lower(strings, expressions, body);
-            expressions.abstraction(lowered_parameter, lowered_body)
+            expressions.annotated_abstraction(lowered_parameter, lowered_annotation, lowered_body)
         }
-        Expression::NamelessAbstraction { body } => {
-            let lowered_body = lower(strings, expressions, body);
-            expressions.nameless_abstraction(lowered_body)
+        Expression::NamelessAbstraction {
+            annotation,
+            body: _,
+        } => {
+            let lowered_body = children.pop().unwrap();
+            let lowered_annotation = annotation.as_ref().map(|_| children[0]);
+            expressions.annotated_nameless_abstraction(lowered_annotation, lowered_body)
         }
         Expression::Application {
-            function,
-            arguments,
+            function: _,
+            arguments: _,
+        } => {
+            let lowered_arguments = children.split_off(1);
+            expressions.application(children[0], lowered_arguments)
+        }
+        Expression::Let {
+            parameter,
+            definition: _,
+            body: _,
+        } => {
+            let lowered_parameter = parameter.as_ref().map(|n| strings.intern(n));
+            expressions.let_binding(lowered_parameter, children[0], children[1])
+        }
+        Expression::NamelessLet {
+            definition: _,
+            body: _,
+        } => expressions.nameless_let_binding(children[0], children[1]),
+        Expression::LetRec {
+            parameters,
+            definitions: _,
+            body: _,
+        } => {
+            let lowered_parameters = parameters
+                .iter()
+                .map(|parameter| parameter.as_ref().map(|n| strings.intern(n)))
+                .collect();
+            let lowered_body = children.pop().unwrap();
+            expressions.let_rec(lowered_parameters, children, lowered_body)
+        }
+        Expression::NamelessLetRec {
+            definitions: _,
+            body: _,
+        } => {
+            let lowered_body = children.pop().unwrap();
+            expressions.nameless_let_rec(children, lowered_body)
+        }
+        Expression::Pi {
+            parameter,
+            domain: _,
+            codomain: _,
         } => {
-            let lowered_function = lower(strings, expressions, function);
-            let mut lowered_arguments = Vec::with_capacity(arguments.len());
-            for argument in arguments {
-                let lowered_argument = lower(strings, expressions, argument);
-                lowered_arguments.push(lowered_argument);
+            let lowered_parameter = parameter.as_ref().map(|n| strings.intern(n));
+            expressions.pi(lowered_parameter, children[0], children[1])
+        }
+        Expression::NamelessPi {
+            domain: _,
+            codomain: _,
+        } => expressions.nameless_pi(children[0], children[1]),
+        Expression::Case {
+            scrutinee: _,
+            branches,
+        } => {
+            let lowered_branches = branches
+                .iter()
+                .zip(&children[1..])
+                .map(|(branch, &lowered_body)| match branch {
+                    CaseBranch::Named {
+                        constructor,
+                        parameters,
+                        body: _,
+                    } => expression::CaseBranch::Named {
+                        constructor: strings.intern(constructor),
+                        parameters: parameters
+                            .iter()
+                            .map(|parameter| parameter.as_ref().map(|n| strings.intern(n)))
+                            .collect(),
+                        body: lowered_body,
+                    },
+                    CaseBranch::Nameless {
+                        constructor,
+                        arity,
+                        body: _,
+                    } => expression::CaseBranch::Nameless {
+                        constructor: strings.intern(constructor),
+                        arity: *arity,
+                        body: lowered_body,
+                    },
+                })
+                .collect();
+            expressions.case(children[0], lowered_branches)
+        }
+        Expression::Located {
+            start: _,
+            end: _,
+            expression: _,
+        } => unreachable!(),
+    }
+}
+
+impl Expression {
+    /// The immediate sub-expressions of this expression, in order.
+    fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Variable { identifier: _ } => vec![],
+            Expression::NamelessVariable { index: _ } => vec![],
+            Expression::Constant { constant: _ } => vec![],
+            Expression::Abstraction {
+                parameter: _,
+                annotation,
+                body,
+            }
+            | Expression::NamelessAbstraction { annotation, body } => {
+                annotation.as_deref().into_iter().chain([&**body]).collect()
+            }
+            Expression::Application {
+                function,
+                arguments,
+            } => [&**function].into_iter().chain(arguments).collect(),
+            Expression::Let {
+                parameter: _,
+                definition,
+                body,
+            }
+            | Expression::NamelessLet { definition, body } => vec![definition, body],
+            Expression::LetRec {
+                parameters: _,
+                definitions,
+                body,
+            }
+            | Expression::NamelessLetRec { definitions, body } => {
+                definitions.iter().chain([&**body]).collect()
+            }
+            Expression::Pi {
+                parameter: _,
+                domain,
+                codomain,
+            }
+            | Expression::NamelessPi { domain, codomain } => vec![domain, codomain],
+            Expression::Case {
+                scrutinee,
+                branches,
+            } => [&**scrutinee]
+                .into_iter()
+                .chain(branches.iter().map(|branch| match branch {
+                    CaseBranch::Named { body, .. } | CaseBranch::Nameless { body, .. } => body,
+                }))
+                .collect(),
+            Expression::Located {
+                start: _,
+                end: _,
+                expression,
+            } => vec![expression],
+        }
+    }
+
+    /// Moves the immediate sub-expressions out of this expression, leaving
+    /// placeholders in their stead.
+    fn take_children(&mut self) -> Vec<Expression> {
+        fn take(expression: &mut Expression) -> Expression {
+            std::mem::replace(expression, Expression::NamelessVariable { index: 0 })
+        }
+
+        match self {
+            Expression::Variable { identifier: _ } => vec![],
+            Expression::NamelessVariable { index: _ } => vec![],
+            Expression::Constant { constant: _ } => vec![],
+            Expression::Abstraction {
+                parameter: _,
+                annotation,
+                body,
+            }
+            | Expression::NamelessAbstraction { annotation, body } => {
+                let annotation = annotation
+                    .take()
+                    .map(|mut annotation| take(&mut annotation));
+                annotation.into_iter().chain([take(body)]).collect()
+            }
+            Expression::Application {
+                function,
+                arguments,
+            } => {
+                let mut children = std::mem::take(arguments);
+                children.push(take(function));
+                children
+            }
+            Expression::Let {
+                parameter: _,
+                definition,
+                body,
+            }
+            | Expression::NamelessLet { definition, body } => vec![take(definition), take(body)],
+            Expression::LetRec {
+                parameters: _,
+                definitions,
+                body,
+            }
+            | Expression::NamelessLetRec { definitions, body } => {
+                let mut children = std::mem::take(definitions);
+                children.push(take(body));
+                children
+            }
+            Expression::Pi {
+                parameter: _,
+                domain,
+                codomain,
+            }
+            | Expression::NamelessPi { domain, codomain } => vec![take(domain), take(codomain)],
+            Expression::Case {
+                scrutinee,
+                branches,
+            } => {
+                let mut children = vec![take(scrutinee)];
+                for branch in branches.iter_mut() {
+                    match branch {
+                        CaseBranch::Named { body, .. } | CaseBranch::Nameless { body, .. } => {
+                            children.push(take(body))
+                        }
+                    }
+                }
+                children
+            }
+            Expression::Located {
+                start: _,
+                end: _,
+                expression,
+            } => vec![take(expression)],
+        }
+    }
+}
+
+impl Drop for Expression {
+    fn drop(&mut self) {
+        // Deep expressions would overflow the call stack if dropped recursively
+        let mut pending = self.take_children();
+        while let Option::Some(mut expression) = pending.pop() {
+            pending.extend(expression.take_children());
+        }
+    }
+}
+
+impl ParseError {
+    fn new(source: &[u8], failure: Failure<&[u8]>) -> ParseError {
+        let offset = source.len() - failure.input.len();
+        let token = match identifier(failure.input) {
+            IResult::Ok((_, identifier)) => {
+                Option::Some(String::from_utf8_lossy(identifier).into_owned())
+            }
+            IResult::Err(_) => {
+                let digits = failure
+                    .input
+                    .iter()
+                    .take_while(|byte| byte.is_ascii_digit())
+                    .count();
+                if digits > 0 {
+                    Option::Some(String::from_utf8_lossy(&failure.input[..digits]).into_owned())
+                } else {
+                    String::from_utf8_lossy(failure.input)
+                        .chars()
+                        .next()
+                        .map(String::from)
+                }
+            }
+        };
+        let mut expected = failure.expected;
+        expected.sort();
+        expected.dedup();
+        ParseError::at(source, offset, token, ParseErrorKind::Syntax(expected))
+    }
+
+    /// Constructs the error reporting that the index of `scope_error` is
+    /// unbound, located by `source_map`.
+    fn scope(source: &[u8], source_map: &SourceMap, scope_error: ScopeError) -> ParseError {
+        let span = source_map.get(scope_error.expression()).unwrap();
+        let token = String::from_utf8_lossy(&source[span]).into_owned();
+        ParseError::at(
+            source,
+            span.start(),
+            Option::Some(token),
+            ParseErrorKind::Scope(scope_error),
+        )
+    }
+
+    fn at(source: &[u8], offset: usize, token: Option<String>, kind: ParseErrorKind) -> ParseError {
+        let position = LineIndex::new(source).position(offset);
+        let line_start = source[..offset]
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        let line_end = source[offset..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(source.len(), |newline| offset + newline);
+        let line = String::from_utf8_lossy(&source[line_start..line_end])
+            .trim_end_matches('\r')
+            .to_owned();
+        ParseError {
+            offset,
+            position,
+            token,
+            kind,
+            source_line: line,
+        }
+    }
+
+    /// The byte offset in the input at which parsing failed.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    #[inline]
+    pub fn line(&self) -> usize {
+        self.position.line()
+    }
+
+    #[inline]
+    pub fn column(&self) -> usize {
+        self.position.column()
+    }
+
+    /// The offending token, or `None` if the input ended unexpectedly.
+    #[inline]
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// The alternatives expected in place of the offending token, which are
+    /// empty for scope errors.
+    #[inline]
+    pub fn expected(&self) -> &[Expected] {
+        match &self.kind {
+            ParseErrorKind::Syntax(expected) => expected,
+            ParseErrorKind::Scope(_) => &[],
+        }
+    }
+
+    /// The scope error of the offending index, if parsing failed scope
+    /// checking rather than because of a syntax error.
+    #[inline]
+    pub fn scope_error(&self) -> Option<ScopeError> {
+        match &self.kind {
+            ParseErrorKind::Scope(scope_error) => Option::Some(*scope_error),
+            ParseErrorKind::Syntax(_) => Option::None,
+        }
+    }
+}
+
+impl std::fmt::Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expected::Token(token) => write!(f, "`{}`", token),
+            Expected::Description(description) => write!(f, "{}", description),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.position)?;
+        match &self.kind {
+            ParseErrorKind::Scope(scope_error) => write!(f, "{}", scope_error)?,
+            ParseErrorKind::Syntax(_) => {
+                write!(f, "unexpected ")?;
+                match &self.token {
+                    Option::Some(token) => write!(f, "`{}`", token)?,
+                    Option::None => write!(f, "end of input")?,
+                }
             }
-            expressions.application(lowered_function, lowered_arguments)
+        }
+        match self.expected() {
+            [] => {}
+            [expected] => write!(f, ", expected {}", expected)?,
+            [first, second] => write!(f, ", expected {} or {}", first, second)?,
+            [first, rest @ .., last] => {
+                write!(f, ", expected one of {}", first)?;
+                for expected in rest {
+                    write!(f, ", {}", expected)?;
+                }
+                write!(f, " or {}", last)?;
+            }
+        }
+        writeln!(f)?;
+        writeln!(f, "{}", self.source_line)?;
+        // Align the caret with the offending token, preserving tabs
+        for c in self.source_line.chars().take(self.position.column() - 1) {
+            write!(f, "{}", if c == '\t' { '\t' } else { ' ' })?;
+        }
+        let width = self.token.as_ref().map_or(1, |token| token.chars().count());
+        write!(f, "{}", "^".repeat(width))
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ParseOptions {
+    /// Options for parsing without further checks.
+    #[inline]
+    pub fn new() -> ParseOptions {
+        ParseOptions {
+            scope_check: Option::None,
+        }
+    }
+
+    /// Options for parsing, and then checking that every de Bruijn index is
+    /// bound by an enclosing binder or by a binder in `environment`.
+    #[inline]
+    pub fn with_scope_check(environment: Rc<ReferencingEnvironment>) -> ParseOptions {
+        ParseOptions {
+            scope_check: Option::Some(environment),
         }
     }
 }
 
 impl expression::Expression {
-    pub fn parse_expression<'a>(
+    pub fn parse_expression(
         strings: &mut StringArena,
         expressions: &mut ExpressionArena,
-        input: &'a [u8],
-    ) -> Result<ExpressionId, nom::error::Error<&'a [u8]>> {
-        match terminated(delimited(multispace0, expression, multispace0), eof)(input).finish() {
+        input: &[u8],
+    ) -> Result<ExpressionId, ParseError> {
+        Self::parse_expression_with_source_map(strings, expressions, &mut SourceMap::new(), input)
+    }
+
+    /// Parses `input` like [`parse_expression`](Self::parse_expression), and
+    /// records the spans of the parsed expressions in `source_map`.
+    pub fn parse_expression_with_source_map(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        source_map: &mut SourceMap,
+        input: &[u8],
+    ) -> Result<ExpressionId, ParseError> {
+        match terminated(
+            delimited(trivia0, expression, trivia0),
+            expect(Expected::Description("end of input"), eof),
+        )(input)
+        .finish()
+        {
             Result::Ok((_input, parsed)) => {
-                let lowered = lower(strings, expressions, &parsed);
+                let lowered = lower(strings, expressions, source_map, input.len(), &parsed);
+                for span in comment_spans(input) {
+                    source_map.add_comment(span);
+                }
                 Result::Ok(lowered)
             }
-            Result::Err(error) => Result::Err(error),
+            Result::Err(failure) => Result::Err(ParseError::new(input, failure)),
         }
     }
 
-    pub fn parse_mixed_expression<'a>(
+    pub fn parse_mixed_expression(
         strings: &mut StringArena,
         expressions: &mut ExpressionArena,
-        input: &'a [u8],
-    ) -> Result<ExpressionId, nom::error::Error<&'a [u8]>> {
-        match terminated(delimited(multispace0, mixed_expression, multispace0), eof)(input).finish()
+        input: &[u8],
+    ) -> Result<ExpressionId, ParseError> {
+        Self::parse_mixed_expression_with_source_map(
+            strings,
+            expressions,
+            &mut SourceMap::new(),
+            input,
+        )
+    }
+
+    /// Parses `input` like [`parse_mixed_expression`](Self::parse_mixed_expression),
+    /// and records the spans of the parsed expressions in `source_map`.
+    pub fn parse_mixed_expression_with_source_map(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        source_map: &mut SourceMap,
+        input: &[u8],
+    ) -> Result<ExpressionId, ParseError> {
+        match terminated(
+            delimited(trivia0, mixed_expression, trivia0),
+            expect(Expected::Description("end of input"), eof),
+        )(input)
+        .finish()
         {
             Result::Ok((_input, parsed)) => {
-                let lowered = lower(strings, expressions, &parsed);
+                let lowered = lower(strings, expressions, source_map, input.len(), &parsed);
+                for span in comment_spans(input) {
+                    source_map.add_comment(span);
+                }
                 Result::Ok(lowered)
             }
-            Result::Err(error) => Result::Err(error),
+            Result::Err(failure) => Result::Err(ParseError::new(input, failure)),
+        }
+    }
+}
+
+impl expression::Expression {
+    /// Parses `input` like
+    /// [`parse_mixed_expression_with_source_map`](Self::parse_mixed_expression_with_source_map),
+    /// and then performs the checks enabled in `options`, reporting every
+    /// offending expression.
+    pub fn parse_mixed_expression_with_options(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        source_map: &mut SourceMap,
+        options: &ParseOptions,
+        input: &[u8],
+    ) -> Result<ExpressionId, Vec<ParseError>> {
+        let expression =
+            Self::parse_mixed_expression_with_source_map(strings, expressions, source_map, input)
+                .map_err(|error| vec![error])?;
+        if let Option::Some(environment) = &options.scope_check {
+            let scope_errors = Self::check_scope(environment, expressions, expression);
+            if !scope_errors.is_empty() {
+                return Result::Err(
+                    scope_errors
+                        .into_iter()
+                        .map(|scope_error| ParseError::scope(input, source_map, scope_error))
+                        .collect(),
+                );
+            }
+        }
+        Result::Ok(expression)
+    }
+}
+
+impl Program {
+    pub fn parse_program(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        input: &[u8],
+    ) -> Result<Program, ParseError> {
+        Self::parse_program_with_source_map(strings, expressions, &mut SourceMap::new(), input)
+    }
+
+    /// Parses `input` like [`parse_program`](Self::parse_program), and
+    /// records the spans of the parsed expressions in `source_map`.
+    pub fn parse_program_with_source_map(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        source_map: &mut SourceMap,
+        input: &[u8],
+    ) -> Result<Program, ParseError> {
+        match preceded(trivia0, program)(input).finish() {
+            Result::Ok((_input, parsed)) => {
+                let mut lowered = Program::new();
+                for (name, expression) in parsed {
+                    let lowered_name = strings.intern(&name);
+                    let lowered_expression =
+                        lower(strings, expressions, source_map, input.len(), &expression);
+                    lowered.push(lowered_name, lowered_expression);
+                }
+                for span in comment_spans(input) {
+                    source_map.add_comment(span);
+                }
+                Result::Ok(lowered)
+            }
+            Result::Err(failure) => Result::Err(ParseError::new(input, failure)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expression::ExpressionArena, strings::StringArena};
+
+    use super::*;
+
+    fn parse_error(input: &str) -> ParseError {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        expression::Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            input.as_bytes(),
+        )
+        .unwrap_err()
+    }
+
+    fn check_parse_error(input: &str, expected: &str) {
+        assert_eq!(parse_error(input).to_string(), expected);
+    }
+
+    fn check_parse(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let e1 = expression::Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            input.as_bytes(),
+        )
+        .unwrap();
+        let e2 = expression::Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            expected.as_bytes(),
+        )
+        .unwrap();
+        assert!(expression::Expression::equals(
+            (&expressions, e1),
+            (&expressions, e2)
+        ));
+    }
+
+    #[test]
+    fn parse_desugars_multi_parameter_abstractions() {
+        check_parse("λx y _ z. x", "λx. λy. λ_. λz. x");
+        check_parse("λx. y. λ. x", "λx. λy. λ. x");
+        check_parse("λ(x : A) y. x", "λx : A. λy. x");
+    }
+
+    #[test]
+    fn parse_errors_locate_offending_token() {
+        let error = parse_error("λx.\n  (f x");
+        assert_eq!(error.offset(), 11);
+        assert_eq!(error.position(), Position::new(2, 7));
+        assert_eq!(error.token(), Option::None);
+        assert_eq!(error.expected(), [Expected::Token(")")]);
+
+        let error = parse_error("let x = y = z");
+        assert_eq!((error.line(), error.column()), (1, 11));
+        assert_eq!(error.token(), Option::Some("="));
+        assert_eq!(error.expected(), [Expected::Token("in")]);
+    }
+
+    #[test]
+    fn parse_errors_merge_expected_alternatives() {
+        let error = parse_error("λx. )");
+        assert_eq!(error.token(), Option::Some(")"));
+        assert!(error.expected().contains(&Expected::Token("λ")));
+        assert!(error.expected().contains(&Expected::Token("(")));
+        assert!(error.expected().contains(&Expected::Description("a name")));
+    }
+
+    #[test]
+    fn parse_errors_render_caret_diagnostics() {
+        check_parse_error(
+            "f )",
+            "1:3: unexpected `)`, expected end of input\nf )\n  ^",
+        );
+        check_parse_error("#x", "1:2: unexpected `x`, expected an integer\n#x\n ^");
+        check_parse_error(
+            "let x₁' = y in",
+            "1:15: unexpected end of input, expected one of `\"`, `#`, `(`, `\\`, `case`, \
+             `false`, `forall`, `fun`, `let`, `letrec`, `true`, `Π`, `λ`, a de Bruijn index or a name\n\
+             let x₁' = y in\n              ^",
+        );
+        check_parse_error(
+            "f 12)",
+            "1:5: unexpected `)`, expected end of input\nf 12)\n    ^",
+        );
+        check_parse_error(
+            "λα. β_γ-",
+            "1:8: unexpected `-`, expected end of input\nλα. β_γ-\n       ^",
+        );
+        check_parse_error(
+            "λ(x : A y",
+            "1:10: unexpected end of input, expected `)`\nλ(x : A y\n         ^",
+        );
+        check_parse_error(
+            "case x\n\tof { a => b c",
+            "2:15: unexpected end of input, expected `}`\n\tof { a => b c\n\t             ^",
+        );
+        check_parse_error(
+            "letrec f = 1\nand = 2 in 3",
+            "2:1: unexpected `and`, expected `in`\nand = 2 in 3\n^^^",
+        );
+    }
+
+    #[test]
+    fn parsing_is_linear_in_long_applications() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        // f xx xx … xx, about 1 MB long
+        let count = 350_000;
+        let input = format!("f{}", " xx".repeat(count));
+        let expression = expression::Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            input.as_bytes(),
+        )
+        .unwrap();
+        match &expressions[expression] {
+            expression::Expression::Application {
+                function: _,
+                arguments,
+            } => assert_eq!(arguments.len(), count),
+            _ => panic!("expected an application"),
+        }
+    }
+
+    fn parse_height(input: &str) -> usize {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let expression = expression::Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            input.as_bytes(),
+        )
+        .unwrap();
+        expression::Expression::height(&expressions, expression)
+    }
+
+    #[test]
+    fn parsing_handles_long_chains_of_binders() {
+        let count = 1_000_000;
+        assert_eq!(
+            parse_height(&format!("{}y", "let x = y in ".repeat(count))),
+            count
+        );
+        assert_eq!(parse_height(&format!("{}1", "λ. ".repeat(count))), count);
+        assert_eq!(parse_height(&format!("λ{}. x", " x".repeat(count))), count);
+        assert_eq!(
+            parse_height(&format!("{}1", "Π(: A). ".repeat(count))),
+            count
+        );
+        assert_eq!(parse_height(&format!("{}A", "A → ".repeat(count))), count);
+        assert_eq!(
+            parse_height(&format!("{}1", "letrec = 1 in ".repeat(count))),
+            count
+        );
+    }
+
+    #[test]
+    fn parsing_handles_deep_nesting() {
+        let depth = 1_000_000;
+        let parenthesized = format!("{}x{}", "(λx. ".repeat(depth), ")".repeat(depth));
+        assert_eq!(parse_height(&parenthesized), depth);
+        let definitions = format!("{}y{}", "let x = ".repeat(depth), " in x".repeat(depth));
+        assert_eq!(parse_height(&definitions), depth);
+        let scrutinees = format!("{}x{}", "case λx. ".repeat(depth), " of {}".repeat(depth));
+        assert_eq!(parse_height(&scrutinees), depth);
+        let branches = format!(
+            "{}x{}",
+            "case x of { c => ".repeat(depth),
+            " }".repeat(depth)
+        );
+        assert_eq!(parse_height(&branches), depth);
+        let annotations = format!("{}A{}", "λ(x : ".repeat(depth), "). x".repeat(depth));
+        assert_eq!(parse_height(&annotations), depth);
+    }
+
+    #[test]
+    fn parse_errors_locate_deeply_nested_tokens() {
+        let depth = 1_000_000;
+        let error = parse_error(&format!("{}x{}", "(".repeat(depth), ")".repeat(depth - 1)));
+        assert_eq!(error.offset(), 2 * depth);
+        assert_eq!(error.token(), Option::None);
+        assert_eq!(error.expected(), [Expected::Token(")")]);
+    }
+
+    #[test]
+    fn parsing_reads_back_printed_deep_applications() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        // f (f (… (f x)))
+        let depth = 1_000_000;
+        let f = strings.intern(b"f");
+        let mut expected = expressions.variable(strings.intern(b"x"));
+        for _ in 0..depth {
+            let function = expressions.variable(f);
+            expected = expressions.application(function, vec![expected]);
+        }
+        let printed =
+            expression::Expression::to_string(&strings, &expressions, 4 * depth, expected).unwrap();
+        let parsed = expression::Expression::parse_expression(
+            &mut strings,
+            &mut expressions,
+            printed.as_bytes(),
+        )
+        .unwrap();
+        assert!(expression::Expression::equals(
+            (&expressions, parsed),
+            (&expressions, expected)
+        ));
+    }
+
+    #[test]
+    fn lowering_handles_deep_expressions() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let depth = 1_000_000;
+        let mut expression = Expression::NamelessVariable { index: 1 };
+        for _ in 0..depth {
+            expression = Expression::NamelessAbstraction {
+                annotation: Option::None,
+                body: Box::new(expression),
+            };
         }
+        let lowered = lower(
+            &mut strings,
+            &mut expressions,
+            &mut SourceMap::new(),
+            0,
+            &expression,
+        );
+        assert_eq!(expression::Expression::height(&expressions, lowered), depth);
     }
 }
@@ -2,20 +2,82 @@ extern crate pretty;
 
 use std::string::FromUtf8Error;
 
-use pretty::RcDoc;
+use pretty::{Arena, DocAllocator, DocBuilder, RcAllocator, RcDoc};
 
 use crate::{
-    expression::{Expression, ExpressionArena, ExpressionId},
+    expression::{CaseBranch, Constant, Expression, ExpressionArena, ExpressionId},
+    program::Program,
     strings::{StringArena, StringId},
+    visitor::{Fold, Scoping},
 };
 
+/// Concrete syntaxes for the symbols of printed expressions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Syntax {
+    /// Prints `λx. e`, `Π(x : A). B` and `A → B`.
+    #[default]
+    Unicode,
+    /// Prints `\x. e`, `forall (x : A). B` and `A -> B`.
+    Ascii,
+}
+
+impl Syntax {
+    fn lambda(self) -> &'static str {
+        match self {
+            Syntax::Unicode => "λ",
+            Syntax::Ascii => "\\",
+        }
+    }
+
+    fn pi(self) -> &'static str {
+        match self {
+            Syntax::Unicode => "Π",
+            Syntax::Ascii => "forall ",
+        }
+    }
+
+    fn arrow(self) -> &'static str {
+        match self {
+            Syntax::Unicode => "→",
+            Syntax::Ascii => "->",
+        }
+    }
+
+    fn open_mark(self) -> &'static str {
+        match self {
+            Syntax::Unicode => "⟦",
+            Syntax::Ascii => "[",
+        }
+    }
+
+    fn close_mark(self) -> &'static str {
+        match self {
+            Syntax::Unicode => "⟧",
+            Syntax::Ascii => "]",
+        }
+    }
+}
+
 impl Expression {
+    /// Prints `e` as a reference-counted document. Dropping such a document
+    /// recurses over its depth, so [Expression::to_string] allocates the
+    /// document in an arena instead.
     pub fn to_doc<'a>(
         strings: &StringArena,
         expressions: &ExpressionArena,
         e: ExpressionId,
     ) -> Result<RcDoc<'a>, FromUtf8Error> {
-        expression(strings, expressions, e)
+        Expression::to_doc_with_syntax(strings, expressions, Syntax::Unicode, e)
+    }
+
+    pub fn to_doc_with_syntax<'a>(
+        strings: &StringArena,
+        expressions: &ExpressionArena,
+        syntax: Syntax,
+        e: ExpressionId,
+    ) -> Result<RcDoc<'a>, FromUtf8Error> {
+        let document = expression(&RcAllocator, strings, expressions, syntax, e)?;
+        Result::Ok(document.into_doc())
     }
 
     pub fn to_string(
@@ -24,116 +86,689 @@ impl Expression {
         width: usize,
         e: ExpressionId,
     ) -> Result<String, PrettyPrintError> {
+        Expression::to_string_with_syntax(strings, expressions, Syntax::Unicode, width, e)
+    }
+
+    pub fn to_string_with_syntax(
+        strings: &StringArena,
+        expressions: &ExpressionArena,
+        syntax: Syntax,
+        width: usize,
+        e: ExpressionId,
+    ) -> Result<String, PrettyPrintError> {
+        let arena = Arena::new();
         let mut buffer = Vec::default();
-        let document = Expression::to_doc(strings, expressions, e)?;
+        let document = expression(&arena, strings, expressions, syntax, e)?;
         document.render(width, &mut buffer)?;
         let rendered = String::from_utf8(buffer)?;
         Result::Ok(rendered)
     }
 }
 
-pub fn name<'a>(strings: &StringArena, n: StringId) -> Result<RcDoc<'a>, FromUtf8Error> {
+impl Program {
+    pub fn to_doc<'a>(
+        strings: &StringArena,
+        expressions: &ExpressionArena,
+        program: &Program,
+    ) -> Result<RcDoc<'a>, FromUtf8Error> {
+        Program::to_doc_with_syntax(strings, expressions, Syntax::Unicode, program)
+    }
+
+    /// Prints each definition of `program` as `def <name> = <expression>;` on
+    /// its own lines.
+    pub fn to_doc_with_syntax<'a>(
+        strings: &StringArena,
+        expressions: &ExpressionArena,
+        syntax: Syntax,
+        program: &Program,
+    ) -> Result<RcDoc<'a>, FromUtf8Error> {
+        let document = program_doc(&RcAllocator, strings, expressions, syntax, program)?;
+        Result::Ok(document.into_doc())
+    }
+
+    pub fn to_string(
+        strings: &StringArena,
+        expressions: &ExpressionArena,
+        width: usize,
+        program: &Program,
+    ) -> Result<String, PrettyPrintError> {
+        Program::to_string_with_syntax(strings, expressions, Syntax::Unicode, width, program)
+    }
+
+    pub fn to_string_with_syntax(
+        strings: &StringArena,
+        expressions: &ExpressionArena,
+        syntax: Syntax,
+        width: usize,
+        program: &Program,
+    ) -> Result<String, PrettyPrintError> {
+        let arena = Arena::new();
+        let mut buffer = Vec::default();
+        let document = program_doc(&arena, strings, expressions, syntax, program)?;
+        document.render(width, &mut buffer)?;
+        let rendered = String::from_utf8(buffer)?;
+        Result::Ok(rendered)
+    }
+}
+
+fn program_doc<'a, D>(
+    allocator: &'a D,
+    strings: &StringArena,
+    expressions: &ExpressionArena,
+    syntax: Syntax,
+    program: &Program,
+) -> Result<DocBuilder<'a, D>, FromUtf8Error>
+where
+    D: DocAllocator<'a> + 'a,
+    D::Doc: Clone,
+{
+    let mut definition_docs = Vec::with_capacity(program.len());
+    for &(n, definition) in program.definitions() {
+        let name_doc = name(allocator, strings, n)?;
+        let definition_doc = expression(allocator, strings, expressions, syntax, definition)?;
+        definition_docs.push(
+            allocator
+                .text("def ")
+                .append(name_doc)
+                .append(" =")
+                .append(allocator.line().append(definition_doc).nest(2))
+                .append(";")
+                .group()
+                .append(allocator.hardline()),
+        );
+    }
+    Result::Ok(allocator.concat(definition_docs))
+}
+
+pub fn name<'a, D>(
+    allocator: &'a D,
+    strings: &StringArena,
+    n: StringId,
+) -> Result<DocBuilder<'a, D>, FromUtf8Error>
+where
+    D: DocAllocator<'a>,
+{
     let n = String::from_utf8(strings[n].to_vec())?;
-    Result::Ok(RcDoc::as_string(n))
+    Result::Ok(allocator.as_string(n))
 }
 
-fn name_option<'a>(strings: &StringArena, n: Option<StringId>) -> Result<RcDoc<'a>, FromUtf8Error> {
+fn name_option<'a, D>(
+    allocator: &'a D,
+    strings: &StringArena,
+    n: Option<StringId>,
+) -> Result<DocBuilder<'a, D>, FromUtf8Error>
+where
+    D: DocAllocator<'a>,
+{
     match n {
-        Option::Some(n) => name(strings, n),
-        Option::None => Result::Ok(RcDoc::text("_")),
+        Option::Some(n) => name(allocator, strings, n),
+        Option::None => Result::Ok(allocator.text("_")),
     }
 }
 
-fn expression1<'a>(
+fn constant_doc<'a, D>(
+    allocator: &'a D,
     strings: &StringArena,
-    pool: &ExpressionArena,
-    expression: ExpressionId,
-) -> Result<RcDoc<'a>, FromUtf8Error> {
-    match &pool[expression] {
-        Expression::Abstraction { parameter, body } => {
-            let parameter_doc = name_option(strings, *parameter)?;
-            let body_doc = expression1(strings, pool, *body)?;
-            Result::Ok(
-                RcDoc::text("λ")
-                    .append(parameter_doc)
-                    .append(".")
-                    .append(RcDoc::line().append(body_doc).nest(2))
-                    .group(),
-            )
+    constant: Constant,
+) -> Result<DocBuilder<'a, D>, FromUtf8Error>
+where
+    D: DocAllocator<'a>,
+{
+    match constant {
+        Constant::Integer(integer) => {
+            Result::Ok(allocator.text("#").append(allocator.as_string(integer)))
         }
-        Expression::NamelessAbstraction { body } => {
-            let body_doc = expression1(strings, pool, *body)?;
-            Result::Ok(
-                RcDoc::text("λ.")
-                    .append(RcDoc::line().append(body_doc).nest(2))
-                    .group(),
-            )
+        Constant::String(string) => {
+            let string = String::from_utf8(strings[string].to_vec())?;
+            let mut escaped = String::with_capacity(string.len() + 2);
+            escaped.push('"');
+            for c in string.chars() {
+                match c {
+                    '\\' => escaped.push_str("\\\\"),
+                    '"' => escaped.push_str("\\\""),
+                    '\n' => escaped.push_str("\\n"),
+                    '\t' => escaped.push_str("\\t"),
+                    c => escaped.push(c),
+                }
+            }
+            escaped.push('"');
+            Result::Ok(allocator.as_string(escaped))
         }
-        _ => expression2(strings, pool, expression),
+        Constant::Boolean(boolean) => Result::Ok(allocator.as_string(boolean)),
     }
 }
 
-fn expression2<'a>(
-    strings: &StringArena,
-    pool: &ExpressionArena,
-    expression: ExpressionId,
-) -> Result<RcDoc<'a>, FromUtf8Error> {
-    match &pool[expression] {
-        Expression::Application {
-            function,
-            arguments,
-        } => {
-            let function_doc = expression3(strings, pool, *function)?;
-            let mut argument_docs = Vec::with_capacity(arguments.len());
-            for &argument in arguments {
-                let argument_doc = expression3(strings, pool, argument)?;
-                argument_docs.push(argument_doc);
-            }
-            Result::Ok(
-                function_doc
+/// The levels of the grammar of expressions, from the loosest to the
+/// tightest. An expression printed at a looser level than the one expected
+/// by its context is parenthesized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    /// Binders, whose bodies extend as far to the right as possible.
+    Binder,
+    /// Non-dependent function types `A → B`.
+    Arrow,
+    Application,
+    /// Variables, constants, case expressions and parenthesized expressions.
+    Atom,
+}
+
+/// The document of a printed expression, before it is parenthesized for its
+/// context.
+enum Printed<'a, D>
+where
+    D: DocAllocator<'a>,
+{
+    Doc {
+        doc: DocBuilder<'a, D>,
+        level: Level,
+    },
+    /// A chain of nested named lambda abstractions, printed as a single
+    /// lambda abstraction with several parameters, like `λx (y : A) _. e`.
+    /// The parameters and their annotations are listed from the innermost to
+    /// the outermost.
+    Abstractions {
+        parameters: Vec<(DocBuilder<'a, D>, Option<Printed<'a, D>>)>,
+        body: DocBuilder<'a, D>,
+    },
+}
+
+impl<'a, D> Printed<'a, D>
+where
+    D: DocAllocator<'a> + 'a,
+    D::Doc: Clone,
+{
+    /// Prints this expression at `level`, with parentheses if it belongs to a
+    /// looser level.
+    fn at(self, allocator: &'a D, syntax: Syntax, level: Level) -> DocBuilder<'a, D> {
+        match self {
+            Printed::Doc {
+                doc,
+                level: doc_level,
+            } if doc_level >= level => doc,
+            Printed::Doc { doc, level: _ } => parenthesized(allocator, doc),
+            Printed::Abstractions { .. } if level > Level::Binder => {
+                parenthesized(allocator, self.at(allocator, syntax, Level::Binder))
+            }
+            Printed::Abstractions {
+                mut parameters,
+                body,
+            } => {
+                let parameters_doc = if parameters.len() == 1 {
+                    let (parameter_doc, annotation) = parameters.pop().unwrap();
+                    match annotation {
+                        Option::Some(annotation) => parameter_doc
+                            .append(" : ")
+                            .append(annotation.at(allocator, syntax, Level::Arrow)),
+                        Option::None => parameter_doc,
+                    }
+                } else {
+                    let parameter_docs =
+                        parameters
+                            .into_iter()
+                            .rev()
+                            .map(|(parameter_doc, annotation)| match annotation {
+                                Option::Some(annotation) => allocator
+                                    .text("(")
+                                    .append(parameter_doc)
+                                    .append(" : ")
+                                    .append(annotation.at(allocator, syntax, Level::Binder))
+                                    .append(")"),
+                                Option::None => parameter_doc,
+                            });
+                    allocator.intersperse(parameter_docs, allocator.space())
+                };
+                allocator
+                    .text(syntax.lambda())
+                    .append(parameters_doc)
+                    .append(".")
+                    .append(allocator.line().append(body).nest(2))
+                    .group()
+            }
+        }
+    }
+}
+
+fn parenthesized<'a, D>(allocator: &'a D, doc: DocBuilder<'a, D>) -> DocBuilder<'a, D>
+where
+    D: DocAllocator<'a>,
+{
+    allocator
+        .text("(")
+        .append(
+            allocator
+                .line_()
+                .append(doc)
+                .append(allocator.line_())
+                .nest(2),
+        )
+        .append(allocator.text(")"))
+        .group()
+}
+
+/// Prints expressions bottom-up, with the sub-expression `marked` if any
+/// between marks.
+struct Printer<'e, 'a, D> {
+    allocator: &'a D,
+    strings: &'e StringArena,
+    expressions: &'e ExpressionArena,
+    syntax: Syntax,
+    marked: Option<ExpressionId>,
+}
+
+impl<'e, 'a, D> Printer<'e, 'a, D>
+where
+    D: DocAllocator<'a> + 'a,
+    D::Doc: Clone,
+{
+    fn new(
+        allocator: &'a D,
+        strings: &'e StringArena,
+        expressions: &'e ExpressionArena,
+        syntax: Syntax,
+        marked: Option<ExpressionId>,
+    ) -> Printer<'e, 'a, D> {
+        Printer {
+            allocator,
+            strings,
+            expressions,
+            syntax,
+            marked,
+        }
+    }
+
+    fn print(mut self, expression: ExpressionId) -> Result<DocBuilder<'a, D>, FromUtf8Error> {
+        let printed = self.fold_expression(expression)?;
+        Result::Ok(self.at(printed, Level::Binder))
+    }
+
+    fn at(&self, printed: Printed<'a, D>, level: Level) -> DocBuilder<'a, D> {
+        printed.at(self.allocator, self.syntax, level)
+    }
+
+    fn annotation_doc(&self, annotation: Option<Printed<'a, D>>) -> DocBuilder<'a, D> {
+        match annotation {
+            Option::Some(annotation) => self
+                .allocator
+                .text(" : ")
+                .append(self.at(annotation, Level::Arrow)),
+            Option::None => self.allocator.nil(),
+        }
+    }
+
+    fn let_doc(
+        &self,
+        binding_doc: DocBuilder<'a, D>,
+        definition: Printed<'a, D>,
+        body: Printed<'a, D>,
+    ) -> DocBuilder<'a, D> {
+        let allocator = self.allocator;
+        binding_doc
+            .append(
+                allocator
+                    .line()
+                    .append(self.at(definition, Level::Binder))
+                    .nest(2),
+            )
+            .append(allocator.line())
+            .append("in")
+            .group()
+            .append(allocator.line().append(self.at(body, Level::Binder)))
+            .group()
+    }
+
+    /// Prints a recursive let-binding group, where `binding_docs` are the
+    /// documents for the parameters of the definitions, which come before the
+    /// body in `children`.
+    fn let_rec_doc(
+        &self,
+        binding_docs: Vec<DocBuilder<'a, D>>,
+        mut children: Vec<Printed<'a, D>>,
+    ) -> DocBuilder<'a, D> {
+        let allocator = self.allocator;
+        let body = children.pop().unwrap();
+        let docs = binding_docs.into_iter().zip(children).enumerate().map(
+            |(i, (binding_doc, definition))| {
+                let keyword = if i == 0 { "letrec " } else { "and " };
+                allocator
+                    .text(keyword)
+                    .append(binding_doc)
                     .append(
-                        RcDoc::line()
-                            .append(RcDoc::intersperse(argument_docs, RcDoc::line()))
+                        allocator
+                            .line()
+                            .append(self.at(definition, Level::Binder))
                             .nest(2),
                     )
-                    .group(),
+                    .group()
+            },
+        );
+        allocator
+            .intersperse(docs, allocator.line())
+            .append(allocator.line())
+            .append("in")
+            .group()
+            .append(allocator.line().append(self.at(body, Level::Binder)))
+            .group()
+    }
+
+    fn branch_doc(
+        &self,
+        branch: &CaseBranch,
+        body: Printed<'a, D>,
+    ) -> Result<DocBuilder<'a, D>, FromUtf8Error> {
+        let allocator = self.allocator;
+        let constructor_doc = name(allocator, self.strings, branch.constructor())?;
+        let pattern_doc = match branch {
+            CaseBranch::Named { parameters, .. } => {
+                let mut parameter_docs = Vec::with_capacity(parameters.len());
+                for &parameter in parameters {
+                    let parameter_doc = name_option(allocator, self.strings, parameter)?;
+                    parameter_docs.push(allocator.space().append(parameter_doc));
+                }
+                constructor_doc.append(allocator.concat(parameter_docs))
+            }
+            CaseBranch::Nameless { arity, .. } => constructor_doc
+                .append("/")
+                .append(allocator.as_string(arity)),
+        };
+        Result::Ok(
+            pattern_doc
+                .append(" =>")
+                .append(
+                    allocator
+                        .line()
+                        .append(self.at(body, Level::Binder))
+                        .nest(2),
+                )
+                .group(),
+        )
+    }
+
+    fn pi_doc(
+        &self,
+        binding_doc: DocBuilder<'a, D>,
+        domain: Printed<'a, D>,
+        codomain: Printed<'a, D>,
+    ) -> DocBuilder<'a, D> {
+        let allocator = self.allocator;
+        allocator
+            .text(self.syntax.pi())
+            .append("(")
+            .append(binding_doc)
+            .append(self.at(domain, Level::Binder))
+            .append(").")
+            .append(
+                allocator
+                    .line()
+                    .append(self.at(codomain, Level::Binder))
+                    .nest(2),
             )
-        }
-        _ => expression3(strings, pool, expression),
+            .group()
     }
-}
 
-fn expression3<'a>(
-    strings: &StringArena,
-    pool: &ExpressionArena,
-    expression: ExpressionId,
-) -> Result<RcDoc<'a>, FromUtf8Error> {
-    match &pool[expression] {
-        Expression::Variable { identifier } => name(strings, *identifier),
-        Expression::NamelessVariable { index } => Result::Ok(RcDoc::as_string(index.into_usize())),
-        _ => {
-            let expression_doc = expression1(strings, pool, expression)?;
-            Result::Ok(
-                RcDoc::text("(")
+    /// Prints `expression` from the documents of its sub-expressions, before
+    /// it is marked.
+    fn print_unmarked(
+        &self,
+        expression: ExpressionId,
+        mut children: Vec<Printed<'a, D>>,
+    ) -> Result<Printed<'a, D>, FromUtf8Error> {
+        let allocator = self.allocator;
+        let syntax = self.syntax;
+        let (doc, level) = match &self.expressions[expression] {
+            Expression::Variable { identifier } => {
+                (name(allocator, self.strings, *identifier)?, Level::Atom)
+            }
+            Expression::NamelessVariable { index } => {
+                (allocator.as_string(index.into_usize()), Level::Atom)
+            }
+            Expression::Constant { constant } => (
+                constant_doc(allocator, self.strings, *constant)?,
+                Level::Atom,
+            ),
+            Expression::Abstraction {
+                parameter,
+                annotation: _,
+                body: _,
+            } => {
+                let body = children.pop().unwrap();
+                let annotation = children.pop();
+                let parameter_doc = name_option(allocator, self.strings, *parameter)?;
+                return Result::Ok(match body {
+                    Printed::Abstractions {
+                        mut parameters,
+                        body,
+                    } => {
+                        parameters.push((parameter_doc, annotation));
+                        Printed::Abstractions { parameters, body }
+                    }
+                    body => Printed::Abstractions {
+                        parameters: vec![(parameter_doc, annotation)],
+                        body: self.at(body, Level::Binder),
+                    },
+                });
+            }
+            Expression::NamelessAbstraction {
+                annotation: _,
+                body: _,
+            } => {
+                let body = children.pop().unwrap();
+                let annotation_doc = self.annotation_doc(children.pop());
+                let doc = allocator
+                    .text(syntax.lambda())
+                    .append(annotation_doc)
+                    .append(".")
                     .append(
-                        RcDoc::line_()
-                            .append(expression_doc)
-                            .append(RcDoc::line_())
+                        allocator
+                            .line()
+                            .append(self.at(body, Level::Binder))
                             .nest(2),
                     )
-                    .append(RcDoc::text(")"))
-                    .group(),
-            )
+                    .group();
+                (doc, Level::Binder)
+            }
+            Expression::Application {
+                function: _,
+                arguments: _,
+            } => {
+                let mut children = children.into_iter();
+                let function_doc = self.at(children.next().unwrap(), Level::Atom);
+                let argument_docs = children.map(|argument| self.at(argument, Level::Atom));
+                let doc = function_doc
+                    .append(
+                        allocator
+                            .line()
+                            .append(allocator.intersperse(argument_docs, allocator.line()))
+                            .nest(2),
+                    )
+                    .group();
+                (doc, Level::Application)
+            }
+            Expression::Let {
+                parameter,
+                definition: _,
+                body: _,
+            } => {
+                let body = children.pop().unwrap();
+                let definition = children.pop().unwrap();
+                let parameter_doc = name_option(allocator, self.strings, *parameter)?;
+                let binding_doc = allocator.text("let ").append(parameter_doc).append(" =");
+                (self.let_doc(binding_doc, definition, body), Level::Binder)
+            }
+            Expression::NamelessLet {
+                definition: _,
+                body: _,
+            } => {
+                let body = children.pop().unwrap();
+                let definition = children.pop().unwrap();
+                let binding_doc = allocator.text("let =");
+                (self.let_doc(binding_doc, definition, body), Level::Binder)
+            }
+            Expression::LetRec {
+                parameters,
+                definitions: _,
+                body: _,
+            } => {
+                let mut parameter_docs = Vec::with_capacity(parameters.len());
+                for &parameter in parameters {
+                    let parameter_doc = name_option(allocator, self.strings, parameter)?;
+                    parameter_docs.push(parameter_doc.append(" ="));
+                }
+                (self.let_rec_doc(parameter_docs, children), Level::Binder)
+            }
+            Expression::NamelessLetRec {
+                definitions,
+                body: _,
+            } => {
+                let parameter_docs = vec![allocator.text("="); definitions.len()];
+                (self.let_rec_doc(parameter_docs, children), Level::Binder)
+            }
+            Expression::Pi {
+                parameter: Option::Some(parameter),
+                domain: _,
+                codomain: _,
+            } => {
+                let codomain = children.pop().unwrap();
+                let domain = children.pop().unwrap();
+                let parameter_doc = name(allocator, self.strings, *parameter)?;
+                let binding_doc = parameter_doc.append(" : ");
+                (self.pi_doc(binding_doc, domain, codomain), Level::Binder)
+            }
+            Expression::NamelessPi {
+                domain: _,
+                codomain: _,
+            } => {
+                let codomain = children.pop().unwrap();
+                let domain = children.pop().unwrap();
+                let binding_doc = allocator.text(": ");
+                (self.pi_doc(binding_doc, domain, codomain), Level::Binder)
+            }
+            Expression::Pi {
+                parameter: Option::None,
+                domain: _,
+                codomain: _,
+            } => {
+                // The parameter is unused, so the dependent function type is a non-dependent arrow
+                let codomain = children.pop().unwrap();
+                let domain = children.pop().unwrap();
+                let doc = self
+                    .at(domain, Level::Application)
+                    .append(" ")
+                    .append(syntax.arrow())
+                    .append(allocator.line().append(self.at(codomain, Level::Arrow)))
+                    .group();
+                (doc, Level::Arrow)
+            }
+            Expression::Case {
+                scrutinee: _,
+                branches,
+            } => {
+                let mut children = children.into_iter();
+                let scrutinee_doc = self.at(children.next().unwrap(), Level::Binder);
+                let mut branch_docs = Vec::with_capacity(branches.len());
+                for (branch, body) in branches.iter().zip(children) {
+                    branch_docs.push(self.branch_doc(branch, body)?);
+                }
+                let branches_doc = if branch_docs.is_empty() {
+                    allocator.text("{}")
+                } else {
+                    allocator
+                        .text("{")
+                        .append(
+                            allocator
+                                .line()
+                                .append(
+                                    allocator
+                                        .intersperse(branch_docs, allocator.line().append("| ")),
+                                )
+                                .nest(2),
+                        )
+                        .append(allocator.line())
+                        .append("}")
+                };
+                let doc = allocator
+                    .text("case")
+                    .append(allocator.line().append(scrutinee_doc).nest(2))
+                    .append(allocator.line())
+                    .append("of")
+                    .group()
+                    .append(" ")
+                    .append(branches_doc)
+                    .group();
+                (doc, Level::Atom)
+            }
+        };
+        Result::Ok(Printed::Doc { doc, level })
+    }
+}
+
+impl<'a, D> Scoping for Printer<'_, 'a, D> {}
+
+impl<'e, 'a, D> Fold<'e> for Printer<'e, 'a, D>
+where
+    D: DocAllocator<'a> + 'a,
+    D::Doc: Clone,
+{
+    type Output = Result<Printed<'a, D>, FromUtf8Error>;
+
+    fn expressions(&self) -> &'e ExpressionArena {
+        self.expressions
+    }
+
+    fn combine(
+        &mut self,
+        expression: ExpressionId,
+        children: Vec<Result<Printed<'a, D>, FromUtf8Error>>,
+    ) -> Result<Printed<'a, D>, FromUtf8Error> {
+        let children = children.into_iter().collect::<Result<Vec<_>, _>>()?;
+        let printed = self.print_unmarked(expression, children)?;
+        if self.marked != Option::Some(expression) {
+            return Result::Ok(printed);
         }
+        // The marks delimit the marked expression like parentheses
+        let doc = self
+            .allocator
+            .text(self.syntax.open_mark())
+            .append(self.at(printed, Level::Binder))
+            .append(self.syntax.close_mark())
+            .group();
+        Result::Ok(Printed::Doc {
+            doc,
+            level: Level::Atom,
+        })
     }
 }
 
-pub fn expression<'a>(
+/// Prints `expression` as a document allocated by `allocator`.
+pub fn expression<'a, D>(
+    allocator: &'a D,
+    strings: &StringArena,
+    pool: &ExpressionArena,
+    syntax: Syntax,
+    expression: ExpressionId,
+) -> Result<DocBuilder<'a, D>, FromUtf8Error>
+where
+    D: DocAllocator<'a> + 'a,
+    D::Doc: Clone,
+{
+    Printer::new(allocator, strings, pool, syntax, Option::None).print(expression)
+}
+
+/// Prints `expression` like [expression], with its sub-expression `marked`
+/// between marks, like `f ⟦(λx. x) y⟧`. The marks delimit `marked` like
+/// parentheses would.
+pub fn marked_expression<'a, D>(
+    allocator: &'a D,
     strings: &StringArena,
     pool: &ExpressionArena,
+    syntax: Syntax,
     expression: ExpressionId,
-) -> Result<RcDoc<'a>, FromUtf8Error> {
-    expression1(strings, pool, expression)
+    marked: ExpressionId,
+) -> Result<DocBuilder<'a, D>, FromUtf8Error>
+where
+    D: DocAllocator<'a> + 'a,
+    D::Doc: Clone,
+{
+    Printer::new(allocator, strings, pool, syntax, Option::Some(marked)).print(expression)
 }
 
 #[derive(Debug)]
@@ -203,9 +838,203 @@ mod tests {
         roundabout_test("λf. λx. f x");
         roundabout_test("λf. λx. f ((λg. g) x)");
         roundabout_test("λx. λy. λz. x z (y z)");
+        roundabout_test("let x = y in x");
+        roundabout_test("let _ = y in x");
+        roundabout_test("let f = λx. x in f (let g = f in g y)");
+        roundabout_test("λf. let x = f (λy. y) in let y = x in f x y");
+        roundabout_test("let letter = inner in letter inner");
+        roundabout_test("λx : T. x");
+        roundabout_test("λ_ : T. x");
+        roundabout_test("λf : A B. λx : (λy. y) A. f x");
+        roundabout_test("λx : T. λy : x. let z = (λw : y. w) x in z");
+        roundabout_test("A → B");
+        roundabout_test("A → B → C");
+        roundabout_test("(A → B) → C");
+        roundabout_test("Π(x : A). B x");
+        roundabout_test("Π(_ : A). B");
+        roundabout_test("Π(A : Type). (Π(x : A). P x) → A → (λx. x) A");
+        roundabout_test("λf : A → B. λx : A. f x");
+        roundabout_test("case p of {}");
+        roundabout_test("case p of { pair a b => f b a }");
+        roundabout_test("case f p of { pair a _ => a | nil => λx. x | cons x xs => g x xs }");
+        roundabout_test("λf. case case p of { c => x } of { d y => f y } y");
+        roundabout_test("λx y _ z. x z (y z)");
+        roundabout_test("λx. y. λ_. z. x z");
+        roundabout_test("λ(f : A → B) (x : A) y. f x");
+        roundabout_test("λx : A. y z. x y z");
+        roundabout_test("λf. (λx y. x) f");
+        roundabout_test("f #0 #42 #-3");
+        roundabout_test("f \"\" \"hello, world\" \"a\\\\b\\\"c\\nd\\te\"");
+        roundabout_test("λx. case x of { some y => #1 | none => \"none\" }");
+        roundabout_test("let truth = true in truth");
+        roundabout_test("letrec f = λx. f x in f");
+        roundabout_test("letrec even = λn. odd n and odd = λn. even n and _ = x in even #4");
+        roundabout_test("λf. letrec g = f g in letrec h = g and i = h in i");
+        roundabout_test("letrec android = and1 in android");
+        roundabout_test("\\x. \\y z. x z");
+        roundabout_test("fun f (x : A) => fun _ => f x");
+        roundabout_test("fun x : A -> B => forall (y : A). B -> x");
+        roundabout_test("let fund = forallx in fund");
+        roundabout_test("λx x' x''. x'' x' x");
+        roundabout_test("λf_inner my-var. f_inner my-var");
+        roundabout_test("λα β. α₁ β₀ x₁₂");
+        roundabout_test("λx. x->x-1->x'");
+        roundabout_test("let in' = let' in in'");
     }
 
-    fn fuzz_test<R: Rng>(rng: &mut R, max_depth: usize) {
+    fn check_printing(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let parsed_expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+
+        let printed = Expression::to_string(&strings, &expressions, 80, parsed_expression).unwrap();
+
+        assert_eq!(printed, expected);
+    }
+
+    #[test]
+    fn printing_tests() {
+        check_printing("λx. x", "λx. x");
+        check_printing("λ. 1", "λ. 1");
+        check_printing("let x = y in x", "let x = y in x");
+        check_printing("λ : T. 1", "λ : T. 1");
+        check_printing("Π(_ : A). B", "A → B");
+        check_printing("Π(x : A). B", "Π(x : A). B");
+        check_printing("Π(: A). 1", "Π(: A). 1");
+        check_printing("(A → B) → C", "(A → B) → C");
+        check_printing("λx. λy. λz. x z (y z)", "λx y z. x z (y z)");
+        check_printing("f #1 1 #-1", "f #1 1 #-1");
+        check_printing("\"say \\\"hi\\\"\"", "\"say \\\"hi\\\"\"");
+        check_printing("f true (false)", "f true false");
+        check_printing("letrec f = g and g = f in f", "letrec f = g and g = f in f");
+        check_printing("letrec = 2 and = 1 in 1", "letrec = 2 and = 1 in 1");
+        check_printing("λx y _ z. x", "λx y _ z. x");
+        check_printing("λ x . y. x y", "λx y. x y");
+        check_printing("λx : A. λy : B. x", "λ(x : A) (y : B). x");
+        check_printing("λx : A → B. x", "λx : A → B. x");
+        check_printing("λ(x : A). λy. λ. λz. x", "λ(x : A) y. λ. λz. x");
+        check_printing("case p of{}", "case p of {}");
+        check_printing(
+            "case p of {pair/2=>2|nil=>x}",
+            "case p of { pair/2 => 2 | nil => x }",
+        );
+        check_printing("λx. -- identity\n  x", "λx. x");
+        check_printing("f{- first -}x {- second -}\ty", "f x y");
+        check_printing(
+            "{- {- nested -} -} case p of {- c -} {-- c\n}",
+            "case p of {}",
+        );
+        check_printing("λ{--}. 1 -->", "λ. 1");
+    }
+
+    fn check_marked_printing(input: &str, path: &[usize], expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let parsed_expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+        let mut marked = parsed_expression;
+        for &child in path {
+            marked = expressions[marked].children()[child];
+        }
+
+        let mut buffer = Vec::default();
+        marked_expression(
+            &Arena::new(),
+            &strings,
+            &expressions,
+            Syntax::Unicode,
+            parsed_expression,
+            marked,
+        )
+        .unwrap()
+        .render(80, &mut buffer)
+        .unwrap();
+
+        assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+    }
+
+    #[test]
+    fn marked_printing_tests() {
+        check_marked_printing("(λx. x) y", &[], "⟦(λx. x) y⟧");
+        check_marked_printing("f ((λx. x) y) z", &[1], "f ⟦(λx. x) y⟧ z");
+        check_marked_printing("f (g y)", &[1, 0], "f (⟦g⟧ y)");
+        check_marked_printing("λx. λy. λz. x", &[0], "λx. ⟦λy z. x⟧");
+        check_marked_printing("A → B → C", &[1], "A → ⟦B → C⟧");
+    }
+
+    fn check_ascii_printing(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let parsed_expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+
+        let printed = Expression::to_string_with_syntax(
+            &strings,
+            &expressions,
+            Syntax::Ascii,
+            80,
+            parsed_expression,
+        )
+        .unwrap();
+
+        assert_eq!(printed, expected);
+    }
+
+    #[test]
+    fn ascii_printing_tests() {
+        check_ascii_printing("λx. x", "\\x. x");
+        check_ascii_printing("fun x y => x", "\\x y. x");
+        check_ascii_printing("fun : T => 1", "\\ : T. 1");
+        check_ascii_printing("λ(x : A). λ. y", "\\x : A. \\. y");
+        check_ascii_printing("Π(x : A). B → C", "forall (x : A). B -> C");
+        check_ascii_printing("Π(: A). 1", "forall (: A). 1");
+        check_ascii_printing("(A -> B) -> C", "(A -> B) -> C");
+    }
+
+    /// Checks that `input` is printed back as is on a line wide enough for
+    /// it.
+    fn check_printing_on_one_line(input: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let parsed_expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+
+        let printed =
+            Expression::to_string(&strings, &expressions, input.len(), parsed_expression).unwrap();
+
+        assert_eq!(printed, input);
+    }
+
+    #[test]
+    fn printing_handles_deep_expressions() {
+        let depth = 1_000_000;
+        check_printing_on_one_line(&format!("{}1", "λ. ".repeat(depth)));
+        check_printing_on_one_line(&format!("λx{}. x", " x".repeat(depth)));
+        check_printing_on_one_line(&format!("{}y", "let x = y in ".repeat(depth)));
+        check_printing_on_one_line(&format!("{}A", "A → ".repeat(depth)));
+
+        check_printing_on_one_line(&format!(
+            "{}f x{}",
+            "f (".repeat(depth - 1),
+            ")".repeat(depth - 1)
+        ));
+    }
+
+    #[test]
+    fn printing_handles_long_applications() {
+        check_printing_on_one_line(&format!("f{}", " xx".repeat(350_000)));
+    }
+
+    fn fuzz_test<R: Rng>(rng: &mut R, syntax: Syntax, max_depth: usize) {
         let mut strings = StringArena::new();
         let mut expressions = ExpressionArena::new();
         let environment = Rc::new(ReferencingEnvironment::new());
@@ -213,7 +1042,9 @@ mod tests {
         let expression =
             Expression::sample(&mut strings, &mut expressions, environment, rng, max_depth);
 
-        let input = Expression::to_string(&strings, &expressions, 80, expression).unwrap();
+        let input =
+            Expression::to_string_with_syntax(&strings, &expressions, syntax, 80, expression)
+                .unwrap();
 
         let parsed_expression =
             Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
@@ -231,7 +1062,8 @@ mod tests {
         let max_depth = 7;
         let test_count = 50;
         for _ in 0..test_count {
-            fuzz_test(&mut rng, max_depth);
+            fuzz_test(&mut rng, Syntax::Unicode, max_depth);
+            fuzz_test(&mut rng, Syntax::Ascii, max_depth);
         }
     }
 }
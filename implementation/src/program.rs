@@ -0,0 +1,178 @@
+use crate::{expression::ExpressionId, strings::StringId};
+
+/// Files of named definitions `def <name> = <expression>;`. Later definitions
+/// may refer to earlier ones as free variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    definitions: Vec<(StringId, ExpressionId)>,
+}
+
+impl Program {
+    #[inline]
+    pub fn new() -> Program {
+        Program {
+            definitions: Vec::new(),
+        }
+    }
+
+    /// The definitions of the program, in order of appearance.
+    #[inline]
+    pub fn definitions(&self) -> &[(StringId, ExpressionId)] {
+        &self.definitions
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    #[inline]
+    pub fn push(&mut self, name: StringId, expression: ExpressionId) {
+        self.definitions.push((name, expression));
+    }
+
+    /// Looks up the latest definition of `name`, if any.
+    pub fn lookup(&self, name: StringId) -> Option<ExpressionId> {
+        self.definitions
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|&(_, expression)| expression)
+    }
+}
+
+impl Default for Program {
+    fn default() -> Program {
+        Program::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        expression::{Expression, ExpressionArena},
+        referencing_environment::ReferencingEnvironment,
+        strings::StringArena,
+    };
+
+    use super::*;
+
+    #[test]
+    fn parsing_program_preserves_order() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let program = Program::parse_program(
+            &mut strings,
+            &mut expressions,
+            b"-- combinators\ndef id = \\x. x;\ndef k = \\x. \\_. x;\ndef ki = k id;\n",
+        )
+        .unwrap();
+
+        let names: Vec<&[u8]> = program
+            .definitions()
+            .iter()
+            .map(|&(name, _)| &strings[name])
+            .collect();
+        assert_eq!(names, [&b"id"[..], b"k", b"ki"]);
+
+        // Later definitions refer to earlier ones as free variables
+        let ki = program.lookup(strings.intern(b"ki")).unwrap();
+        let free_variables =
+            Expression::free_variables(Rc::new(ReferencingEnvironment::new()), &expressions, ki);
+        assert!(free_variables.contains(&strings.intern(b"k")));
+        assert!(free_variables.contains(&strings.intern(b"id")));
+    }
+
+    #[test]
+    fn lookup_finds_latest_definition() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let program =
+            Program::parse_program(&mut strings, &mut expressions, b"def x = a; def x = b;")
+                .unwrap();
+
+        let x = program.lookup(strings.intern(b"x")).unwrap();
+        assert_eq!(program.len(), 2);
+        assert_eq!(x, program.definitions()[1].1);
+        assert_eq!(program.lookup(strings.intern(b"y")), Option::None);
+    }
+
+    #[test]
+    fn parsing_empty_program() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let program =
+            Program::parse_program(&mut strings, &mut expressions, b"  {- nothing -}  ").unwrap();
+
+        assert!(program.is_empty());
+    }
+
+    fn check_program_error(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let error =
+            Program::parse_program(&mut strings, &mut expressions, input.as_bytes()).unwrap_err();
+
+        assert_eq!(error.to_string().lines().next().unwrap(), expected);
+    }
+
+    #[test]
+    fn parsing_program_errors() {
+        check_program_error(
+            "def id = λx. x",
+            "1:15: unexpected end of input, expected `;`",
+        );
+        check_program_error(
+            "def id = λx. x;\ndef = y;",
+            "2:5: unexpected `=`, expected a name",
+        );
+        check_program_error(
+            "id = λx. x;",
+            "1:1: unexpected `id`, expected `def` or end of input",
+        );
+    }
+
+    fn check_printing(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let program =
+            Program::parse_program(&mut strings, &mut expressions, input.as_bytes()).unwrap();
+        let printed = Program::to_string(&strings, &expressions, 80, &program).unwrap();
+        assert_eq!(printed, expected);
+
+        let reparsed =
+            Program::parse_program(&mut strings, &mut expressions, printed.as_bytes()).unwrap();
+        assert_eq!(program.len(), reparsed.len());
+        for (&(name, expression), &(reparsed_name, reparsed_expression)) in
+            program.definitions().iter().zip(reparsed.definitions())
+        {
+            assert_eq!(name, reparsed_name);
+            assert!(Expression::equals(
+                (&expressions, expression),
+                (&expressions, reparsed_expression)
+            ));
+        }
+    }
+
+    #[test]
+    fn printing_programs() {
+        check_printing("", "");
+        check_printing("def id=λx.x;", "def id = λx. x;\n");
+        check_printing(
+            "def id = λx. x; def k = λx. λ_. x; def s = λx y z. x z (y z);",
+            "def id = λx. x;\ndef k = λx _. x;\ndef s = λx y z. x z (y z);\n",
+        );
+    }
+}
@@ -5,7 +5,8 @@ use std::rc::Rc;
 use rand::Rng;
 
 use crate::{
-    expression::{DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+    expression::{CaseBranch, Constant, DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+    parser::is_keyword,
     referencing_environment::ReferencingEnvironment,
     strings::{StringArena, StringId},
 };
@@ -55,10 +56,14 @@ impl<'a, R: Rng> ExpressionSampler<'a, R> {
             return self.sample_variable_expression();
         }
 
-        match self.rng.gen_range(0..=7) {
+        match self.rng.gen_range(0..=11) {
             0 => self.sample_variable_expression(),
             1..=3 => self.sample_lambda_expression(max_depth),
             4..=7 => self.sample_application_expression(max_depth),
+            8 => self.sample_let_expression(max_depth),
+            9 => self.sample_pi_expression(max_depth),
+            10 => self.sample_case_expression(max_depth),
+            11 => self.sample_constant_expression(),
             _ => unreachable!(),
         }
     }
@@ -99,6 +104,24 @@ impl<'a, R: Rng> ExpressionSampler<'a, R> {
         self.expressions.nameless_variable(index)
     }
 
+    fn sample_constant_expression(&mut self) -> ExpressionId {
+        let constant = match self.rng.gen_range(0..=2) {
+            0 => Constant::Integer(self.rng.gen_range(-100..=100)),
+            1 => {
+                let len = self.rng.gen_range(0..=5);
+                let mut string = Vec::with_capacity(len);
+                for _ in 0..len {
+                    // Include characters that have to be escaped in string literals
+                    string.push(b"ab \"\\\n\t"[self.rng.gen_range(0..7)]);
+                }
+                Constant::String(self.strings.intern(&string))
+            }
+            2 => Constant::Boolean(self.rng.gen_bool(0.5)),
+            _ => unreachable!(),
+        };
+        self.expressions.constant(constant)
+    }
+
     fn sample_lambda_expression(&mut self, max_depth: usize) -> ExpressionId {
         debug_assert!(max_depth > 0);
         match self.rng.gen_range(0..=1) {
@@ -118,6 +141,7 @@ impl<'a, R: Rng> ExpressionSampler<'a, R> {
                 Option::Some(identifier)
             }
         };
+        let annotation = self.sample_annotation(max_depth);
         let body = match parameter {
             Option::Some(parameter) => {
                 self.environment.bind(parameter);
@@ -132,15 +156,28 @@ impl<'a, R: Rng> ExpressionSampler<'a, R> {
                 body
             }
         };
-        self.expressions.abstraction(parameter, body)
+        self.expressions
+            .annotated_abstraction(parameter, annotation, body)
     }
 
     fn sample_nameless_lambda_expression(&mut self, max_depth: usize) -> ExpressionId {
         debug_assert!(max_depth > 0);
+        let annotation = self.sample_annotation(max_depth);
         self.environment.shift();
         let body = self.sample_expression(max_depth - 1);
         self.environment.unshift();
-        self.expressions.nameless_abstraction(body)
+        self.expressions
+            .annotated_nameless_abstraction(annotation, body)
+    }
+
+    fn sample_annotation(&mut self, max_depth: usize) -> Option<ExpressionId> {
+        debug_assert!(max_depth > 0);
+        if self.rng.gen_bool(0.2) {
+            let annotation = self.sample_expression(max_depth - 1);
+            Option::Some(annotation)
+        } else {
+            Option::None
+        }
     }
 
     fn sample_application_expression(&mut self, max_depth: usize) -> ExpressionId {
@@ -155,6 +192,175 @@ impl<'a, R: Rng> ExpressionSampler<'a, R> {
         self.expressions.application(function, arguments)
     }
 
+    fn sample_let_expression(&mut self, max_depth: usize) -> ExpressionId {
+        debug_assert!(max_depth > 0);
+        match self.rng.gen_range(0..=3) {
+            0 => self.sample_named_let_expression(max_depth),
+            1 => self.sample_nameless_let_expression(max_depth),
+            2 => self.sample_named_let_rec_expression(max_depth),
+            3 => self.sample_nameless_let_rec_expression(max_depth),
+            _ => unreachable!(),
+        }
+    }
+
+    fn sample_named_let_expression(&mut self, max_depth: usize) -> ExpressionId {
+        debug_assert!(max_depth > 0);
+        let parameter = {
+            if self.rng.gen_bool(0.2) {
+                Option::None
+            } else {
+                let identifier = self.sample_identifier();
+                Option::Some(identifier)
+            }
+        };
+        let definition = self.sample_expression(max_depth - 1);
+        self.environment.bind_parameter(parameter);
+        let body = self.sample_expression(max_depth - 1);
+        self.environment.unbind_parameter(parameter);
+        self.expressions.let_binding(parameter, definition, body)
+    }
+
+    fn sample_nameless_let_expression(&mut self, max_depth: usize) -> ExpressionId {
+        debug_assert!(max_depth > 0);
+        let definition = self.sample_expression(max_depth - 1);
+        self.environment.shift();
+        let body = self.sample_expression(max_depth - 1);
+        self.environment.unshift();
+        self.expressions.nameless_let_binding(definition, body)
+    }
+
+    fn sample_named_let_rec_expression(&mut self, max_depth: usize) -> ExpressionId {
+        debug_assert!(max_depth > 0);
+        let definitions_count = self.rng.gen_range(1..=3);
+        let mut parameters = Vec::with_capacity(definitions_count);
+        for _ in 0..definitions_count {
+            let parameter = {
+                if self.rng.gen_bool(0.2) {
+                    Option::None
+                } else {
+                    let identifier = self.sample_identifier();
+                    Option::Some(identifier)
+                }
+            };
+            parameters.push(parameter);
+        }
+        self.environment.bind_parameters(&parameters);
+        let mut definitions = Vec::with_capacity(definitions_count);
+        for _ in 0..definitions_count {
+            let definition = self.sample_expression(max_depth - 1);
+            definitions.push(definition);
+        }
+        let body = self.sample_expression(max_depth - 1);
+        self.environment.unbind_parameters(&parameters);
+        self.expressions.let_rec(parameters, definitions, body)
+    }
+
+    fn sample_nameless_let_rec_expression(&mut self, max_depth: usize) -> ExpressionId {
+        debug_assert!(max_depth > 0);
+        let definitions_count = self.rng.gen_range(1..=3);
+        for _ in 0..definitions_count {
+            self.environment.shift();
+        }
+        let mut definitions = Vec::with_capacity(definitions_count);
+        for _ in 0..definitions_count {
+            let definition = self.sample_expression(max_depth - 1);
+            definitions.push(definition);
+        }
+        let body = self.sample_expression(max_depth - 1);
+        for _ in 0..definitions_count {
+            self.environment.unshift();
+        }
+        self.expressions.nameless_let_rec(definitions, body)
+    }
+
+    fn sample_pi_expression(&mut self, max_depth: usize) -> ExpressionId {
+        debug_assert!(max_depth > 0);
+        match self.rng.gen_range(0..=1) {
+            0 => self.sample_named_pi_expression(max_depth),
+            1 => self.sample_nameless_pi_expression(max_depth),
+            _ => unreachable!(),
+        }
+    }
+
+    fn sample_named_pi_expression(&mut self, max_depth: usize) -> ExpressionId {
+        debug_assert!(max_depth > 0);
+        let parameter = {
+            if self.rng.gen_bool(0.2) {
+                Option::None
+            } else {
+                let identifier = self.sample_identifier();
+                Option::Some(identifier)
+            }
+        };
+        let domain = self.sample_expression(max_depth - 1);
+        self.environment.bind_parameter(parameter);
+        let codomain = self.sample_expression(max_depth - 1);
+        self.environment.unbind_parameter(parameter);
+        self.expressions.pi(parameter, domain, codomain)
+    }
+
+    fn sample_nameless_pi_expression(&mut self, max_depth: usize) -> ExpressionId {
+        debug_assert!(max_depth > 0);
+        let domain = self.sample_expression(max_depth - 1);
+        self.environment.shift();
+        let codomain = self.sample_expression(max_depth - 1);
+        self.environment.unshift();
+        self.expressions.nameless_pi(domain, codomain)
+    }
+
+    fn sample_case_expression(&mut self, max_depth: usize) -> ExpressionId {
+        debug_assert!(max_depth > 0);
+        let scrutinee = self.sample_expression(max_depth - 1);
+        let branches_count = self.rng.gen_range(0..=3);
+        let mut branches = Vec::with_capacity(branches_count);
+        for _ in 0..branches_count {
+            let branch = self.sample_case_branch(max_depth);
+            branches.push(branch);
+        }
+        self.expressions.case(scrutinee, branches)
+    }
+
+    fn sample_case_branch(&mut self, max_depth: usize) -> CaseBranch {
+        debug_assert!(max_depth > 0);
+        let constructor = self.sample_identifier();
+        let arity = self.rng.gen_range(0..=3);
+        if self.rng.gen_bool(0.5) {
+            let mut parameters = Vec::with_capacity(arity);
+            for _ in 0..arity {
+                let parameter = {
+                    if self.rng.gen_bool(0.2) {
+                        Option::None
+                    } else {
+                        let identifier = self.sample_identifier();
+                        Option::Some(identifier)
+                    }
+                };
+                parameters.push(parameter);
+            }
+            self.environment.bind_parameters(&parameters);
+            let body = self.sample_expression(max_depth - 1);
+            self.environment.unbind_parameters(&parameters);
+            CaseBranch::Named {
+                constructor,
+                parameters,
+                body,
+            }
+        } else {
+            for _ in 0..arity {
+                self.environment.shift();
+            }
+            let body = self.sample_expression(max_depth - 1);
+            for _ in 0..arity {
+                self.environment.unshift();
+            }
+            CaseBranch::Nameless {
+                constructor,
+                arity,
+                body,
+            }
+        }
+    }
+
     fn sample_index(&mut self) -> DeBruijnIndex {
         debug_assert!(!self.environment.is_empty());
         let size = self.environment.len();
@@ -169,13 +375,20 @@ impl<'a, R: Rng> ExpressionSampler<'a, R> {
     }
 
     fn sample_identifier(&mut self) -> StringId {
-        let len = self.rng.gen_range(1..=5) as usize;
-        let mut identifier = Vec::with_capacity(len);
-        identifier.push(self.sample_alpha());
-        for _ in 1..len {
-            identifier.push(self.sample_alphanumeric());
+        loop {
+            let len = self.rng.gen_range(1..=5) as usize;
+            let mut identifier = Vec::with_capacity(len);
+            identifier.push(self.sample_alpha());
+            for _ in 1..len {
+                identifier.push(self.sample_alphanumeric());
+            }
+            if self.rng.gen_ratio(1, 4) {
+                self.sample_identifier_suffix(&mut identifier);
+            }
+            if !is_keyword(&identifier) {
+                return self.strings.intern(&identifier);
+            }
         }
-        self.strings.intern(&identifier)
     }
 
     fn sample_alpha(&mut self) -> u8 {
@@ -186,6 +399,27 @@ impl<'a, R: Rng> ExpressionSampler<'a, R> {
         }
     }
 
+    /// Extends `identifier` with a prime, an inner underscore or hyphen, or a
+    /// subscript digit.
+    fn sample_identifier_suffix(&mut self, identifier: &mut Vec<u8>) {
+        match self.rng.gen_range(0..4) {
+            0 => identifier.push(b'\''),
+            1 => {
+                identifier.push(b'_');
+                identifier.push(self.sample_alphanumeric());
+            }
+            2 => {
+                identifier.push(b'-');
+                identifier.push(self.sample_alpha());
+            }
+            _ => {
+                let subscript = char::from_u32('₀' as u32 + self.rng.gen_range(0..10)).unwrap();
+                let mut buffer = [0; 4];
+                identifier.extend(subscript.encode_utf8(&mut buffer).as_bytes());
+            }
+        }
+    }
+
     fn sample_alphanumeric(&mut self) -> u8 {
         if self.rng.gen_ratio(26 * 2, 26 * 2 + 10) {
             self.sample_alpha()
@@ -0,0 +1,563 @@
+use std::rc::Rc;
+
+use crate::{
+    expression::{Expression, ExpressionArena, ExpressionId},
+    referencing_environment::ReferencingEnvironment,
+};
+
+/// Strategies selecting the next redex to contract. Redexes are applications
+/// of abstractions to at least one argument, which are contracted with their
+/// first argument, and let-bindings.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// Contracts the leftmost outermost redex, including under binders. This
+    /// reaches the normal form of an expression whenever it has one.
+    NormalOrder,
+    /// Contracts the leftmost innermost redex, including under binders, such
+    /// that functions and arguments are normalized before being applied.
+    ApplicativeOrder,
+    /// Contracts the redex at the head of an expression, leaving arguments
+    /// unevaluated and not reducing under binders. This reaches weak head
+    /// normal forms.
+    CallByName,
+    /// Evaluates functions and then arguments before contracting a redex,
+    /// without reducing under binders. This reaches weak normal forms.
+    CallByValue,
+}
+
+/// The outcome of reducing an expression with a limited amount of fuel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Reduction {
+    /// `expression` has no redex for the strategy, and was reached in `steps`
+    /// steps.
+    NormalForm {
+        expression: ExpressionId,
+        steps: usize,
+    },
+    /// The fuel ran out after `steps` steps, with `expression` still having a
+    /// redex for the strategy.
+    OutOfFuel {
+        expression: ExpressionId,
+        steps: usize,
+    },
+}
+
+impl Reduction {
+    /// The expression reached by the reduction.
+    pub fn expression(&self) -> ExpressionId {
+        match self {
+            Reduction::NormalForm {
+                expression,
+                steps: _,
+            }
+            | Reduction::OutOfFuel {
+                expression,
+                steps: _,
+            } => *expression,
+        }
+    }
+
+    /// The number of steps taken.
+    pub fn steps(&self) -> usize {
+        match self {
+            Reduction::NormalForm {
+                expression: _,
+                steps,
+            }
+            | Reduction::OutOfFuel {
+                expression: _,
+                steps,
+            } => *steps,
+        }
+    }
+
+    /// Whether the reduction reached a normal form for the strategy.
+    pub fn is_normal_form(&self) -> bool {
+        matches!(
+            self,
+            Reduction::NormalForm {
+                expression: _,
+                steps: _,
+            }
+        )
+    }
+}
+
+impl Expression {
+    /// Contracts the next redex of `expression` for `strategy`, and constructs
+    /// the reduct in locally nameless representation in `destination`.
+    /// Returns `None` if `expression` has no redex for `strategy`.
+    pub fn reduce_step(
+        strategy: Strategy,
+        (environment, expressions, expression): (
+            Rc<ReferencingEnvironment>,
+            &ExpressionArena,
+            ExpressionId,
+        ),
+        destination: &mut ExpressionArena,
+    ) -> Option<ExpressionId> {
+        let expression = Expression::convert_to_locally_nameless(
+            (environment, expressions, expression),
+            destination,
+        );
+        Reducer::new(strategy, destination).step(expression)
+    }
+
+    /// Reduces `expression` for `strategy` until it has no redex, taking at
+    /// most `fuel` steps, and constructs the result in locally nameless
+    /// representation in `destination`.
+    pub fn reduce(
+        strategy: Strategy,
+        (environment, expressions, expression): (
+            Rc<ReferencingEnvironment>,
+            &ExpressionArena,
+            ExpressionId,
+        ),
+        destination: &mut ExpressionArena,
+        fuel: usize,
+    ) -> Reduction {
+        let expression = Expression::convert_to_locally_nameless(
+            (environment, expressions, expression),
+            destination,
+        );
+        Reducer::new(strategy, destination).reduce(expression, fuel)
+    }
+}
+
+/// Positions to search for the next redex in an expression, in order.
+enum Position {
+    /// The `child`-th sub-expression of the expression.
+    Child(usize),
+    /// The expression itself, which is a redex.
+    Redex,
+}
+
+/// Steps of the search for the next redex. Entries index the visited
+/// sub-expressions, which record their parent entry.
+enum Step {
+    Search { entry: usize },
+    Contract { entry: usize },
+}
+
+/// A visited sub-expression, with the entry of its parent and its position in
+/// that parent if any.
+struct Entry {
+    expression: ExpressionId,
+    parent: Option<(usize, usize)>,
+}
+
+pub(crate) struct Reducer<'a> {
+    strategy: Strategy,
+    expressions: &'a mut ExpressionArena,
+}
+
+impl<'a> Reducer<'a> {
+    pub(crate) fn new(strategy: Strategy, expressions: &'a mut ExpressionArena) -> Reducer<'a> {
+        Reducer {
+            strategy,
+            expressions,
+        }
+    }
+
+    fn reduce(mut self, mut expression: ExpressionId, fuel: usize) -> Reduction {
+        for steps in 0..fuel {
+            match self.step(expression) {
+                Option::Some(reduct) => expression = reduct,
+                Option::None => return Reduction::NormalForm { expression, steps },
+            }
+        }
+        if self.has_redex(expression) {
+            Reduction::OutOfFuel {
+                expression,
+                steps: fuel,
+            }
+        } else {
+            Reduction::NormalForm {
+                expression,
+                steps: fuel,
+            }
+        }
+    }
+
+    /// Contracts the next redex of `expression`, whose bound variables are all
+    /// nameless. Binders may keep their parameters, which are then kept in the
+    /// reduct. Returns `None` if `expression` has no redex.
+    pub(crate) fn step(&mut self, expression: ExpressionId) -> Option<ExpressionId> {
+        self.step_with_redex(expression)
+            .map(|(_redex_path, reduct)| reduct)
+    }
+
+    /// Contracts the next redex of `expression` like [Reducer::step]. Returns
+    /// the path of sub-expressions from `expression` down to the contracted
+    /// redex, and the reduct.
+    pub(crate) fn step_with_redex(
+        &mut self,
+        expression: ExpressionId,
+    ) -> Option<(Vec<ExpressionId>, ExpressionId)> {
+        let (entries, redex) = self.find_redex(expression)?;
+        let mut redex_path = vec![entries[redex].expression];
+        let mut reduct = self.contract(entries[redex].expression);
+        let mut parent = entries[redex].parent;
+        while let Option::Some((entry, child)) = parent {
+            let node = &self.expressions[entries[entry].expression];
+            let mut children = node.children();
+            children[child] = reduct;
+            let rebuilt = node.with_children(&children);
+            reduct = self.expressions.add(rebuilt);
+            redex_path.push(entries[entry].expression);
+            parent = entries[entry].parent;
+        }
+        redex_path.reverse();
+        Option::Some((redex_path, reduct))
+    }
+
+    /// Whether `expression` has a redex for the strategy.
+    pub(crate) fn has_redex(&self, expression: ExpressionId) -> bool {
+        self.find_redex(expression).is_some()
+    }
+
+    /// Finds the next redex of `expression`. Returns the visited
+    /// sub-expressions and the entry of the redex among them.
+    fn find_redex(&self, expression: ExpressionId) -> Option<(Vec<Entry>, usize)> {
+        let mut entries = vec![Entry {
+            expression,
+            parent: Option::None,
+        }];
+        let mut steps = vec![Step::Search { entry: 0 }];
+        while let Option::Some(step) = steps.pop() {
+            match step {
+                Step::Search { entry } => {
+                    let expression = entries[entry].expression;
+                    let children = self.expressions[expression].children();
+                    for position in self.positions(expression).into_iter().rev() {
+                        match position {
+                            Position::Child(child) => {
+                                steps.push(Step::Search {
+                                    entry: entries.len(),
+                                });
+                                entries.push(Entry {
+                                    expression: children[child],
+                                    parent: Option::Some((entry, child)),
+                                });
+                            }
+                            Position::Redex => steps.push(Step::Contract { entry }),
+                        }
+                    }
+                }
+                Step::Contract { entry } => return Option::Some((entries, entry)),
+            }
+        }
+        Option::None
+    }
+
+    fn is_redex(&self, expression: ExpressionId) -> bool {
+        match &self.expressions[expression] {
+            Expression::Application {
+                function,
+                arguments: _,
+            } => matches!(
+                self.expressions[*function],
+                Expression::Abstraction {
+                    parameter: _,
+                    annotation: _,
+                    body: _,
+                } | Expression::NamelessAbstraction {
+                    annotation: _,
+                    body: _,
+                }
+            ),
+            Expression::Let {
+                parameter: _,
+                definition: _,
+                body: _,
+            }
+            | Expression::NamelessLet {
+                definition: _,
+                body: _,
+            } => true,
+            _ => false,
+        }
+    }
+
+    /// The positions to search for the next redex in `expression`, in order.
+    fn positions(&self, expression: ExpressionId) -> Vec<Position> {
+        let node = &self.expressions[expression];
+        let is_redex = self.is_redex(expression);
+        let children = || (0..node.children().len()).map(Position::Child).collect();
+        match self.strategy {
+            Strategy::NormalOrder if is_redex => vec![Position::Redex],
+            Strategy::NormalOrder => children(),
+            Strategy::ApplicativeOrder => match node {
+                Expression::Application {
+                    function: _,
+                    arguments: _,
+                } if is_redex => vec![Position::Child(0), Position::Child(1), Position::Redex],
+                // Normalizes the body as the function, then the definition as
+                // the argument
+                Expression::Let {
+                    parameter: _,
+                    definition: _,
+                    body: _,
+                }
+                | Expression::NamelessLet {
+                    definition: _,
+                    body: _,
+                } => vec![Position::Child(1), Position::Child(0), Position::Redex],
+                _ => children(),
+            },
+            Strategy::CallByName => match node {
+                _ if is_redex => vec![Position::Redex],
+                Expression::Application {
+                    function: _,
+                    arguments: _,
+                } => vec![Position::Child(0)],
+                _ => vec![],
+            },
+            Strategy::CallByValue => match node {
+                Expression::Application {
+                    function: _,
+                    arguments: _,
+                } if is_redex => vec![Position::Child(1), Position::Redex],
+                Expression::Application {
+                    function: _,
+                    arguments: _,
+                } => children(),
+                Expression::Let {
+                    parameter: _,
+                    definition: _,
+                    body: _,
+                }
+                | Expression::NamelessLet {
+                    definition: _,
+                    body: _,
+                } => vec![Position::Child(0), Position::Redex],
+                _ => vec![],
+            },
+        }
+    }
+
+    /// Contracts the redex `expression`.
+    fn contract(&mut self, expression: ExpressionId) -> ExpressionId {
+        match &self.expressions[expression] {
+            Expression::Application {
+                function,
+                arguments,
+            } => {
+                let body = match self.expressions[*function] {
+                    Expression::Abstraction {
+                        parameter: _,
+                        annotation: _,
+                        body,
+                    }
+                    | Expression::NamelessAbstraction {
+                        annotation: _,
+                        body,
+                    } => body,
+                    _ => unreachable!(),
+                };
+                let argument = arguments[0];
+                let remaining = arguments[1..].to_vec();
+                let reduct = Expression::open(self.expressions, body, argument);
+                if remaining.is_empty() {
+                    reduct
+                } else {
+                    self.expressions.application(reduct, remaining)
+                }
+            }
+            Expression::Let {
+                parameter: _,
+                definition,
+                body,
+            }
+            | Expression::NamelessLet { definition, body } => {
+                let (definition, body) = (*definition, *body);
+                Expression::open(self.expressions, body, definition)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::strings::StringArena;
+
+    use super::*;
+
+    fn check_reduce(strategy: Strategy, input: &str, fuel: usize, expected: Option<(&str, usize)>) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+        let environment = Rc::new(ReferencingEnvironment::new());
+
+        let expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+        let reduction = Expression::reduce(
+            strategy,
+            (environment.clone(), &expressions, expression),
+            &mut destination,
+            fuel,
+        );
+        match expected {
+            Option::Some((expected, steps)) => {
+                let expected = Expression::parse_mixed_expression(
+                    &mut strings,
+                    &mut expressions,
+                    expected.as_bytes(),
+                )
+                .unwrap();
+                assert!(reduction.is_normal_form());
+                assert_eq!(reduction.steps(), steps);
+                assert!(Expression::alpha_equivalent(
+                    (environment.clone(), &destination, reduction.expression()),
+                    (environment.clone(), &expressions, expected)
+                ));
+            }
+            Option::None => {
+                assert!(!reduction.is_normal_form());
+                assert_eq!(reduction.steps(), fuel);
+            }
+        }
+    }
+
+    const OMEGA: &str = "((λx. x x) (λx. x x))";
+
+    #[test]
+    fn normal_order_reduces_to_normal_forms() {
+        let strategy = Strategy::NormalOrder;
+        check_reduce(strategy, "(λx. x) y", 10, Option::Some(("y", 1)));
+        check_reduce(strategy, "(λx y. x) a b", 10, Option::Some(("a", 2)));
+        check_reduce(strategy, "λz. (λx. x) z", 10, Option::Some(("λz. z", 1)));
+        check_reduce(
+            strategy,
+            &format!("(λx y. x) a {}", OMEGA),
+            10,
+            Option::Some(("a", 2)),
+        );
+        check_reduce(
+            strategy,
+            "(λm n f x. m f (n f x)) (λf x. f (f x)) (λf x. f (f x))",
+            20,
+            Option::Some(("λf x. f (f (f (f x)))", 6)),
+        );
+        check_reduce(
+            strategy,
+            "let x = (λy. y) a in f x",
+            10,
+            Option::Some(("f a", 2)),
+        );
+        check_reduce(strategy, OMEGA, 10, Option::None);
+    }
+
+    #[test]
+    fn applicative_order_normalizes_arguments_first() {
+        let strategy = Strategy::ApplicativeOrder;
+        check_reduce(
+            strategy,
+            "(λx. f x) ((λy. y) a)",
+            10,
+            Option::Some(("f a", 2)),
+        );
+        check_reduce(strategy, "λz. (λx. x) z", 10, Option::Some(("λz. z", 1)));
+        check_reduce(
+            strategy,
+            &format!("(λx y. x) a {}", OMEGA),
+            10,
+            Option::None,
+        );
+    }
+
+    #[test]
+    fn call_by_name_reduces_to_weak_head_normal_forms() {
+        let strategy = Strategy::CallByName;
+        check_reduce(
+            strategy,
+            "(λx. f x) ((λy. y) a)",
+            10,
+            Option::Some(("f ((λy. y) a)", 1)),
+        );
+        check_reduce(
+            strategy,
+            "λz. (λx. x) z",
+            10,
+            Option::Some(("λz. (λx. x) z", 0)),
+        );
+        check_reduce(
+            strategy,
+            &format!("(λx y. x) a {}", OMEGA),
+            10,
+            Option::Some(("a", 2)),
+        );
+    }
+
+    #[test]
+    fn call_by_value_evaluates_arguments_first() {
+        let strategy = Strategy::CallByValue;
+        check_reduce(
+            strategy,
+            "(λx. f x) ((λy. y) a)",
+            10,
+            Option::Some(("f a", 2)),
+        );
+        check_reduce(
+            strategy,
+            "λz. (λx. x) z",
+            10,
+            Option::Some(("λz. (λx. x) z", 0)),
+        );
+        check_reduce(strategy, "f ((λy. y) a)", 10, Option::Some(("f a", 1)));
+        check_reduce(
+            strategy,
+            &format!("(λx y. x) a {}", OMEGA),
+            10,
+            Option::None,
+        );
+    }
+
+    #[test]
+    fn reduce_step_contracts_one_redex() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+        let environment = Rc::new(ReferencingEnvironment::new());
+
+        let expression = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            "(λx y. y x) a b".as_bytes(),
+        )
+        .unwrap();
+        let expected = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            "(λy. y a) b".as_bytes(),
+        )
+        .unwrap();
+        let reduct = Expression::reduce_step(
+            Strategy::NormalOrder,
+            (environment.clone(), &expressions, expression),
+            &mut destination,
+        )
+        .unwrap();
+        assert!(Expression::alpha_equivalent(
+            (environment.clone(), &destination, reduct),
+            (environment.clone(), &expressions, expected)
+        ));
+
+        let normal_form = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            "λx. f x".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            Expression::reduce_step(
+                Strategy::NormalOrder,
+                (environment.clone(), &expressions, normal_form),
+                &mut destination,
+            ),
+            Option::None
+        );
+    }
+}
@@ -98,6 +98,32 @@ impl ReferencingEnvironment {
         self.unshift();
     }
 
+    pub fn bind_parameter(&mut self, parameter: Option<StringId>) {
+        match parameter {
+            Option::Some(identifier) => self.bind(identifier),
+            Option::None => self.shift(),
+        }
+    }
+
+    pub fn unbind_parameter(&mut self, parameter: Option<StringId>) {
+        match parameter {
+            Option::Some(identifier) => self.unbind(identifier),
+            Option::None => self.unshift(),
+        }
+    }
+
+    pub fn bind_parameters(&mut self, parameters: &[Option<StringId>]) {
+        for parameter in parameters {
+            self.bind_parameter(*parameter);
+        }
+    }
+
+    pub fn unbind_parameters(&mut self, parameters: &[Option<StringId>]) {
+        for parameter in parameters.iter().rev() {
+            self.unbind_parameter(*parameter);
+        }
+    }
+
     #[inline]
     pub fn shift(&mut self) {
         self.size += 1;
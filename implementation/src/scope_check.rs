@@ -0,0 +1,228 @@
+use crate::{
+    expression::{DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+    referencing_environment::ReferencingEnvironment,
+    strings::StringId,
+    visitor::{Scoping, Visitor},
+};
+
+/// De Bruijn indices that do not refer to a binder in scope, either because
+/// they are zero, or because they exceed the number of binders in scope.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ScopeError {
+    expression: ExpressionId,
+    index: usize,
+    binders: usize,
+}
+
+impl ScopeError {
+    /// The nameless variable with the offending index.
+    #[inline]
+    pub fn expression(self) -> ExpressionId {
+        self.expression
+    }
+
+    /// The offending index.
+    #[inline]
+    pub fn index(self) -> usize {
+        self.index
+    }
+
+    /// The number of binders in scope of the offending index, including those
+    /// of the outer environment.
+    #[inline]
+    pub fn binders(self) -> usize {
+        self.binders
+    }
+}
+
+impl std::fmt::Display for ScopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.index == 0 {
+            write!(f, "de Bruijn index 0 is invalid, as indices start at 1")
+        } else {
+            write!(
+                f,
+                "de Bruijn index {} is out of scope, as there are only {} binders in scope",
+                self.index, self.binders
+            )
+        }
+    }
+}
+
+impl std::error::Error for ScopeError {}
+
+impl Expression {
+    /// Checks that every de Bruijn index in `expression` refers to an enclosing
+    /// binder, or to a binder in `environment`. The offending indices are
+    /// reported in order of appearance.
+    pub fn check_scope(
+        environment: &ReferencingEnvironment,
+        expressions: &ExpressionArena,
+        expression: ExpressionId,
+    ) -> Vec<ScopeError> {
+        ScopeCheck::new(environment.len(), expressions).check_scope(expression)
+    }
+}
+
+struct ScopeCheck<'a> {
+    expressions: &'a ExpressionArena,
+    binders: usize,
+    errors: Vec<ScopeError>,
+}
+
+impl<'a> ScopeCheck<'a> {
+    pub fn new(binders: usize, expressions: &'a ExpressionArena) -> ScopeCheck<'a> {
+        ScopeCheck {
+            expressions,
+            binders,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn check_scope(mut self, expression: ExpressionId) -> Vec<ScopeError> {
+        self.visit_expression(expression);
+        self.errors
+    }
+}
+
+impl Scoping for ScopeCheck<'_> {
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders += parameters.len();
+    }
+
+    fn exit_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders -= parameters.len();
+    }
+}
+
+impl<'a> Visitor<'a> for ScopeCheck<'a> {
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn visit_nameless_variable(&mut self, expression: ExpressionId, index: DeBruijnIndex) {
+        let index = index.into_usize();
+        if index == 0 || index > self.binders {
+            self.errors.push(ScopeError {
+                expression,
+                index,
+                binders: self.binders,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        parser::ParseOptions,
+        span::{LineIndex, SourceMap},
+        strings::StringArena,
+    };
+
+    use super::*;
+
+    fn check_indices(input: &str, outer_binders: usize, expected: Vec<(usize, usize)>) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut environment = ReferencingEnvironment::new();
+        for _ in 0..outer_binders {
+            environment.shift();
+        }
+
+        let expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+
+        let errors: Vec<(usize, usize)> =
+            Expression::check_scope(&environment, &expressions, expression)
+                .into_iter()
+                .map(|error| (error.index(), error.binders()))
+                .collect();
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn check_scope_reports_dangling_indices() {
+        check_indices("λ. 1", 0, vec![]);
+        check_indices("λx. λ. 2 1 x", 0, vec![]);
+        check_indices("1", 0, vec![(1, 0)]);
+        check_indices("λ. 2", 0, vec![(2, 1)]);
+        check_indices("λ. 2 0", 1, vec![(0, 2)]);
+        check_indices("f 1 (λ. 1) 2", 1, vec![(2, 1)]);
+        check_indices("λ : 1. 1", 0, vec![(1, 0)]);
+        check_indices("let = 1 in 1", 0, vec![(1, 0)]);
+        check_indices("letrec = 2 and = 1 in 3", 0, vec![(3, 2)]);
+        check_indices("Π(: 1). 1", 0, vec![(1, 0)]);
+        check_indices("case 1 of { c a b => 3 | d/1 => 3 }", 1, vec![(3, 2)]);
+        check_indices("λ. 3 (λ. 3)", 0, vec![(3, 1), (3, 2)]);
+    }
+
+    fn check_parse_scope_errors(input: &str, expected: Vec<(usize, usize, String)>) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut source_map = SourceMap::new();
+        let mut environment = ReferencingEnvironment::new();
+        environment.bind(strings.intern(b"y"));
+        let options = ParseOptions::with_scope_check(Rc::new(environment));
+
+        let errors = Expression::parse_mixed_expression_with_options(
+            &mut strings,
+            &mut expressions,
+            &mut source_map,
+            &options,
+            input.as_bytes(),
+        )
+        .unwrap_err();
+
+        let index = LineIndex::new(input.as_bytes());
+        let actual: Vec<(usize, usize, String)> = errors
+            .iter()
+            .map(|error| {
+                let position = error.position();
+                assert_eq!(index.position(error.offset()), position);
+                (position.line(), position.column(), error.to_string())
+            })
+            .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parsing_reports_scope_errors_with_positions() {
+        check_parse_scope_errors(
+            "λ. 0",
+            vec![(
+                1,
+                4,
+                "1:4: de Bruijn index 0 is invalid, as indices start at 1\nλ. 0\n   ^".to_string(),
+            )],
+        );
+        check_parse_scope_errors(
+            "λ. 1 2\n  (λ. 4 3)",
+            vec![(
+                2,
+                7,
+                "2:7: de Bruijn index 4 is out of scope, as there are only 3 binders in \
+                     scope\n  (λ. 4 3)\n      ^"
+                    .to_string(),
+            )],
+        );
+    }
+
+    #[test]
+    fn parsing_without_scope_check_accepts_dangling_indices() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, "λ. 3".as_bytes())
+                .unwrap();
+
+        assert_eq!(
+            Expression::check_scope(&ReferencingEnvironment::new(), &expressions, expression).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use std::ops::Index;
+
+use crate::expression::ExpressionId;
+
+/// Ranges of byte offsets `start..end` into some source text.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    start: usize,
+    end: usize,
+}
+
+/// Line and column numbers in some source text. Both start at 1, and columns
+/// are counted in characters rather than bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    line: usize,
+    column: usize,
+}
+
+/// Side table associating [expressions](crate::expression::Expression) with
+/// the spans of source text they were parsed from. Expressions constructed
+/// without parsing have no span. The spans of comments are kept as well, so
+/// that they can be re-emitted alongside the expressions they surround.
+#[derive(Debug)]
+pub struct SourceMap {
+    spans: Vec<Option<Span>>,
+    comments: Vec<Span>,
+}
+
+/// Index of the line starts in some source text, for converting byte offsets
+/// into line and column numbers.
+#[derive(Debug)]
+pub struct LineIndex<'a> {
+    source: &'a [u8],
+    line_starts: Vec<usize>,
+}
+
+impl Span {
+    #[inline]
+    pub fn new(start: usize, end: usize) -> Span {
+        debug_assert!(start <= end);
+        Span { start, end }
+    }
+
+    #[inline]
+    pub fn start(self) -> usize {
+        self.start
+    }
+
+    #[inline]
+    pub fn end(self) -> usize {
+        self.end
+    }
+
+    #[inline]
+    pub fn len(self) -> usize {
+        self.end - self.start
+    }
+
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl Position {
+    #[inline]
+    pub fn new(line: usize, column: usize) -> Position {
+        debug_assert!(line > 0 && column > 0);
+        Position { line, column }
+    }
+
+    #[inline]
+    pub fn line(self) -> usize {
+        self.line
+    }
+
+    #[inline]
+    pub fn column(self) -> usize {
+        self.column
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl SourceMap {
+    #[inline]
+    pub fn new() -> SourceMap {
+        SourceMap {
+            spans: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, id: ExpressionId) -> Option<Span> {
+        self.spans.get(id.into_usize()).copied().flatten()
+    }
+
+    #[inline]
+    pub fn has(&self, id: ExpressionId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Associates the expression `id` with `span`, replacing the span
+    /// previously associated with it, if any.
+    pub fn set(&mut self, id: ExpressionId, span: Span) {
+        let index = id.into_usize();
+        if index >= self.spans.len() {
+            self.spans.resize(index + 1, Option::None);
+        }
+        self.spans[index] = Option::Some(span);
+    }
+
+    /// The spans of the comments in the source text, in order.
+    #[inline]
+    pub fn comments(&self) -> &[Span] {
+        &self.comments
+    }
+
+    /// Records a comment spanning `span`, which must start after the
+    /// previously recorded comments.
+    pub fn add_comment(&mut self, span: Span) {
+        debug_assert!(self
+            .comments
+            .last()
+            .into_iter()
+            .all(|last| last.end() <= span.start()));
+        self.comments.push(span);
+    }
+
+    /// The spans of the comments within the span of the expression `id`.
+    pub fn comments_within(&self, id: ExpressionId) -> &[Span] {
+        match self.get(id) {
+            Option::Some(span) => {
+                let start = self
+                    .comments
+                    .partition_point(|comment| comment.start() < span.start());
+                let end = self
+                    .comments
+                    .partition_point(|comment| comment.end() <= span.end());
+                &self.comments[start..end.max(start)]
+            }
+            Option::None => &[],
+        }
+    }
+}
+
+impl Default for SourceMap {
+    fn default() -> SourceMap {
+        SourceMap::new()
+    }
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a [u8]) -> LineIndex<'a> {
+        let mut line_starts = vec![0];
+        for (offset, &byte) in source.iter().enumerate() {
+            if byte == b'\n' {
+                line_starts.push(offset + 1);
+            }
+        }
+        LineIndex {
+            source,
+            line_starts,
+        }
+    }
+
+    /// Computes the line and column numbers of the byte offset `offset`,
+    /// which may be the offset just past the end of the source text.
+    pub fn position(&self, offset: usize) -> Position {
+        debug_assert!(offset <= self.source.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Result::Ok(line) => line,
+            Result::Err(next_line) => next_line - 1,
+        };
+        let line_start = self.line_starts[line];
+        // Count the characters before `offset` on its line, skipping UTF-8 continuation bytes
+        let column = self.source[line_start..offset]
+            .iter()
+            .filter(|&&byte| (byte & 0b1100_0000) != 0b1000_0000)
+            .count();
+        Position::new(line + 1, column + 1)
+    }
+
+    /// Computes the line and column numbers of the start and end of `span`.
+    #[inline]
+    pub fn range(&self, span: Span) -> (Position, Position) {
+        (self.position(span.start()), self.position(span.end()))
+    }
+}
+
+impl Index<Span> for [u8] {
+    type Output = [u8];
+
+    #[inline]
+    fn index(&self, index: Span) -> &Self::Output {
+        &self[index.start()..index.end()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expression::Expression, expression::ExpressionArena, strings::StringArena};
+
+    use super::*;
+
+    #[test]
+    fn source_map_is_initially_empty() {
+        let mut expressions = ExpressionArena::new();
+        let source_map = SourceMap::default();
+
+        let v = expressions.nameless_variable(1.into());
+
+        assert!(!source_map.has(v));
+        assert_eq!(source_map.get(v), Option::None);
+    }
+
+    #[test]
+    fn source_map_set_associates_span() {
+        let mut expressions = ExpressionArena::new();
+        let mut source_map = SourceMap::new();
+
+        let v1 = expressions.nameless_variable(1.into());
+        let v2 = expressions.nameless_variable(2.into());
+        source_map.set(v2, Span::new(3, 5));
+
+        assert!(!source_map.has(v1));
+        assert_eq!(source_map.get(v2), Option::Some(Span::new(3, 5)));
+    }
+
+    fn check_positions(source: &str, expected: Vec<(usize, usize, usize)>) {
+        let index = LineIndex::new(source.as_bytes());
+        for (offset, line, column) in expected {
+            assert_eq!(index.position(offset), Position::new(line, column));
+        }
+    }
+
+    #[test]
+    fn line_index_computes_positions() {
+        check_positions("", vec![(0, 1, 1)]);
+        check_positions("x y", vec![(0, 1, 1), (2, 1, 3), (3, 1, 4)]);
+        check_positions("λx.\n  x", vec![(0, 1, 1), (2, 1, 2), (5, 2, 1), (7, 2, 3)]);
+        check_positions("a\n\nb", vec![(1, 1, 2), (2, 2, 1), (3, 3, 1)]);
+    }
+
+    fn check_spans(input: &str, expected: Vec<&str>) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut source_map = SourceMap::new();
+
+        Expression::parse_mixed_expression_with_source_map(
+            &mut strings,
+            &mut expressions,
+            &mut source_map,
+            input.as_bytes(),
+        )
+        .unwrap();
+
+        // Every parsed expression has a span, and expressions are allocated in post-order
+        let mut spanned = Vec::with_capacity(expressions.len());
+        for index in 0..expressions.len() {
+            let span = source_map.get(ExpressionId::new(index)).unwrap();
+            spanned.push(std::str::from_utf8(&input.as_bytes()[span]).unwrap());
+        }
+        assert_eq!(spanned, expected);
+    }
+
+    #[test]
+    fn parsing_records_spans() {
+        check_spans("x", vec!["x"]);
+        check_spans("  f  x ", vec!["f", "x", "f  x"]);
+        check_spans("λx. f (x)", vec!["f", "x", "f (x)", "λx. f (x)"]);
+        check_spans("λx y. x", vec!["x", "y. x", "λx y. x"]);
+        check_spans("λ : T. 1", vec!["T", "1", "λ : T. 1"]);
+        check_spans("A → B", vec!["A", "B", "A → B"]);
+        check_spans("Π(x : A). x", vec!["A", "x", "Π(x : A). x"]);
+        check_spans("let x = #1 in x", vec!["#1", "x", "let x = #1 in x"]);
+        check_spans(
+            "letrec f = g and g = f in \"s\"",
+            vec!["g", "f", "\"s\"", "letrec f = g and g = f in \"s\""],
+        );
+        check_spans(
+            "case p of { c a => a | d/1 => true }",
+            vec!["p", "a", "true", "case p of { c a => a | d/1 => true }"],
+        );
+        check_spans("f\n  (λ.\n 1)", vec!["f", "1", "λ.\n 1", "f\n  (λ.\n 1)"]);
+    }
+
+    fn check_comments(input: &str, expected: Vec<&str>) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut source_map = SourceMap::new();
+
+        Expression::parse_mixed_expression_with_source_map(
+            &mut strings,
+            &mut expressions,
+            &mut source_map,
+            input.as_bytes(),
+        )
+        .unwrap();
+
+        let comments: Vec<&str> = source_map
+            .comments()
+            .iter()
+            .map(|&span| std::str::from_utf8(&input.as_bytes()[span]).unwrap())
+            .collect();
+        assert_eq!(comments, expected);
+    }
+
+    #[test]
+    fn parsing_records_comments() {
+        check_comments("x", vec![]);
+        check_comments("-- identity\nλx. x -- done", vec!["-- identity", "-- done"]);
+        check_comments(
+            "f {- a {- nested -} comment -} x",
+            vec!["{- a {- nested -} comment -}"],
+        );
+        check_comments("f \"-- not a comment\" {--}", vec!["{--}"]);
+    }
+
+    #[test]
+    fn comments_within_expressions() {
+        let input = "{- K -} λx. {- unused -} λ_. x -- returns x";
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut source_map = SourceMap::new();
+
+        let expression = Expression::parse_expression_with_source_map(
+            &mut strings,
+            &mut expressions,
+            &mut source_map,
+            input.as_bytes(),
+        )
+        .unwrap();
+
+        assert_eq!(source_map.comments().len(), 3);
+        let comments = source_map.comments_within(expression);
+        assert_eq!(comments.len(), 1);
+        assert_eq!(&input.as_bytes()[comments[0]], b"{- unused -}");
+        if let Expression::Abstraction { body, .. } = expressions[expression] {
+            assert!(source_map.comments_within(body).is_empty());
+        }
+    }
+
+    #[test]
+    fn line_index_computes_ranges_of_parsed_expressions() {
+        let input = "let x = y\nin λz.\n  x z";
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut source_map = SourceMap::new();
+
+        let expression = Expression::parse_expression_with_source_map(
+            &mut strings,
+            &mut expressions,
+            &mut source_map,
+            input.as_bytes(),
+        )
+        .unwrap();
+
+        let index = LineIndex::new(input.as_bytes());
+        let span = source_map.get(expression).unwrap();
+        assert_eq!(
+            index.range(span),
+            (Position::new(1, 1), Position::new(3, 6))
+        );
+        if let Expression::Let { body, .. } = expressions[expression] {
+            let span = source_map.get(body).unwrap();
+            assert_eq!(
+                index.range(span),
+                (Position::new(2, 4), Position::new(3, 6))
+            );
+        }
+    }
+}
@@ -103,6 +103,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::len_zero)]
     fn is_initially_empty() {
         let strings = StringArena::default();
         assert!(strings.len() == 0);
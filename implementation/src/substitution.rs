@@ -0,0 +1,500 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    admissible_variable_name_generator::AdmissibleVariableNameGenerator,
+    expression::{CaseBranch, DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+    referencing_environment::ReferencingEnvironment,
+    strings::{StringArena, StringId},
+    visitor::{Fold, Scoping},
+};
+
+impl Expression {
+    /// Substitutes `replacement` for the free occurrences of `variable` in
+    /// `target`, and constructs the result in `destination`. Binders of
+    /// `target` that would capture a free variable of `replacement` are
+    /// renamed using `variable_name_generator`, and the nameless variables of
+    /// `replacement` referring outside of it are shifted past the binders it
+    /// is substituted under.
+    pub fn substitute<G: AdmissibleVariableNameGenerator>(
+        strings: &mut StringArena,
+        expressions: &ExpressionArena,
+        target: ExpressionId,
+        variable: StringId,
+        replacement: ExpressionId,
+        variable_name_generator: G,
+        destination: &mut ExpressionArena,
+    ) -> ExpressionId {
+        let replacement_free_variables = Expression::free_variables(
+            Rc::new(ReferencingEnvironment::new()),
+            expressions,
+            replacement,
+        );
+        let renamings = Renaming::new(
+            strings,
+            expressions,
+            variable,
+            replacement_free_variables,
+            variable_name_generator,
+        )
+        .rename(target);
+        Substitution::new(expressions, destination, variable, replacement, renamings)
+            .substitute(target)
+    }
+}
+
+/// The parameters renamed by substitution, for the binders introduced by an
+/// expression over one of its sub-expressions.
+type Renamings = HashMap<(ExpressionId, usize), Vec<Option<StringId>>>;
+
+/// Determines the binders that would capture a free variable of the
+/// replacement, which are those binding a free variable of the replacement in
+/// a scope where the substituted variable occurs free.
+struct Renaming<'a, G: AdmissibleVariableNameGenerator> {
+    strings: &'a mut StringArena,
+    expressions: &'a ExpressionArena,
+    variable: StringId,
+    replacement_free_variables: HashSet<StringId>,
+    variable_name_generator: G,
+    /// The names that renamed parameters must avoid: the free variables of
+    /// the replacement, and the names seen so far in the target.
+    avoided: HashSet<StringId>,
+    renamings: Renamings,
+}
+
+impl<'a, G: AdmissibleVariableNameGenerator> Renaming<'a, G> {
+    fn new(
+        strings: &'a mut StringArena,
+        expressions: &'a ExpressionArena,
+        variable: StringId,
+        replacement_free_variables: HashSet<StringId>,
+        variable_name_generator: G,
+    ) -> Renaming<'a, G> {
+        let mut avoided = replacement_free_variables.clone();
+        avoided.insert(variable);
+        Renaming {
+            strings,
+            expressions,
+            variable,
+            replacement_free_variables,
+            variable_name_generator,
+            avoided,
+            renamings: HashMap::new(),
+        }
+    }
+
+    fn rename(mut self, expression: ExpressionId) -> Renamings {
+        self.fold_expression(expression);
+        self.renamings
+    }
+
+    /// Renames the `parameters` that are free variables of the replacement.
+    /// Returns `None` if there are none.
+    fn rename_parameters(
+        &mut self,
+        parameters: &[Option<StringId>],
+    ) -> Option<Vec<Option<StringId>>> {
+        let captures = parameters
+            .iter()
+            .flatten()
+            .any(|parameter| self.replacement_free_variables.contains(parameter));
+        if !captures {
+            return Option::None;
+        }
+        let renamed = parameters
+            .iter()
+            .map(|&parameter| match parameter {
+                Option::Some(name) if self.replacement_free_variables.contains(&name) => {
+                    let avoided = &self.avoided;
+                    let fresh = self
+                        .variable_name_generator
+                        .generate_admissible_name(self.strings, |name| !avoided.contains(&name));
+                    self.avoided.insert(fresh);
+                    Option::Some(fresh)
+                }
+                _ => parameter,
+            })
+            .collect();
+        Option::Some(renamed)
+    }
+}
+
+impl<G: AdmissibleVariableNameGenerator> Scoping for Renaming<'_, G> {
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.avoided.extend(parameters.iter().flatten());
+    }
+}
+
+impl<'a, G: AdmissibleVariableNameGenerator> Fold<'a> for Renaming<'a, G> {
+    /// Whether the substituted variable occurs free in the expression.
+    type Output = bool;
+
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn combine(&mut self, expression: ExpressionId, children: Vec<bool>) -> bool {
+        let node = &self.expressions[expression];
+        if let Expression::Variable { identifier } = node {
+            self.avoided.insert(*identifier);
+            return *identifier == self.variable;
+        }
+        let variable = Option::Some(self.variable);
+        let scopes: Vec<bool> = children
+            .iter()
+            .enumerate()
+            .map(|(child, &occurs)| occurs && !node.child_binders(child).contains(&variable))
+            .collect();
+        match node {
+            Expression::LetRec {
+                parameters,
+                definitions: _,
+                body: _,
+            } => {
+                // The parameters are bound in every sub-expression, so they
+                // are renamed alike in all of them
+                if scopes.contains(&true) {
+                    if let Option::Some(renamed) = self.rename_parameters(parameters) {
+                        for child in 0..children.len() {
+                            self.renamings.insert((expression, child), renamed.clone());
+                        }
+                    }
+                }
+            }
+            _ => {
+                for (child, &occurs) in scopes.iter().enumerate() {
+                    if occurs {
+                        if let Option::Some(renamed) =
+                            self.rename_parameters(&node.child_binders(child))
+                        {
+                            self.renamings.insert((expression, child), renamed);
+                        }
+                    }
+                }
+            }
+        }
+        scopes.contains(&true)
+    }
+}
+
+/// Copies the replacement into the destination, shifting the nameless
+/// variables referring outside of it by `amount`.
+struct Shifting<'a> {
+    expressions: &'a ExpressionArena,
+    destination: &'a mut ExpressionArena,
+    amount: usize,
+    binders: usize,
+}
+
+impl<'a> Shifting<'a> {
+    fn new(
+        expressions: &'a ExpressionArena,
+        destination: &'a mut ExpressionArena,
+        amount: usize,
+    ) -> Shifting<'a> {
+        Shifting {
+            expressions,
+            destination,
+            amount,
+            binders: 0,
+        }
+    }
+
+    fn shift(mut self, expression: ExpressionId) -> ExpressionId {
+        self.fold_expression(expression)
+    }
+}
+
+impl Scoping for Shifting<'_> {
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders += parameters.len();
+    }
+
+    fn exit_binders(&mut self, parameters: &[Option<StringId>]) {
+        self.binders -= parameters.len();
+    }
+}
+
+impl<'a> Fold<'a> for Shifting<'a> {
+    type Output = ExpressionId;
+
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn combine(&mut self, expression: ExpressionId, children: Vec<ExpressionId>) -> ExpressionId {
+        match self.expressions[expression] {
+            Expression::NamelessVariable { index } if index.into_usize() > self.binders => self
+                .destination
+                .nameless_variable(DeBruijnIndex::new(index.into_usize() + self.amount)),
+            ref node => self.destination.add(node.with_children(&children)),
+        }
+    }
+}
+
+/// Steps of the substitution.
+enum Step {
+    Visit(ExpressionId),
+    Bind {
+        expression: ExpressionId,
+        child: usize,
+    },
+    Unbind {
+        expression: ExpressionId,
+        child: usize,
+    },
+    /// Constructs the substituted expression for `expression` from its
+    /// substituted sub-expressions.
+    Build(ExpressionId),
+}
+
+struct Substitution<'a> {
+    expressions: &'a ExpressionArena,
+    destination: &'a mut ExpressionArena,
+    variable: StringId,
+    replacement: ExpressionId,
+    renamings: Renamings,
+    environment: ReferencingEnvironment,
+    /// The parameters in the destination of the binders in scope, from the
+    /// outermost to the innermost.
+    parameters: Vec<Option<StringId>>,
+}
+
+impl<'a> Substitution<'a> {
+    fn new(
+        expressions: &'a ExpressionArena,
+        destination: &'a mut ExpressionArena,
+        variable: StringId,
+        replacement: ExpressionId,
+        renamings: Renamings,
+    ) -> Substitution<'a> {
+        Substitution {
+            expressions,
+            destination,
+            variable,
+            replacement,
+            renamings,
+            environment: ReferencingEnvironment::new(),
+            parameters: Vec::new(),
+        }
+    }
+
+    fn substitute(mut self, expression: ExpressionId) -> ExpressionId {
+        let expressions = self.expressions;
+        let mut steps = vec![Step::Visit(expression)];
+        let mut results = Vec::new();
+        while let Option::Some(step) = steps.pop() {
+            match step {
+                Step::Visit(expression) => match self.substitute_leaf(expression) {
+                    Option::Some(substituted) => results.push(substituted),
+                    Option::None => {
+                        steps.push(Step::Build(expression));
+                        let node = &expressions[expression];
+                        for (child, id) in node.children().into_iter().enumerate().rev() {
+                            steps.push(Step::Unbind { expression, child });
+                            steps.push(Step::Visit(id));
+                            steps.push(Step::Bind { expression, child });
+                        }
+                    }
+                },
+                Step::Bind { expression, child } => {
+                    let parameters = expressions[expression].child_binders(child);
+                    self.environment.bind_parameters(&parameters);
+                    let renamed = self.destination_parameters(expression, child);
+                    self.parameters.extend(renamed);
+                }
+                Step::Unbind { expression, child } => {
+                    let parameters = expressions[expression].child_binders(child);
+                    self.environment.unbind_parameters(&parameters);
+                    self.parameters
+                        .truncate(self.parameters.len() - parameters.len());
+                }
+                Step::Build(expression) => {
+                    let count = expressions[expression].children().len();
+                    let children = results.split_off(results.len() - count);
+                    let substituted = self.build(expression, &children);
+                    results.push(substituted);
+                }
+            }
+        }
+        results.pop().unwrap()
+    }
+
+    /// Substitutes in `expression` if it has no sub-expressions.
+    fn substitute_leaf(&mut self, expression: ExpressionId) -> Option<ExpressionId> {
+        let substituted = match &self.expressions[expression] {
+            Expression::Variable { identifier } => match self.environment.lookup_index(*identifier)
+            {
+                Option::Some(index) => {
+                    // `expression` is bound, and its binder may have been renamed
+                    let parameter = self.parameters[self.parameters.len() - index.into_usize()];
+                    self.destination.variable(parameter.unwrap())
+                }
+                Option::None if *identifier == self.variable => {
+                    Shifting::new(self.expressions, self.destination, self.parameters.len())
+                        .shift(self.replacement)
+                }
+                Option::None => self.destination.variable(*identifier),
+            },
+            node @ (Expression::NamelessVariable { index: _ }
+            | Expression::Constant { constant: _ }) => {
+                self.destination.add(node.with_children(&[]))
+            }
+            _ => return Option::None,
+        };
+        Option::Some(substituted)
+    }
+
+    /// The parameters in the destination of the binders introduced by
+    /// `expression` over its `child`-th sub-expression.
+    fn destination_parameters(
+        &self,
+        expression: ExpressionId,
+        child: usize,
+    ) -> Vec<Option<StringId>> {
+        match self.renamings.get(&(expression, child)) {
+            Option::Some(renamed) => renamed.clone(),
+            Option::None => self.expressions[expression]
+                .child_binders(child)
+                .into_owned(),
+        }
+    }
+
+    /// Constructs the substituted expression for `expression` from its
+    /// substituted sub-expressions, with its binders renamed.
+    fn build(&mut self, expression: ExpressionId, children: &[ExpressionId]) -> ExpressionId {
+        let mut substituted = self.expressions[expression].with_children(children);
+        let last = children.len() - 1;
+        match &mut substituted {
+            Expression::Abstraction {
+                parameter,
+                annotation: _,
+                body: _,
+            }
+            | Expression::Let {
+                parameter,
+                definition: _,
+                body: _,
+            }
+            | Expression::Pi {
+                parameter,
+                domain: _,
+                codomain: _,
+            } => {
+                *parameter = self.destination_parameters(expression, last)[0];
+            }
+            Expression::LetRec {
+                parameters,
+                definitions: _,
+                body: _,
+            } => {
+                *parameters = self.destination_parameters(expression, 0);
+            }
+            Expression::Case {
+                scrutinee: _,
+                branches,
+            } => {
+                for (index, branch) in branches.iter_mut().enumerate() {
+                    if let CaseBranch::Named {
+                        constructor: _,
+                        parameters,
+                        body: _,
+                    } = branch
+                    {
+                        *parameters = self.destination_parameters(expression, index + 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+        self.destination.add(substituted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admissible_variable_name_generator::VariableNameGenerator;
+
+    use super::*;
+
+    fn check_substitute(input: &str, variable: &str, replacement: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+
+        let target =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+        let replacement = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            replacement.as_bytes(),
+        )
+        .unwrap();
+        let expected =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, expected.as_bytes())
+                .unwrap();
+
+        let variable = strings.intern(variable.as_bytes());
+        let substituted = Expression::substitute(
+            &mut strings,
+            &expressions,
+            target,
+            variable,
+            replacement,
+            VariableNameGenerator::new(),
+            &mut destination,
+        );
+        assert!(Expression::equals(
+            (&destination, substituted),
+            (&expressions, expected)
+        ));
+    }
+
+    #[test]
+    fn substitute_replaces_free_occurrences() {
+        check_substitute("x", "x", "y", "y");
+        check_substitute("z", "x", "y", "z");
+        check_substitute("f x (g x)", "x", "λa. a", "f (λa. a) (g (λa. a))");
+        check_substitute("λz. x z", "x", "y", "λz. y z");
+        check_substitute("λx. x", "x", "y", "λx. x");
+        check_substitute("let x = x in x", "x", "y", "let x = y in x");
+        check_substitute("λ : x. x", "x", "A", "λ : A. A");
+        check_substitute(
+            "case x of { c x => x | d/1 => x }",
+            "x",
+            "y",
+            "case y of { c x => x | d/1 => y }",
+        );
+    }
+
+    #[test]
+    fn substitute_renames_capturing_binders() {
+        check_substitute("λy. x y", "x", "y", "λz. y z");
+        check_substitute("λy. λz. x y z", "x", "f y", "λx1. λz. (f y) x1 z");
+        check_substitute("λy. z y", "x", "y", "λy. z y");
+        check_substitute("λy. λ. x 1 2", "x", "y", "λz. λ. y 1 2");
+        check_substitute("let y = x in λy. x", "x", "y", "let x1 = y in λz. y");
+        check_substitute(
+            "case p of { c y => x y | d y => y }",
+            "x",
+            "y",
+            "case p of { c z => y z | d y => y }",
+        );
+        check_substitute(
+            "letrec f = λn. x f and g = f in g",
+            "x",
+            "f",
+            "letrec y = λn. f y and g = y in g",
+        );
+    }
+
+    #[test]
+    fn substitute_shifts_nameless_variables() {
+        check_substitute("λ. x 1", "x", "1", "λ. 2 1");
+        check_substitute("λy. λ. x", "x", "λ. 1 2", "λy. λ. λ. 1 4");
+        check_substitute("let = 1 in x", "x", "1", "let = 1 in 2");
+    }
+}
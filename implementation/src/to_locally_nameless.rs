@@ -1,8 +1,9 @@
 use std::rc::Rc;
 
 use crate::{
-    expression::{Expression, ExpressionArena, ExpressionId},
+    expression::{CaseBranch, Expression, ExpressionArena, ExpressionId},
     referencing_environment::ReferencingEnvironment,
+    visitor::{Fold, Scoping},
 };
 
 impl Expression {
@@ -38,7 +39,29 @@ impl<'a> Indexing<'a> {
         }
     }
 
-    fn convert_to_locally_nameless(&mut self, expression: ExpressionId) -> ExpressionId {
+    pub fn convert(mut self, expression: ExpressionId) -> ExpressionId {
+        self.fold_expression(expression)
+    }
+}
+
+impl Scoping for Indexing<'_> {
+    fn environment(&mut self) -> Option<&mut ReferencingEnvironment> {
+        Option::Some(self.environment)
+    }
+}
+
+impl<'a> Fold<'a> for Indexing<'a> {
+    type Output = ExpressionId;
+
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.arena
+    }
+
+    fn combine(
+        &mut self,
+        expression: ExpressionId,
+        mut children: Vec<ExpressionId>,
+    ) -> ExpressionId {
         match &self.arena[expression] {
             Expression::Variable { identifier } => {
                 match self.environment.lookup_index(*identifier) {
@@ -47,45 +70,77 @@ impl<'a> Indexing<'a> {
                 }
             }
             Expression::NamelessVariable { index } => self.destination.nameless_variable(*index),
-            Expression::Abstraction { parameter, body } => match parameter {
-                Option::Some(parameter) => {
-                    self.environment.bind(*parameter);
-                    let indexed_body = self.convert_to_locally_nameless(*body);
-                    self.environment.unbind(*parameter);
-                    self.destination.nameless_abstraction(indexed_body)
-                }
-                Option::None => {
-                    self.environment.shift();
-                    let indexed_body = self.convert_to_locally_nameless(*body);
-                    self.environment.unshift();
-                    self.destination.nameless_abstraction(indexed_body)
-                }
-            },
-            Expression::NamelessAbstraction { body } => {
-                self.environment.shift();
-                let indexed_body = self.convert_to_locally_nameless(*body);
-                self.environment.unshift();
-                self.destination.nameless_abstraction(indexed_body)
+            Expression::Constant { constant } => self.destination.constant(*constant),
+            Expression::Abstraction {
+                parameter: _,
+                annotation,
+                body: _,
             }
-            Expression::Application {
-                function,
-                arguments,
+            | Expression::NamelessAbstraction {
+                annotation,
+                body: _,
             } => {
-                let indexed_function = self.convert_to_locally_nameless(*function);
-                let mut indexed_arguments = Vec::with_capacity(arguments.len());
-                for &argument in arguments {
-                    let indexed_argument = self.convert_to_locally_nameless(argument);
-                    indexed_arguments.push(indexed_argument)
-                }
+                let indexed_body = children.pop().unwrap();
+                let indexed_annotation = annotation.map(|_| children[0]);
                 self.destination
-                    .application(indexed_function, indexed_arguments)
+                    .annotated_nameless_abstraction(indexed_annotation, indexed_body)
+            }
+            Expression::Application {
+                function: _,
+                arguments: _,
+            } => {
+                let indexed_arguments = children.split_off(1);
+                self.destination.application(children[0], indexed_arguments)
+            }
+            Expression::Let {
+                parameter: _,
+                definition: _,
+                body: _,
+            }
+            | Expression::NamelessLet {
+                definition: _,
+                body: _,
+            } => self
+                .destination
+                .nameless_let_binding(children[0], children[1]),
+            Expression::LetRec {
+                parameters: _,
+                definitions: _,
+                body: _,
+            }
+            | Expression::NamelessLetRec {
+                definitions: _,
+                body: _,
+            } => {
+                let indexed_body = children.pop().unwrap();
+                self.destination.nameless_let_rec(children, indexed_body)
+            }
+            Expression::Pi {
+                parameter: _,
+                domain: _,
+                codomain: _,
+            }
+            | Expression::NamelessPi {
+                domain: _,
+                codomain: _,
+            } => self.destination.nameless_pi(children[0], children[1]),
+            Expression::Case {
+                scrutinee: _,
+                branches,
+            } => {
+                let indexed_branches = branches
+                    .iter()
+                    .zip(&children[1..])
+                    .map(|(branch, &indexed_body)| CaseBranch::Nameless {
+                        constructor: branch.constructor(),
+                        arity: branch.arity(),
+                        body: indexed_body,
+                    })
+                    .collect();
+                self.destination.case(children[0], indexed_branches)
             }
         }
     }
-
-    pub fn convert(mut self, expression: ExpressionId) -> ExpressionId {
-        self.convert_to_locally_nameless(expression)
-    }
 }
 
 #[cfg(test)]
@@ -156,6 +211,32 @@ mod tests {
             "λ. λ. λ. 3 1 (2 1)",
         );
         check_to_locally_nameless_structural_equality("x z (y z)", "x z (y z)");
+        check_to_locally_nameless_structural_equality("let x = y in x", "let = y in 1");
+        check_to_locally_nameless_structural_equality("let x = x in x", "let = x in 1");
+        check_to_locally_nameless_structural_equality("λx. let y = x in x y", "λ. let = 1 in 2 1");
+        check_to_locally_nameless_structural_equality("let _ = x in λy. y", "let = x in λ. 1");
+        check_to_locally_nameless_structural_equality("λx : T. x", "λ : T. 1");
+        check_to_locally_nameless_structural_equality("λx. λx : x. x", "λ. λ : 1. 1");
+        check_to_locally_nameless_structural_equality("λx : x. λy : x. y", "λ : x. λ : 1. 1");
+        check_to_locally_nameless_structural_equality("A → B", "Π(: A). B");
+        check_to_locally_nameless_structural_equality("Π(A : Type). A → A", "Π(: Type). Π(: 1). 2");
+        check_to_locally_nameless_structural_equality("λx. x #0 \"x\" true", "λ. 1 #0 \"x\" true");
+        check_to_locally_nameless_structural_equality(
+            "letrec f = λx. g x and g = f in f h",
+            "letrec = λ. 2 1 and = 2 in 2 h",
+        );
+        check_to_locally_nameless_structural_equality(
+            "λx. letrec f = x f and _ = x in x",
+            "λ. letrec = 3 2 and = 3 in 3",
+        );
+        check_to_locally_nameless_structural_equality(
+            "λp. case p of { pair a b => f a b | nil => p }",
+            "λ. case 1 of { pair/2 => f 2 1 | nil/0 => 1 }",
+        );
+        check_to_locally_nameless_structural_equality(
+            "case p of { triple a _ a => a }",
+            "case p of { triple/3 => 1 }",
+        );
     }
 
     fn fuzz_test<R: Rng>(rng: &mut R, max_depth: usize) {
@@ -198,4 +279,35 @@ mod tests {
             fuzz_test(&mut rng, max_depth);
         }
     }
+
+    #[test]
+    fn conversion_handles_deep_expressions() {
+        let mut strings = StringArena::new();
+        let mut named_expressions = ExpressionArena::new();
+        let mut nameless_expressions = ExpressionArena::new();
+
+        let depth = 1_000_000;
+        let x = strings.intern(b"x");
+        let mut expression = named_expressions.variable(x);
+        for _ in 0..depth {
+            let definition = named_expressions.variable(x);
+            expression = named_expressions.let_binding(Option::Some(x), definition, expression);
+        }
+        let nameless_expression = Expression::convert_to_locally_nameless(
+            (
+                Rc::new(ReferencingEnvironment::new()),
+                &named_expressions,
+                expression,
+            ),
+            &mut nameless_expressions,
+        );
+        assert!(Expression::is_locally_nameless(
+            &nameless_expressions,
+            nameless_expression
+        ));
+        assert_eq!(
+            Expression::height(&nameless_expressions, nameless_expression),
+            depth
+        );
+    }
 }
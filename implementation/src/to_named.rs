@@ -6,7 +6,7 @@ use std::{
 
 use crate::{
     admissible_variable_name_generator::AdmissibleVariableNameGenerator,
-    expression::{DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+    expression::{CaseBranch, DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
     strings::{StringArena, StringId},
 };
 
@@ -117,6 +117,33 @@ impl Constraint {
     }
 }
 
+/// Identifies the `index`-th binder introduced by `expression`. Case
+/// expressions introduce one binder per pattern variable, numbered across all
+/// branches, recursive let-bindings introduce one binder per definition, and
+/// every other binding construct introduces a single binder.
+///
+/// Binders introduced together by the same expression, such as the variables
+/// of a pattern or the parameters of a recursive let-binding, are brought into
+/// scope from left to right. Unlike nested binders, they may not shadow each
+/// other, so each of them is restricted by the names of all the others.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct BinderId {
+    expression: ExpressionId,
+    index: usize,
+}
+
+impl BinderId {
+    #[inline]
+    fn new(expression: ExpressionId, index: usize) -> BinderId {
+        BinderId { expression, index }
+    }
+
+    #[inline]
+    fn single(expression: ExpressionId) -> BinderId {
+        BinderId::new(expression, 0)
+    }
+}
+
 struct Binder {
     source_parameter: Option<StringId>,
     destination_parameter: IdentifierId,
@@ -180,7 +207,7 @@ impl Binder {
 }
 
 struct BinderStore {
-    binders: HashMap<ExpressionId, Binder>,
+    binders: HashMap<BinderId, Binder>,
 }
 
 impl BinderStore {
@@ -192,18 +219,18 @@ impl BinderStore {
     }
 
     #[inline]
-    fn set(&mut self, expression: ExpressionId, binder: Binder) {
-        self.binders.insert(expression, binder);
+    fn set(&mut self, binder_id: BinderId, binder: Binder) {
+        self.binders.insert(binder_id, binder);
     }
 
     #[inline]
-    fn get(&self, expression: ExpressionId) -> Option<&Binder> {
-        self.binders.get(&expression)
+    fn get(&self, binder_id: BinderId) -> Option<&Binder> {
+        self.binders.get(&binder_id)
     }
 
     #[inline]
-    fn get_mut(&mut self, expression: ExpressionId) -> Option<&mut Binder> {
-        self.binders.get_mut(&expression)
+    fn get_mut(&mut self, binder_id: BinderId) -> Option<&mut Binder> {
+        self.binders.get_mut(&binder_id)
     }
 }
 
@@ -216,7 +243,7 @@ impl Default for BinderStore {
 
 struct ReferencingEnvironment {
     bindings_map: HashMap<StringId, Vec<IdentifierId>>,
-    binders_stack: Vec<ExpressionId>,
+    binders_stack: Vec<BinderId>,
 }
 
 impl ReferencingEnvironment {
@@ -228,7 +255,7 @@ impl ReferencingEnvironment {
         }
     }
 
-    fn bind(&mut self, name: StringId, identifier: IdentifierId, binder: ExpressionId) {
+    fn bind(&mut self, name: StringId, identifier: IdentifierId, binder: BinderId) {
         match self.bindings_map.entry(name) {
             Entry::Occupied(mut stack) => {
                 stack.get_mut().push(identifier);
@@ -252,7 +279,7 @@ impl ReferencingEnvironment {
     }
 
     #[inline]
-    fn shift(&mut self, binder: ExpressionId) {
+    fn shift(&mut self, binder: BinderId) {
         self.binders_stack.push(binder);
     }
 
@@ -269,12 +296,12 @@ impl ReferencingEnvironment {
     }
 
     #[inline]
-    fn lookup_binder(&self, index: DeBruijnIndex) -> ExpressionId {
+    fn lookup_binder(&self, index: DeBruijnIndex) -> BinderId {
         self.binders_stack[self.binders_stack.len() - index.into_usize()]
     }
 
     #[inline]
-    fn binders_iter(&self) -> Rev<Iter<'_, ExpressionId>> {
+    fn binders_iter(&self) -> Rev<Iter<'_, BinderId>> {
         self.binders_stack.iter().rev()
     }
 }
@@ -285,6 +312,78 @@ impl Default for ReferencingEnvironment {
     }
 }
 
+/// Steps of the traversals.
+enum Step {
+    Visit(ExpressionId),
+    /// Brings into scope the binders introduced by `expression` over its
+    /// `child`-th sub-expression.
+    Bind {
+        expression: ExpressionId,
+        child: usize,
+    },
+    Unbind {
+        expression: ExpressionId,
+        child: usize,
+    },
+    /// Constructs the named expression for `expression` from its converted
+    /// sub-expressions.
+    Build(ExpressionId),
+}
+
+/// Schedules visiting the sub-expressions of `expression` in order, each
+/// within the scope of the binders over it.
+fn push_children(steps: &mut Vec<Step>, expressions: &ExpressionArena, expression: ExpressionId) {
+    let node = &expressions[expression];
+    let children = node.children();
+    match node {
+        Expression::LetRec {
+            parameters: _,
+            definitions: _,
+            body: _,
+        }
+        | Expression::NamelessLetRec {
+            definitions: _,
+            body: _,
+        } => {
+            // Every definition is in the scope of every parameter, and the
+            // binders are registered once
+            steps.push(Step::Unbind {
+                expression,
+                child: 0,
+            });
+            steps.extend(children.into_iter().rev().map(Step::Visit));
+            steps.push(Step::Bind {
+                expression,
+                child: 0,
+            });
+        }
+        _ => {
+            for (child, &id) in children.iter().enumerate().rev() {
+                let binds = !node.child_binders(child).is_empty();
+                if binds {
+                    steps.push(Step::Unbind { expression, child });
+                }
+                steps.push(Step::Visit(id));
+                if binds {
+                    steps.push(Step::Bind { expression, child });
+                }
+            }
+        }
+    }
+}
+
+/// The index of the first binder introduced by `expression` over its
+/// `child`-th sub-expression.
+fn first_binder(expression: &Expression, child: usize) -> usize {
+    match expression {
+        Expression::Case {
+            scrutinee: _,
+            branches,
+        } => branches[..child - 1].iter().map(CaseBranch::arity).sum(),
+        _ => 0,
+    }
+}
+
 struct BinderStoreBuilder<'a> {
     expressions: &'a ExpressionArena,
     identifiers: &'a mut IdentifierArena,
@@ -307,6 +406,25 @@ impl<'a> BinderStoreBuilder<'a> {
     }
 
     fn visit(&mut self, expression: ExpressionId) {
+        let expressions = self.expressions;
+        let mut steps = vec![Step::Visit(expression)];
+        while let Option::Some(step) = steps.pop() {
+            match step {
+                Step::Visit(expression) => self.visit_node(&mut steps, expression),
+                Step::Bind { expression, child } => {
+                    let node = &expressions[expression];
+                    let parameters = node.child_binders(child);
+                    self.bind_parameters(expression, first_binder(node, child), &parameters);
+                }
+                Step::Unbind { expression, child } => {
+                    self.unbind_parameters(&expressions[expression].child_binders(child));
+                }
+                Step::Build(_) => unreachable!(),
+            }
+        }
+    }
+
+    fn visit_node(&mut self, steps: &mut Vec<Step>, expression: ExpressionId) {
         match &self.expressions[expression] {
             Expression::Variable {
                 identifier: variable,
@@ -315,8 +433,8 @@ impl<'a> BinderStoreBuilder<'a> {
                     // `expression` is a bound variable
                     // Constraints to add to the binder for `expression` to avoid unnecessary renamings
                     let mut undesirables = Vec::new();
-                    for binder_expression in self.environment.binders_iter() {
-                        let binder = self.binders.get_mut(*binder_expression).unwrap();
+                    for binder_id in self.environment.binders_iter() {
+                        let binder = self.binders.get_mut(*binder_id).unwrap();
                         if binder.destination_parameter == identifier {
                             // Found the binder for bound variable `expression`
                             for undesirable in undesirables {
@@ -325,9 +443,9 @@ impl<'a> BinderStoreBuilder<'a> {
                             binder.mark_used();
                             break;
                         }
-                        if binder.source_parameter.is_some() {
+                        if let Option::Some(source_parameter) = binder.source_parameter {
                             // If the binder for `expression` needs renaming, avoid this binder's given parameter name
-                            undesirables.push(binder.source_parameter.unwrap());
+                            undesirables.push(source_parameter);
                         }
                         binder.add_identifier_restriction(identifier);
                     }
@@ -340,74 +458,105 @@ impl<'a> BinderStoreBuilder<'a> {
                 };
             }
             Expression::NamelessVariable { index } => {
-                let binder_expression = self.environment.lookup_binder(*index);
+                let binder_id = self.environment.lookup_binder(*index);
                 let (binder_source_parameter_name, binder_destination_parameter_identifier) = {
-                    let binder = self.binders.get(binder_expression).unwrap();
+                    let binder = self.binders.get(binder_id).unwrap();
                     (binder.source_parameter, binder.destination_parameter)
                 };
 
                 // Constraints to add to the binder for `expression` to avoid unnecessary renamings
                 let mut undesirables = Vec::new();
-                for sub_binder_expression in
-                    self.environment.binders_iter().take(index.into_usize() - 1)
-                {
-                    let sub_binder = self.binders.get_mut(*sub_binder_expression).unwrap();
+                for sub_binder_id in self.environment.binders_iter().take(index.into_usize() - 1) {
+                    let sub_binder = self.binders.get_mut(*sub_binder_id).unwrap();
 
                     // `sub_binder` can't use the same parameter as `binder`
                     sub_binder.add_identifier_restriction(binder_destination_parameter_identifier);
 
-                    if sub_binder.source_parameter.is_some()
-                        && binder_source_parameter_name != sub_binder.source_parameter
-                    {
-                        // `binder` should avoid using the same parameter as `sub_binder`
-                        undesirables.push(sub_binder.source_parameter.unwrap());
+                    if let Option::Some(sub_binder_source_parameter) = sub_binder.source_parameter {
+                        if binder_source_parameter_name != Option::Some(sub_binder_source_parameter)
+                        {
+                            // `binder` should avoid using the same parameter as `sub_binder`
+                            undesirables.push(sub_binder_source_parameter);
+                        }
                     }
                 }
 
-                let binder = self.binders.get_mut(binder_expression).unwrap();
+                let binder = self.binders.get_mut(binder_id).unwrap();
                 binder.mark_used();
                 for undesirable in undesirables {
                     binder.add_string_undesirable(undesirable);
                 }
             }
-            Expression::Abstraction { parameter, body } => {
-                let parameter_identifier = self.identifiers.new_identifier();
-                let binder = Binder::new(*parameter, parameter_identifier);
-                self.binders.set(expression, binder);
-                match parameter {
-                    Option::Some(parameter) => {
-                        self.environment
-                            .bind(*parameter, parameter_identifier, expression);
-                        self.visit(*body);
-                        self.environment.unbind(*parameter);
-                    }
-                    Option::None => {
-                        self.environment.shift(expression);
-                        self.visit(*body);
-                        self.environment.unshift();
-                    }
-                }
+            Expression::Constant { constant: _ } => {
+                // Literals are not variables, so they impose no constraints on binders
             }
-            Expression::NamelessAbstraction { body } => {
-                let parameter_identifier = self.identifiers.new_identifier();
-                let binder = Binder::new(Option::None, parameter_identifier);
-                self.binders.set(expression, binder);
-                self.environment.shift(expression);
-                self.visit(*body);
-                self.environment.unshift();
+            _ => push_children(steps, self.expressions, expression),
+        }
+    }
+
+    /// Registers the binder `binder_id` for `parameter`, and brings it into
+    /// scope.
+    fn bind_parameter(&mut self, binder_id: BinderId, parameter: Option<StringId>) {
+        let parameter_identifier = self.identifiers.new_identifier();
+        let binder = Binder::new(parameter, parameter_identifier);
+        self.binders.set(binder_id, binder);
+        match parameter {
+            Option::Some(parameter) => {
+                self.environment
+                    .bind(parameter, parameter_identifier, binder_id);
             }
-            Expression::Application {
-                function,
-                arguments,
-            } => {
-                self.visit(*function);
-                for argument in arguments {
-                    self.visit(*argument);
+            Option::None => {
+                self.environment.shift(binder_id);
+            }
+        }
+    }
+
+    #[inline]
+    fn unbind_parameter(&mut self, parameter: Option<StringId>) {
+        match parameter {
+            Option::Some(parameter) => self.environment.unbind(parameter),
+            Option::None => self.environment.unshift(),
+        }
+    }
+
+    /// Registers the binders introduced by `expression` for `parameters`,
+    /// starting at binder `first_index`, and brings them into scope in order.
+    /// The binders are restricted from sharing names with each other.
+    fn bind_parameters(
+        &mut self,
+        expression: ExpressionId,
+        first_index: usize,
+        parameters: &[Option<StringId>],
+    ) {
+        let binder_ids: Vec<BinderId> = (first_index..first_index + parameters.len())
+            .map(|index| BinderId::new(expression, index))
+            .collect();
+        for (&binder_id, &parameter) in binder_ids.iter().zip(parameters) {
+            self.bind_parameter(binder_id, parameter);
+        }
+        if binder_ids.len() < 2 {
+            return;
+        }
+        let parameter_identifiers: Vec<IdentifierId> = binder_ids
+            .iter()
+            .map(|&binder_id| self.binders.get(binder_id).unwrap().destination_parameter)
+            .collect();
+        for &binder_id in &binder_ids {
+            let binder = self.binders.get_mut(binder_id).unwrap();
+            for &identifier in &parameter_identifiers {
+                if identifier != binder.destination_parameter {
+                    binder.add_identifier_restriction(identifier);
                 }
             }
         }
     }
 
+    fn unbind_parameters(&mut self, parameters: &[Option<StringId>]) {
+        for &parameter in parameters.iter().rev() {
+            self.unbind_parameter(parameter);
+        }
+    }
+
     #[inline]
     fn build(mut self, expression: ExpressionId) -> BinderStore {
         self.visit(expression);
@@ -457,7 +606,40 @@ impl<'a, G: AdmissibleVariableNameGenerator> NameGeneration<'a, G> {
     }
 
     fn convert_to_named(&mut self, expression: ExpressionId) -> ExpressionId {
-        match &self.source[expression] {
+        let source = self.source;
+        let mut steps = vec![Step::Visit(expression)];
+        let mut results = Vec::new();
+        while let Option::Some(step) = steps.pop() {
+            match step {
+                Step::Visit(expression) => match self.convert_leaf_to_named(expression) {
+                    Option::Some(named_expression) => results.push(named_expression),
+                    Option::None => {
+                        steps.push(Step::Build(expression));
+                        push_children(&mut steps, source, expression);
+                    }
+                },
+                Step::Bind { expression, child } => {
+                    let node = &source[expression];
+                    let parameters = node.child_binders(child);
+                    self.bind_parameters(expression, first_binder(node, child), &parameters);
+                }
+                Step::Unbind { expression, child } => {
+                    self.unbind_parameters(&source[expression].child_binders(child));
+                }
+                Step::Build(expression) => {
+                    let count = source[expression].children().len();
+                    let named_children = results.split_off(results.len() - count);
+                    let named_expression = self.build_named(expression, named_children);
+                    results.push(named_expression);
+                }
+            }
+        }
+        results.pop().unwrap()
+    }
+
+    /// Converts `expression` if it has no sub-expressions.
+    fn convert_leaf_to_named(&mut self, expression: ExpressionId) -> Option<ExpressionId> {
+        let named_expression = match &self.source[expression] {
             Expression::Variable { identifier: name } => match self.environment.lookup(*name) {
                 Option::Some(identifier) => {
                     // `expression` is a bound variable that may have been renamed
@@ -471,104 +653,224 @@ impl<'a, G: AdmissibleVariableNameGenerator> NameGeneration<'a, G> {
             },
             Expression::NamelessVariable { index } => {
                 // `expression` is a bound nameless variable
-                let binder_expression = self.environment.lookup_binder(*index);
-                let binder = self.binders.get(binder_expression).unwrap();
+                let binder_id = self.environment.lookup_binder(*index);
+                let binder = self.binders.get(binder_id).unwrap();
                 let name = self
                     .identifiers
                     .lookup(binder.destination_parameter)
                     .unwrap();
                 self.destination.variable(name)
             }
+            Expression::Constant { constant } => self.destination.constant(*constant),
+            _ => return Option::None,
+        };
+        Option::Some(named_expression)
+    }
+
+    /// The parameter name chosen for the binder `binder_id`, if it needs one.
+    fn chosen_parameter(&self, binder_id: BinderId) -> Option<StringId> {
+        let binder = self.binders.get(binder_id).unwrap();
+        self.identifiers.lookup(binder.destination_parameter)
+    }
+
+    /// Constructs the named expression for `expression` from its converted
+    /// sub-expressions, once the names of its binders have been chosen.
+    fn build_named(
+        &mut self,
+        expression: ExpressionId,
+        mut named_children: Vec<ExpressionId>,
+    ) -> ExpressionId {
+        match &self.source[expression] {
+            Expression::Variable { identifier: _ }
+            | Expression::NamelessVariable { index: _ }
+            | Expression::Constant { constant: _ } => unreachable!(),
             Expression::Abstraction {
-                parameter: source_parameter,
-                body,
-            } => {
-                let binder = self.binders.get(expression).unwrap();
-                let chosen_parameter = if let Option::Some(name) = source_parameter {
-                    // A parameter name already exists for `expression`
-                    let restrictions = self.evaluate_constraint_set(&binder.restrictions);
-                    if restrictions.contains(name) {
-                        // `initial_parameter` has to be renamed
-                        let undesirables = self.evaluate_constraint_set(&binder.undesirables);
-                        let new_name = self
-                            .variable_name_generator
-                            .generate_admissible_name(self.strings, |name| {
-                                !restrictions.contains(&name) && !undesirables.contains(&name)
-                            });
-                        self.identifiers.set(binder.destination_parameter, new_name);
-                        Option::Some(new_name)
-                    } else {
-                        // `initial_parameter` can be used as is
-                        self.identifiers.set(binder.destination_parameter, *name);
-                        Option::Some(*name)
-                    }
-                } else if binder.is_used() {
-                    // The parameter for `expression` is used in `body`
-                    let restrictions = self.evaluate_constraint_set(&binder.restrictions);
-                    let undesirables = self.evaluate_constraint_set(&binder.undesirables);
-                    let name = self
-                        .variable_name_generator
-                        .generate_admissible_name(self.strings, |name| {
-                            !restrictions.contains(&name) && !undesirables.contains(&name)
-                        });
-                    self.identifiers.set(binder.destination_parameter, name);
-                    Option::Some(name)
-                } else {
-                    // The parameter for `expression` is never used in `body`
-                    Option::None
-                };
-                match source_parameter {
-                    Option::Some(name) => {
-                        self.environment
-                            .bind(*name, binder.destination_parameter, expression);
-                        let named_body = self.convert_to_named(*body);
-                        self.environment.unbind(*name);
-                        self.destination.abstraction(chosen_parameter, named_body)
-                    }
-                    Option::None => {
-                        self.environment.shift(expression);
-                        let named_body = self.convert_to_named(*body);
-                        self.environment.unshift();
-                        self.destination.abstraction(chosen_parameter, named_body)
-                    }
-                }
+                parameter: _,
+                annotation,
+                body: _,
             }
-            Expression::NamelessAbstraction { body } => {
-                let binder = self.binders.get(expression).unwrap();
-                let parameter = if binder.is_used() {
-                    // The parameter for `expression` is used in `body`
-                    let restrictions = self.evaluate_constraint_set(&binder.restrictions);
-                    let undesirables = self.evaluate_constraint_set(&binder.undesirables);
-                    let name = self
-                        .variable_name_generator
-                        .generate_admissible_name(self.strings, |name| {
-                            !restrictions.contains(&name) && !undesirables.contains(&name)
-                        });
-                    self.identifiers.set(binder.destination_parameter, name);
-                    Option::Some(name)
-                } else {
-                    // The parameter for `expression` is never used in `body`
-                    Option::None
-                };
-                self.environment.shift(expression);
-                let named_body = self.convert_to_named(*body);
-                self.environment.unshift();
-                self.destination.abstraction(parameter, named_body)
+            | Expression::NamelessAbstraction {
+                annotation,
+                body: _,
+            } => {
+                let named_body = named_children.pop().unwrap();
+                let named_annotation = annotation.map(|_| named_children[0]);
+                let chosen_parameter = self.chosen_parameter(BinderId::single(expression));
+                self.destination.annotated_abstraction(
+                    chosen_parameter,
+                    named_annotation,
+                    named_body,
+                )
             }
             Expression::Application {
-                function,
-                arguments,
+                function: _,
+                arguments: _,
+            } => {
+                let named_arguments = named_children.split_off(1);
+                self.destination
+                    .application(named_children[0], named_arguments)
+            }
+            Expression::Let {
+                parameter: _,
+                definition: _,
+                body: _,
+            }
+            | Expression::NamelessLet {
+                definition: _,
+                body: _,
+            } => {
+                let chosen_parameter = self.chosen_parameter(BinderId::single(expression));
+                self.destination
+                    .let_binding(chosen_parameter, named_children[0], named_children[1])
+            }
+            Expression::Pi {
+                parameter: _,
+                domain: _,
+                codomain: _,
+            }
+            | Expression::NamelessPi {
+                domain: _,
+                codomain: _,
+            } => {
+                let chosen_parameter = self.chosen_parameter(BinderId::single(expression));
+                self.destination
+                    .pi(chosen_parameter, named_children[0], named_children[1])
+            }
+            Expression::Case {
+                scrutinee: _,
+                branches,
             } => {
-                let named_function = self.convert_to_named(*function);
-                let mut named_arguments = Vec::with_capacity(arguments.len());
-                for &argument in arguments {
-                    let named_argument = self.convert_to_named(argument);
-                    named_arguments.push(named_argument);
+                let mut named_branches = Vec::with_capacity(branches.len());
+                let mut index = 0;
+                for (branch, &named_body) in branches.iter().zip(&named_children[1..]) {
+                    let chosen_parameters = (index..index + branch.arity())
+                        .map(|index| self.chosen_parameter(BinderId::new(expression, index)))
+                        .collect();
+                    index += branch.arity();
+                    named_branches.push(CaseBranch::Named {
+                        constructor: branch.constructor(),
+                        parameters: chosen_parameters,
+                        body: named_body,
+                    });
                 }
+                self.destination.case(named_children[0], named_branches)
+            }
+            Expression::LetRec {
+                parameters: _,
+                definitions: _,
+                body: _,
+            }
+            | Expression::NamelessLetRec {
+                definitions: _,
+                body: _,
+            } => {
+                let count = self.source[expression].child_binders(0).len();
+                let chosen_parameters = (0..count)
+                    .map(|index| self.chosen_parameter(BinderId::new(expression, index)))
+                    .collect();
+                let named_body = named_children.pop().unwrap();
                 self.destination
-                    .application(named_function, named_arguments)
+                    .let_rec(chosen_parameters, named_children, named_body)
+            }
+        }
+    }
+
+    /// Chooses the parameter name for the binder `binder_id`, where
+    /// `source_parameter` is the binder's initial parameter name.
+    fn generate_parameter(
+        &mut self,
+        binder_id: BinderId,
+        source_parameter: Option<StringId>,
+    ) -> Option<StringId> {
+        let binder = self.binders.get(binder_id).unwrap();
+        if let Option::Some(name) = source_parameter {
+            // A parameter name already exists for `expression`
+            let restrictions = self.evaluate_constraint_set(&binder.restrictions);
+            if restrictions.contains(&name) {
+                // `initial_parameter` has to be renamed
+                let undesirables = self.evaluate_constraint_set(&binder.undesirables);
+                let new_name = self
+                    .variable_name_generator
+                    .generate_admissible_name(self.strings, |name| {
+                        !restrictions.contains(&name) && !undesirables.contains(&name)
+                    });
+                self.identifiers.set(binder.destination_parameter, new_name);
+                Option::Some(new_name)
+            } else {
+                // `initial_parameter` can be used as is
+                self.identifiers.set(binder.destination_parameter, name);
+                Option::Some(name)
+            }
+        } else if binder.is_used() {
+            // The parameter for `expression` is used in its scope
+            let restrictions = self.evaluate_constraint_set(&binder.restrictions);
+            let undesirables = self.evaluate_constraint_set(&binder.undesirables);
+            let name = self
+                .variable_name_generator
+                .generate_admissible_name(self.strings, |name| {
+                    !restrictions.contains(&name) && !undesirables.contains(&name)
+                });
+            self.identifiers.set(binder.destination_parameter, name);
+            Option::Some(name)
+        } else {
+            // The parameter for `expression` is never used in its scope
+            Option::None
+        }
+    }
+
+    /// Chooses the parameter name for the binder `binder_id`, and brings that
+    /// binder into scope.
+    fn bind_parameter(
+        &mut self,
+        binder_id: BinderId,
+        source_parameter: Option<StringId>,
+    ) -> Option<StringId> {
+        let chosen_parameter = self.generate_parameter(binder_id, source_parameter);
+        let destination_parameter = self.binders.get(binder_id).unwrap().destination_parameter;
+        match source_parameter {
+            Option::Some(name) => {
+                self.environment
+                    .bind(name, destination_parameter, binder_id);
+            }
+            Option::None => {
+                self.environment.shift(binder_id);
             }
         }
+        chosen_parameter
+    }
+
+    #[inline]
+    fn unbind_parameter(&mut self, source_parameter: Option<StringId>) {
+        match source_parameter {
+            Option::Some(name) => self.environment.unbind(name),
+            Option::None => self.environment.unshift(),
+        }
+    }
+
+    /// Chooses the parameter names for the binders introduced by
+    /// `expression`, starting at binder `first_index`, and brings them into
+    /// scope in order.
+    fn bind_parameters(
+        &mut self,
+        expression: ExpressionId,
+        first_index: usize,
+        source_parameters: &[Option<StringId>],
+    ) -> Vec<Option<StringId>> {
+        let mut chosen_parameters = Vec::with_capacity(source_parameters.len());
+        for (offset, &source_parameter) in source_parameters.iter().enumerate() {
+            let chosen_parameter = self.bind_parameter(
+                BinderId::new(expression, first_index + offset),
+                source_parameter,
+            );
+            chosen_parameters.push(chosen_parameter);
+        }
+        chosen_parameters
+    }
+
+    fn unbind_parameters(&mut self, source_parameters: &[Option<StringId>]) {
+        for &source_parameter in source_parameters.iter().rev() {
+            self.unbind_parameter(source_parameter);
+        }
     }
 
     #[inline]
@@ -612,6 +914,7 @@ mod tests {
             variable_name_generator,
         );
         assert!(Expression::is_named(&named_expressions, named_expression));
+        assert_binders_named_apart(&named_expressions);
 
         assert!(Expression::alpha_equivalent(
             (
@@ -659,6 +962,123 @@ mod tests {
         test_alpha_equivalence_of_named_mixed_expression(
             "λx. λx. 1 2 (λx. 1 2 3 (λx. 1 2 3 4 (λx. 1 2 3 4 5)))",
         );
+        test_alpha_equivalence_of_named_mixed_expression("let = x in 1");
+        test_alpha_equivalence_of_named_mixed_expression("let = x in x");
+        test_alpha_equivalence_of_named_mixed_expression("λx. let = x in x 1");
+        test_alpha_equivalence_of_named_mixed_expression("λ. let x = 1 in x 2");
+        test_alpha_equivalence_of_named_mixed_expression("let x = y in let = x in x 1");
+        test_alpha_equivalence_of_named_mixed_expression("λx. let x = x in λ. x 1 3");
+        test_alpha_equivalence_of_named_mixed_expression("let _ = λ. 1 in let = 1 in 1");
+        test_alpha_equivalence_of_named_mixed_expression("λ : T. 1");
+        test_alpha_equivalence_of_named_mixed_expression("λx. λ : x. 1");
+        test_alpha_equivalence_of_named_mixed_expression("λ. λx : 1. x 2");
+        test_alpha_equivalence_of_named_mixed_expression("λx. λ. λ : x 1. 1 2 x");
+        test_alpha_equivalence_of_named_mixed_expression("Π(: A). 1");
+        test_alpha_equivalence_of_named_mixed_expression("Π(: Type). Π(: 1). Π(: 2). 3");
+        test_alpha_equivalence_of_named_mixed_expression("Π(x : Type). Π(: x). 1 → x");
+        test_alpha_equivalence_of_named_mixed_expression("case p of { pair/2 => 2 1 }");
+        test_alpha_equivalence_of_named_mixed_expression("letrec = 2 1 and = 1 2 in 1 2");
+        test_alpha_equivalence_of_named_mixed_expression("letrec = x and = y in λx. 3 x 1");
+        test_alpha_equivalence_of_named_mixed_expression("λx. letrec f = 2 f x and g = f in g");
+        test_alpha_equivalence_of_named_mixed_expression("letrec f = 1 and f = 2 in λ. f 3");
+        test_alpha_equivalence_of_named_mixed_expression("case p of { pair/2 => 1 | nil => x }");
+        test_alpha_equivalence_of_named_mixed_expression("λx. case x of { pair/2 => 3 2 1 }");
+        test_alpha_equivalence_of_named_mixed_expression("case p of { pair a b => λ. a b 1 }");
+        test_alpha_equivalence_of_named_mixed_expression("λx. case p of { pair/2 => x 1 2 }");
+        test_alpha_equivalence_of_named_mixed_expression("case p of { pair a a => λ. a 2 3 }");
+        test_alpha_equivalence_of_named_mixed_expression("case p of { c x _ => 1 }");
+        test_alpha_equivalence_of_named_mixed_expression("λa. case a of { c/2 => 1 }");
+        test_alpha_equivalence_of_named_mixed_expression("letrec x = 1 and _ = 1 in 1");
+    }
+
+    fn check_convert_to_named(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut source_expressions = ExpressionArena::new();
+        let mut named_expressions = ExpressionArena::new();
+        let variable_name_generator = VariableNameGenerator::new();
+
+        let expression = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut source_expressions,
+            input.as_bytes(),
+        )
+        .unwrap();
+        let expected_expression = Expression::parse_expression(
+            &mut strings,
+            &mut source_expressions,
+            expected.as_bytes(),
+        )
+        .unwrap();
+        let named_expression = Expression::convert_to_named(
+            &mut strings,
+            &source_expressions,
+            expression,
+            &mut named_expressions,
+            variable_name_generator,
+        );
+
+        assert!(Expression::equals(
+            (&named_expressions, named_expression),
+            (&source_expressions, expected_expression)
+        ));
+    }
+
+    #[test]
+    fn convert_to_named_generates_expected_names() {
+        check_convert_to_named("λ. λ. 2", "λx. λ_. x");
+        check_convert_to_named("λx. λ. x 1", "λx. λy. x y");
+        check_convert_to_named("λx. λx : x. x", "λx. λx : x. x");
+        check_convert_to_named("λ. λ : 1. 2", "λx. λ_ : x. x");
+        check_convert_to_named("λx. λ. λ : x 1. 1 2", "λx. λy. λx : x y. x y");
+        check_convert_to_named("Π(: A). B", "A → B");
+        check_convert_to_named("Π(: Type). Π(: 1). 2", "Π(x : Type). x → x");
+        check_convert_to_named("Π(: Type). Π(: 1). Π(: 2). 3", "Π(x : Type). x → x → x");
+        check_convert_to_named(
+            "Π(x : Type). Π(: x). Π(: x). 2",
+            "Π(x : Type). Π(y : x). x → y",
+        );
+        check_convert_to_named("case p of { pair/2 => 2 }", "case p of { pair x _ => x }");
+        check_convert_to_named("letrec = 1 in 1", "letrec x = x in x");
+        check_convert_to_named("letrec = 2 and = 1 in 2", "letrec x = x and y = y in x");
+        check_convert_to_named("letrec = x and = 2 in 1", "letrec y = x and z = y in z");
+        check_convert_to_named("letrec f = 1 and g = 2 in f", "letrec f = g and g = f in f");
+        check_convert_to_named("letrec f = f and f = 1 in 2", "letrec f = x and x = x in f");
+        check_convert_to_named("λ. λ. 2 \"x\" #1", "λx. λ_. x \"x\" #1");
+        check_convert_to_named("λ. 1 \"x\" true", "λx. x \"x\" true");
+        check_convert_to_named(
+            "case p of { pair/2 => 2 1 }",
+            "case p of { pair x y => x y }",
+        );
+        check_convert_to_named(
+            "λx. case x of { pair/2 => 3 2 1 }",
+            "λx. case x of { pair y z => x y z }",
+        );
+        check_convert_to_named(
+            "case p of { pair a a => 1 2 }",
+            "case p of { pair a x => x a }",
+        );
+        check_convert_to_named("case p of { c x _ => 1 }", "case p of { c x y => y }");
+        check_convert_to_named("λ. case 1 of { c/2 => 1 }", "λx. case x of { c _ x => x }");
+        check_convert_to_named("letrec x = 1 and _ = 1 in 1", "letrec x = y and y = y in y");
+        check_convert_to_named("letrec = 1 and = 1 in 1", "letrec _ = x and x = x in x");
+    }
+
+    /// Checks that the binders introduced together by every expression of
+    /// `expressions` have distinct names.
+    fn assert_binders_named_apart(expressions: &ExpressionArena) {
+        for index in 0..expressions.len() {
+            let expression = &expressions[ExpressionId::new(index)];
+            for child in 0..expression.children().len() {
+                let names: Vec<StringId> = expression
+                    .child_binders(child)
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect();
+                let distinct_names: HashSet<StringId> = names.iter().copied().collect();
+                assert_eq!(names.len(), distinct_names.len());
+            }
+        }
     }
 
     fn roundtrip_test(input: &str) {
@@ -688,6 +1108,7 @@ mod tests {
             variable_name_generator,
         );
         assert!(Expression::is_named(&named_expressions, named_expression));
+        assert_binders_named_apart(&named_expressions);
 
         assert!(Expression::alpha_equivalent(
             (
@@ -716,6 +1137,15 @@ mod tests {
         roundtrip_test("λf. λx. f x y");
         roundtrip_test("λx. λy. f x y");
         roundtrip_test("f x y");
+        roundtrip_test("let x = y in x");
+        roundtrip_test("let y = x in let x = f in x y");
+        roundtrip_test("λx. let f = λy. x y in f (let x = x in x)");
+        roundtrip_test("λp. case p of { pair a b => a b | nil => p }");
+        roundtrip_test("λx. case x of { pair x y => λx. x y }");
+        roundtrip_test("letrec even = λn. odd n and odd = λn. even n in even");
+        roundtrip_test("λx. letrec x = x and y = x in λy. x y");
+        roundtrip_test("case p of { c x _ => x }");
+        roundtrip_test("letrec x = x and _ = x in x");
     }
 
     fn fuzz_test<R: Rng>(rng: &mut R, max_depth: usize) {
@@ -742,6 +1172,7 @@ mod tests {
             variable_name_generator,
         );
         assert!(Expression::is_named(&named_expressions, named_expression));
+        assert_binders_named_apart(&named_expressions);
 
         assert!(Expression::alpha_equivalent(
             (referencing_environment.clone(), &expressions, expression),
@@ -762,4 +1193,43 @@ mod tests {
             fuzz_test(&mut rng, max_depth);
         }
     }
+
+    #[test]
+    fn conversion_handles_deep_expressions() {
+        let mut strings = StringArena::new();
+        let mut nameless_expressions = ExpressionArena::new();
+        let mut named_expressions = ExpressionArena::new();
+        let referencing_environment = Rc::new(ReferencingEnvironment::default());
+
+        // λ. λ. 2 (2 (… (2 1)))
+        let count = 1_000_000;
+        let mut body = nameless_expressions.nameless_variable(DeBruijnIndex::new(1));
+        for _ in 0..count {
+            let function = nameless_expressions.nameless_variable(DeBruijnIndex::new(2));
+            body = nameless_expressions.application(function, vec![body]);
+        }
+        let abstraction = nameless_expressions.nameless_abstraction(body);
+        let nameless_expression = nameless_expressions.nameless_abstraction(abstraction);
+
+        let named_expression = Expression::convert_to_named(
+            &mut strings,
+            &nameless_expressions,
+            nameless_expression,
+            &mut named_expressions,
+            VariableNameGenerator::new(),
+        );
+        assert!(Expression::is_named(&named_expressions, named_expression));
+        assert!(Expression::alpha_equivalent(
+            (
+                referencing_environment.clone(),
+                &nameless_expressions,
+                nameless_expression
+            ),
+            (
+                referencing_environment.clone(),
+                &named_expressions,
+                named_expression
+            )
+        ));
+    }
 }
@@ -0,0 +1,615 @@
+use std::collections::{HashMap, HashSet};
+
+use pretty::{Arena, DocAllocator};
+
+use crate::{
+    admissible_variable_name_generator::{AdmissibleVariableNameGenerator, VariableNameGenerator},
+    expression::{CaseBranch, Expression, ExpressionArena, ExpressionId},
+    pretty_print::{self, marked_expression, PrettyPrintError, Syntax},
+    reduction::{Reducer, Reduction, Strategy},
+    referencing_environment::ReferencingEnvironment,
+    strings::{StringArena, StringId},
+    visitor::{Fold, Scoping, Visitor},
+};
+
+/// A step of a reduction trace, contracting a redex of an expression.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TraceStep {
+    expression: ExpressionId,
+    redex_path: Vec<ExpressionId>,
+}
+
+impl TraceStep {
+    /// The expression before the step.
+    pub fn expression(&self) -> ExpressionId {
+        self.expression
+    }
+
+    /// The sub-expressions from [the expression](TraceStep::expression) down
+    /// to the contracted redex, each being a child of the previous one.
+    pub fn redex_path(&self) -> &[ExpressionId] {
+        &self.redex_path
+    }
+
+    /// The contracted redex.
+    pub fn redex(&self) -> ExpressionId {
+        *self.redex_path.last().unwrap()
+    }
+}
+
+/// The steps taken by a reduction, and its outcome.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Trace {
+    steps: Vec<TraceStep>,
+    reduction: Reduction,
+}
+
+impl Trace {
+    /// The steps taken, from the first to the last.
+    pub fn steps(&self) -> &[TraceStep] {
+        &self.steps
+    }
+
+    /// The outcome of the reduction.
+    pub fn reduction(&self) -> Reduction {
+        self.reduction
+    }
+
+    /// Converts the expressions of the trace to named representation in
+    /// `destination`, one per step followed by the expression reached by the
+    /// reduction, each with its contracted redex if any. Binders keep the
+    /// parameters they had before reduction unless that would capture a free
+    /// variable, so that names are stable across steps.
+    pub fn convert_to_named<G: AdmissibleVariableNameGenerator + Clone>(
+        &self,
+        strings: &mut StringArena,
+        expressions: &ExpressionArena,
+        destination: &mut ExpressionArena,
+        variable_name_generator: G,
+    ) -> Vec<(ExpressionId, Option<ExpressionId>)> {
+        let mut named_steps = Vec::with_capacity(self.steps.len() + 1);
+        for step in &self.steps {
+            let positions = child_positions(expressions, step.redex_path());
+            let named_expression = convert_to_named(
+                strings,
+                expressions,
+                step.expression,
+                destination,
+                variable_name_generator.clone(),
+            );
+            let mut named_redex = named_expression;
+            for child in positions {
+                named_redex = destination[named_redex].children()[child];
+            }
+            named_steps.push((named_expression, Option::Some(named_redex)));
+        }
+        let named_expression = convert_to_named(
+            strings,
+            expressions,
+            self.reduction.expression(),
+            destination,
+            variable_name_generator,
+        );
+        named_steps.push((named_expression, Option::None));
+        named_steps
+    }
+
+    pub fn to_string(
+        &self,
+        strings: &mut StringArena,
+        expressions: &ExpressionArena,
+        width: usize,
+    ) -> Result<String, PrettyPrintError> {
+        self.to_string_with_syntax(strings, expressions, Syntax::Unicode, width)
+    }
+
+    /// Prints the expressions of the trace in named representation one after
+    /// the other, like `⟦(λx. x) y⟧` then `⟶ y`, with the contracted redexes
+    /// between marks.
+    pub fn to_string_with_syntax(
+        &self,
+        strings: &mut StringArena,
+        expressions: &ExpressionArena,
+        syntax: Syntax,
+        width: usize,
+    ) -> Result<String, PrettyPrintError> {
+        let mut named_expressions = ExpressionArena::new();
+        let named_steps = self.convert_to_named(
+            strings,
+            expressions,
+            &mut named_expressions,
+            VariableNameGenerator::new(),
+        );
+        let arrow = match syntax {
+            Syntax::Unicode => "⟶ ",
+            Syntax::Ascii => "--> ",
+        };
+        let arena = Arena::new();
+        let mut step_docs = Vec::with_capacity(named_steps.len());
+        for (i, &(expression, redex)) in named_steps.iter().enumerate() {
+            let expression_doc = match redex {
+                Option::Some(redex) => marked_expression(
+                    &arena,
+                    strings,
+                    &named_expressions,
+                    syntax,
+                    expression,
+                    redex,
+                )?,
+                Option::None => pretty_print::expression(
+                    &arena,
+                    strings,
+                    &named_expressions,
+                    syntax,
+                    expression,
+                )?,
+            };
+            step_docs.push(if i == 0 {
+                expression_doc
+            } else {
+                arena.text(arrow).append(expression_doc).nest(2)
+            });
+        }
+        let mut buffer = Vec::default();
+        arena
+            .intersperse(step_docs, arena.hardline())
+            .render(width, &mut buffer)?;
+        let rendered = String::from_utf8(buffer)?;
+        Result::Ok(rendered)
+    }
+}
+
+impl Expression {
+    /// Reduces `expression` like [Expression::reduce], and records every step
+    /// taken. The expressions of the trace are constructed in `destination`,
+    /// where their binders keep their parameters and their bound variables are
+    /// nameless. `expression` must not have nameless variables referring
+    /// outside of it.
+    pub fn trace(
+        strategy: Strategy,
+        expressions: &ExpressionArena,
+        expression: ExpressionId,
+        destination: &mut ExpressionArena,
+        fuel: usize,
+    ) -> Trace {
+        let mut expression = Hinting::new(expressions, destination).convert(expression);
+        let mut reducer = Reducer::new(strategy, destination);
+        let mut steps = Vec::new();
+        while steps.len() < fuel {
+            match reducer.step_with_redex(expression) {
+                Option::Some((redex_path, reduct)) => {
+                    steps.push(TraceStep {
+                        expression,
+                        redex_path,
+                    });
+                    expression = reduct;
+                }
+                Option::None => {
+                    let reduction = Reduction::NormalForm {
+                        expression,
+                        steps: steps.len(),
+                    };
+                    return Trace { steps, reduction };
+                }
+            }
+        }
+        let reduction = if reducer.has_redex(expression) {
+            Reduction::OutOfFuel {
+                expression,
+                steps: fuel,
+            }
+        } else {
+            Reduction::NormalForm {
+                expression,
+                steps: fuel,
+            }
+        };
+        Trace { steps, reduction }
+    }
+}
+
+/// The positions of the sub-expressions of `path` in their parents.
+fn child_positions(expressions: &ExpressionArena, path: &[ExpressionId]) -> Vec<usize> {
+    path.windows(2)
+        .map(|pair| {
+            let children = expressions[pair[0]].children();
+            children.iter().position(|&child| child == pair[1]).unwrap()
+        })
+        .collect()
+}
+
+/// Converts the traced `expression` to named representation, keeping the
+/// parameters of binders that capture no free variable.
+fn convert_to_named<G: AdmissibleVariableNameGenerator>(
+    strings: &mut StringArena,
+    expressions: &ExpressionArena,
+    expression: ExpressionId,
+    destination: &mut ExpressionArena,
+    variable_name_generator: G,
+) -> ExpressionId {
+    let captures = Captures::new(expressions).find(expression);
+    let mut uncaptured_expressions = ExpressionArena::new();
+    let uncaptured =
+        Uncapturing::new(expressions, &mut uncaptured_expressions, captures).convert(expression);
+    Expression::convert_to_named(
+        strings,
+        &uncaptured_expressions,
+        uncaptured,
+        destination,
+        variable_name_generator,
+    )
+}
+
+/// Copies an expression to the destination, replacing its bound variables
+/// with nameless variables while keeping the parameters of its binders.
+struct Hinting<'a> {
+    expressions: &'a ExpressionArena,
+    destination: &'a mut ExpressionArena,
+    environment: ReferencingEnvironment,
+}
+
+impl<'a> Hinting<'a> {
+    fn new(expressions: &'a ExpressionArena, destination: &'a mut ExpressionArena) -> Hinting<'a> {
+        Hinting {
+            expressions,
+            destination,
+            environment: ReferencingEnvironment::new(),
+        }
+    }
+
+    fn convert(mut self, expression: ExpressionId) -> ExpressionId {
+        self.fold_expression(expression)
+    }
+}
+
+impl Scoping for Hinting<'_> {
+    fn environment(&mut self) -> Option<&mut ReferencingEnvironment> {
+        Option::Some(&mut self.environment)
+    }
+}
+
+impl<'a> Fold<'a> for Hinting<'a> {
+    type Output = ExpressionId;
+
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn combine(&mut self, expression: ExpressionId, children: Vec<ExpressionId>) -> ExpressionId {
+        match self.expressions[expression] {
+            Expression::Variable { identifier } => {
+                match self.environment.lookup_index(identifier) {
+                    Option::Some(index) => self.destination.nameless_variable(index),
+                    Option::None => self.destination.variable(identifier),
+                }
+            }
+            ref node => self.destination.add(node.with_children(&children)),
+        }
+    }
+}
+
+/// Finds the parameters of binders that would capture free variables if
+/// their bound variables were named after them. Since the bound variables of
+/// traced expressions are nameless, every variable is free.
+struct Captures<'a> {
+    expressions: &'a ExpressionArena,
+    /// The expressions being visited, from the outermost to the innermost.
+    ancestors: Vec<ExpressionId>,
+    /// The expressions introducing the binders in scope per parameter, from
+    /// the outermost to the innermost.
+    binders: HashMap<StringId, Vec<ExpressionId>>,
+    captures: HashSet<(ExpressionId, StringId)>,
+}
+
+impl<'a> Captures<'a> {
+    fn new(expressions: &'a ExpressionArena) -> Captures<'a> {
+        Captures {
+            expressions,
+            ancestors: Vec::new(),
+            binders: HashMap::new(),
+            captures: HashSet::new(),
+        }
+    }
+
+    fn find(mut self, expression: ExpressionId) -> HashSet<(ExpressionId, StringId)> {
+        self.visit_expression(expression);
+        self.captures
+    }
+}
+
+impl Scoping for Captures<'_> {
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        let binder = *self.ancestors.last().unwrap();
+        for &parameter in parameters.iter().flatten() {
+            self.binders.entry(parameter).or_default().push(binder);
+        }
+    }
+
+    fn exit_binders(&mut self, parameters: &[Option<StringId>]) {
+        for &parameter in parameters.iter().flatten() {
+            self.binders.get_mut(&parameter).unwrap().pop();
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Captures<'a> {
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn enter_expression(
+        &mut self,
+        _parent: Option<ExpressionId>,
+        expression: ExpressionId,
+    ) -> bool {
+        self.ancestors.push(expression);
+        true
+    }
+
+    fn exit_expression(&mut self, _expression: ExpressionId) {
+        self.ancestors.pop();
+    }
+
+    fn visit_variable(&mut self, _expression: ExpressionId, identifier: StringId) {
+        // Every enclosing binder with that parameter would capture the
+        // variable, and the outer ones are already known to if an inner one is
+        let binders = self.binders.get(&identifier).map_or(&[][..], Vec::as_slice);
+        for &binder in binders.iter().rev() {
+            if !self.captures.insert((binder, identifier)) {
+                break;
+            }
+        }
+    }
+}
+
+/// Copies an expression to the destination, omitting the parameters of
+/// binders that capture free variables.
+struct Uncapturing<'a> {
+    expressions: &'a ExpressionArena,
+    destination: &'a mut ExpressionArena,
+    captures: HashSet<(ExpressionId, StringId)>,
+}
+
+impl<'a> Uncapturing<'a> {
+    fn new(
+        expressions: &'a ExpressionArena,
+        destination: &'a mut ExpressionArena,
+        captures: HashSet<(ExpressionId, StringId)>,
+    ) -> Uncapturing<'a> {
+        Uncapturing {
+            expressions,
+            destination,
+            captures,
+        }
+    }
+
+    fn convert(mut self, expression: ExpressionId) -> ExpressionId {
+        self.fold_expression(expression)
+    }
+
+    /// The parameter of a binder introduced by `expression`, omitted if it
+    /// captures free variables.
+    fn parameter(&self, expression: ExpressionId, parameter: Option<StringId>) -> Option<StringId> {
+        parameter.filter(|&parameter| !self.captures.contains(&(expression, parameter)))
+    }
+
+    fn parameters(
+        &self,
+        expression: ExpressionId,
+        parameters: Vec<Option<StringId>>,
+    ) -> Vec<Option<StringId>> {
+        parameters
+            .into_iter()
+            .map(|parameter| self.parameter(expression, parameter))
+            .collect()
+    }
+}
+
+impl Scoping for Uncapturing<'_> {}
+
+impl<'a> Fold<'a> for Uncapturing<'a> {
+    type Output = ExpressionId;
+
+    fn expressions(&self) -> &'a ExpressionArena {
+        self.expressions
+    }
+
+    fn combine(&mut self, expression: ExpressionId, children: Vec<ExpressionId>) -> ExpressionId {
+        let node = match self.expressions[expression].with_children(&children) {
+            Expression::Abstraction {
+                parameter,
+                annotation,
+                body,
+            } => Expression::Abstraction {
+                parameter: self.parameter(expression, parameter),
+                annotation,
+                body,
+            },
+            Expression::Let {
+                parameter,
+                definition,
+                body,
+            } => Expression::Let {
+                parameter: self.parameter(expression, parameter),
+                definition,
+                body,
+            },
+            Expression::LetRec {
+                parameters,
+                definitions,
+                body,
+            } => Expression::LetRec {
+                parameters: self.parameters(expression, parameters),
+                definitions,
+                body,
+            },
+            Expression::Pi {
+                parameter,
+                domain,
+                codomain,
+            } => Expression::Pi {
+                parameter: self.parameter(expression, parameter),
+                domain,
+                codomain,
+            },
+            Expression::Case {
+                scrutinee,
+                branches,
+            } => Expression::Case {
+                scrutinee,
+                branches: branches
+                    .into_iter()
+                    .map(|branch| match branch {
+                        CaseBranch::Named {
+                            constructor,
+                            parameters,
+                            body,
+                        } => CaseBranch::Named {
+                            constructor,
+                            parameters: self.parameters(expression, parameters),
+                            body,
+                        },
+                        branch => branch,
+                    })
+                    .collect(),
+            },
+            node => node,
+        };
+        self.destination.add(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_trace(strategy: Strategy, input: &str, fuel: usize, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+
+        let expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+        let trace = Expression::trace(strategy, &expressions, expression, &mut destination, fuel);
+        let printed = trace.to_string(&mut strings, &destination, 80).unwrap();
+        assert_eq!(printed, expected);
+    }
+
+    #[test]
+    fn trace_marks_contracted_redexes() {
+        check_trace(
+            Strategy::NormalOrder,
+            "(λx y. x) a b",
+            10,
+            "⟦(λx y. x) a b⟧\n⟶ ⟦(λy. a) b⟧\n⟶ a",
+        );
+        check_trace(
+            Strategy::NormalOrder,
+            "λz. f ((λx. x) z)",
+            10,
+            "λz. f ⟦(λx. x) z⟧\n⟶ λz. f z",
+        );
+        check_trace(
+            Strategy::CallByValue,
+            "(λx. f x) ((λy. y) a)",
+            10,
+            "(λx. f x) ⟦(λy. y) a⟧\n⟶ ⟦(λx. f x) a⟧\n⟶ f a",
+        );
+        check_trace(
+            Strategy::NormalOrder,
+            "let x = a in f x x",
+            10,
+            "⟦let x = a in f x x⟧\n⟶ f a a",
+        );
+        check_trace(Strategy::NormalOrder, "λx. x", 10, "λx. x");
+    }
+
+    #[test]
+    fn trace_keeps_names_across_steps() {
+        check_trace(
+            Strategy::NormalOrder,
+            "(λf x. f (f x)) (λy. g y)",
+            10,
+            "⟦(λf x. f (f x)) (λy. g y)⟧\n\
+             ⟶ λx. ⟦(λy. g y) ((λy. g y) x)⟧\n\
+             ⟶ λx. g ⟦(λy. g y) x⟧\n\
+             ⟶ λx. g (g x)",
+        );
+        // Binders are only renamed to avoid capturing free variables
+        check_trace(
+            Strategy::NormalOrder,
+            "(λx y. x y) y",
+            10,
+            "⟦(λx y. x y) y⟧\n⟶ λx. y x",
+        );
+        check_trace(
+            Strategy::NormalOrder,
+            "(λx y y. x) y",
+            10,
+            "⟦(λx y y. x) y⟧\n⟶ λ_ _. y",
+        );
+    }
+
+    #[test]
+    fn trace_records_redex_paths() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+
+        let expression = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            "λz. f ((λx. x) z)".as_bytes(),
+        )
+        .unwrap();
+        let trace = Expression::trace(
+            Strategy::NormalOrder,
+            &expressions,
+            expression,
+            &mut destination,
+            10,
+        );
+        assert_eq!(trace.steps().len(), 1);
+        assert!(trace.reduction().is_normal_form());
+
+        let step = &trace.steps()[0];
+        let path = step.redex_path();
+        assert_eq!(path.len(), 3);
+        assert_eq!(path[0], step.expression());
+        assert_eq!(path[2], step.redex());
+        for pair in path.windows(2) {
+            assert!(destination[pair[0]].children().contains(&pair[1]));
+        }
+    }
+
+    #[test]
+    fn trace_stops_when_out_of_fuel() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let mut destination = ExpressionArena::new();
+
+        let expression = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            "(λx. x x) (λx. x x)".as_bytes(),
+        )
+        .unwrap();
+        let trace = Expression::trace(
+            Strategy::NormalOrder,
+            &expressions,
+            expression,
+            &mut destination,
+            3,
+        );
+        assert_eq!(trace.steps().len(), 3);
+        assert!(!trace.reduction().is_normal_form());
+        assert_eq!(
+            trace.to_string(&mut strings, &destination, 80).unwrap(),
+            "⟦(λx. x x) (λx. x x)⟧\n\
+             ⟶ ⟦(λx. x x) (λx. x x)⟧\n\
+             ⟶ ⟦(λx. x x) (λx. x x)⟧\n\
+             ⟶ (λx. x x) (λx. x x)"
+        );
+    }
+}
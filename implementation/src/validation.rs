@@ -0,0 +1,376 @@
+use crate::expression::{Expression, ExpressionArena, ExpressionId};
+
+/// Violations of the invariants of expression trees in an
+/// [expression arena](ExpressionArena).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ValidationError {
+    /// The expression `child` of `parent` is not in the arena. The root has no
+    /// parent.
+    DanglingExpression {
+        parent: Option<ExpressionId>,
+        child: ExpressionId,
+    },
+    /// The expression `child` of `parent` is one of its own ancestors.
+    Cycle {
+        parent: ExpressionId,
+        child: ExpressionId,
+    },
+    /// The expression `child` of `parent` was already reached through another
+    /// parent, so the expressions form a DAG rather than a tree.
+    SharedExpression {
+        parent: ExpressionId,
+        child: ExpressionId,
+    },
+    /// The application `expression` has no arguments.
+    EmptyApplication { expression: ExpressionId },
+    /// The recursive let-binding group `expression` has a different number of
+    /// parameters and definitions.
+    MismatchedLetRec {
+        expression: ExpressionId,
+        parameters: usize,
+        definitions: usize,
+    },
+    /// The nameless variable `expression` has an index that is zero, or that
+    /// exceeds the number of enclosing binders.
+    IndexOutOfRange {
+        expression: ExpressionId,
+        index: usize,
+        binders: usize,
+    },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::DanglingExpression {
+                parent: Option::Some(parent),
+                child,
+            } => write!(
+                f,
+                "expression {} has child {} outside the arena",
+                parent.into_usize(),
+                child.into_usize()
+            ),
+            ValidationError::DanglingExpression {
+                parent: Option::None,
+                child,
+            } => write!(
+                f,
+                "root expression {} is outside the arena",
+                child.into_usize()
+            ),
+            ValidationError::Cycle { parent, child } => write!(
+                f,
+                "expression {} has its ancestor {} as child",
+                parent.into_usize(),
+                child.into_usize()
+            ),
+            ValidationError::SharedExpression { parent, child } => write!(
+                f,
+                "expression {} has child {} that is shared with another parent",
+                parent.into_usize(),
+                child.into_usize()
+            ),
+            ValidationError::EmptyApplication { expression } => {
+                write!(
+                    f,
+                    "application {} has no arguments",
+                    expression.into_usize()
+                )
+            }
+            ValidationError::MismatchedLetRec {
+                expression,
+                parameters,
+                definitions,
+            } => write!(
+                f,
+                "recursive let-binding group {} has {} parameters but {} definitions",
+                expression.into_usize(),
+                parameters,
+                definitions
+            ),
+            ValidationError::IndexOutOfRange {
+                expression,
+                index,
+                binders,
+            } => write!(
+                f,
+                "nameless variable {} has index {} under {} binders",
+                expression.into_usize(),
+                index,
+                binders
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl Expression {
+    /// Checks that the expressions reachable from `root` in `expressions` form
+    /// a well-formed expression tree, reporting every violation found.
+    pub fn validate(expressions: &ExpressionArena, root: ExpressionId) -> Vec<ValidationError> {
+        Validation::new(expressions).validate(root)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum State {
+    Unvisited,
+    InProgress,
+    Visited,
+}
+
+/// Steps of the validation.
+enum Step {
+    Enter {
+        parent: ExpressionId,
+        child: ExpressionId,
+    },
+    Exit {
+        expression: ExpressionId,
+    },
+    Bind {
+        count: usize,
+    },
+    Unbind {
+        count: usize,
+    },
+}
+
+struct Validation<'a> {
+    expressions: &'a ExpressionArena,
+    states: Vec<State>,
+    binders: usize,
+    errors: Vec<ValidationError>,
+    steps: Vec<Step>,
+}
+
+impl<'a> Validation<'a> {
+    pub fn new(expressions: &'a ExpressionArena) -> Validation<'a> {
+        Validation {
+            expressions,
+            states: vec![State::Unvisited; expressions.len()],
+            binders: 0,
+            errors: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    fn enter_child(&mut self, parent: ExpressionId, child: ExpressionId) {
+        if !self.expressions.has(child) {
+            self.errors.push(ValidationError::DanglingExpression {
+                parent: Option::Some(parent),
+                child,
+            });
+            return;
+        }
+        match self.states[child.into_usize()] {
+            State::Unvisited => self.enter(child),
+            State::InProgress => self.errors.push(ValidationError::Cycle { parent, child }),
+            State::Visited => self
+                .errors
+                .push(ValidationError::SharedExpression { parent, child }),
+        }
+    }
+
+    fn enter(&mut self, expression: ExpressionId) {
+        self.states[expression.into_usize()] = State::InProgress;
+        let node = &self.expressions[expression];
+        match node {
+            Expression::NamelessVariable { index } => {
+                let index = index.into_usize();
+                if index == 0 || index > self.binders {
+                    self.errors.push(ValidationError::IndexOutOfRange {
+                        expression,
+                        index,
+                        binders: self.binders,
+                    });
+                }
+            }
+            Expression::Application {
+                function: _,
+                arguments,
+            } if arguments.is_empty() => {
+                self.errors
+                    .push(ValidationError::EmptyApplication { expression });
+            }
+            Expression::LetRec {
+                parameters,
+                definitions,
+                body: _,
+            } if parameters.len() != definitions.len() => {
+                self.errors.push(ValidationError::MismatchedLetRec {
+                    expression,
+                    parameters: parameters.len(),
+                    definitions: definitions.len(),
+                });
+            }
+            _ => {}
+        }
+        self.steps.push(Step::Exit { expression });
+        for (index, &child) in node.children().iter().enumerate().rev() {
+            let count = node.child_binders(index).len();
+            self.steps.push(Step::Unbind { count });
+            self.steps.push(Step::Enter {
+                parent: expression,
+                child,
+            });
+            self.steps.push(Step::Bind { count });
+        }
+    }
+
+    pub fn validate(mut self, root: ExpressionId) -> Vec<ValidationError> {
+        if self.expressions.has(root) {
+            self.enter(root);
+        } else {
+            self.errors.push(ValidationError::DanglingExpression {
+                parent: Option::None,
+                child: root,
+            });
+        }
+        while let Option::Some(step) = self.steps.pop() {
+            match step {
+                Step::Enter { parent, child } => self.enter_child(parent, child),
+                Step::Exit { expression } => {
+                    self.states[expression.into_usize()] = State::Visited;
+                }
+                Step::Bind { count } => self.binders += count,
+                Step::Unbind { count } => self.binders -= count,
+            }
+        }
+        self.errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expression::DeBruijnIndex, strings::StringArena};
+
+    use super::*;
+
+    #[test]
+    fn validate_accepts_parsed_expressions() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        for input in [
+            "x",
+            "λx y. f (λ. 1 y) x",
+            "let = λ. 1 in letrec f = 1 and g = 2 in Π(: A). 1 → 2",
+            "case 1 of { c a b => 3 | d/1 => a }",
+        ] {
+            let expression = Expression::parse_mixed_expression(
+                &mut strings,
+                &mut expressions,
+                input.as_bytes(),
+            )
+            .unwrap();
+            let scope_closed = expressions.nameless_abstraction(expression);
+            assert_eq!(Expression::validate(&expressions, scope_closed), vec![]);
+        }
+    }
+
+    #[test]
+    fn validate_reports_dangling_expressions() {
+        let mut expressions = ExpressionArena::new();
+        let missing = ExpressionId::new(10);
+        let abstraction = expressions.nameless_abstraction(missing);
+
+        assert_eq!(
+            Expression::validate(&expressions, abstraction),
+            vec![ValidationError::DanglingExpression {
+                parent: Option::Some(abstraction),
+                child: missing,
+            }]
+        );
+        assert_eq!(
+            Expression::validate(&expressions, missing),
+            vec![ValidationError::DanglingExpression {
+                parent: Option::None,
+                child: missing,
+            }]
+        );
+    }
+
+    #[test]
+    fn validate_reports_cycles_and_shared_expressions() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let x = expressions.variable(strings.intern(b"x"));
+        let shared = expressions.application(x, vec![x]);
+        // The abstraction refers to the application added right after it
+        let cycle = expressions.nameless_abstraction(ExpressionId::new(3));
+        let application = expressions.add(Expression::Application {
+            function: cycle,
+            arguments: vec![],
+        });
+
+        assert_eq!(
+            Expression::validate(&expressions, shared),
+            vec![ValidationError::SharedExpression {
+                parent: shared,
+                child: x,
+            }]
+        );
+        assert_eq!(
+            Expression::validate(&expressions, cycle),
+            vec![
+                ValidationError::EmptyApplication {
+                    expression: application
+                },
+                ValidationError::Cycle {
+                    parent: application,
+                    child: cycle,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_reports_malformed_nodes() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let one = expressions.nameless_variable(DeBruijnIndex::new(1));
+        let two = expressions.nameless_variable(DeBruijnIndex::new(2));
+        let zero = expressions.nameless_variable(DeBruijnIndex::new_unchecked(0));
+        let let_rec = expressions.add(Expression::LetRec {
+            parameters: vec![Option::Some(strings.intern(b"f"))],
+            definitions: vec![one, zero],
+            body: two,
+        });
+
+        assert_eq!(
+            Expression::validate(&expressions, let_rec),
+            vec![
+                ValidationError::MismatchedLetRec {
+                    expression: let_rec,
+                    parameters: 1,
+                    definitions: 2,
+                },
+                ValidationError::IndexOutOfRange {
+                    expression: zero,
+                    index: 0,
+                    binders: 1,
+                },
+                ValidationError::IndexOutOfRange {
+                    expression: two,
+                    index: 2,
+                    binders: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_handles_deep_expressions() {
+        let mut expressions = ExpressionArena::new();
+
+        let depth = 1_000_000;
+        let mut expression = expressions.nameless_variable(DeBruijnIndex::new(depth));
+        for _ in 0..depth {
+            expression = expressions.nameless_abstraction(expression);
+        }
+        assert_eq!(Expression::validate(&expressions, expression), vec![]);
+    }
+}
@@ -0,0 +1,749 @@
+use std::borrow::Cow;
+
+use crate::{
+    expression::{CaseBranch, Constant, DeBruijnIndex, Expression, ExpressionArena, ExpressionId},
+    referencing_environment::ReferencingEnvironment,
+    strings::StringId,
+};
+
+/// Hooks called by traversals when entering and exiting the scope of binders.
+/// By default, these hooks maintain the [environment](Scoping::environment)
+/// of the traversal, if it has one.
+pub trait Scoping {
+    /// The environment of the identifiers in scope, kept up to date during
+    /// traversals.
+    fn environment(&mut self) -> Option<&mut ReferencingEnvironment> {
+        Option::None
+    }
+
+    /// Enters the scope of `parameters`, bound in order. Omitted parameters
+    /// are nameless binders.
+    fn enter_binders(&mut self, parameters: &[Option<StringId>]) {
+        if let Option::Some(environment) = self.environment() {
+            environment.bind_parameters(parameters);
+        }
+    }
+
+    /// Exits the scope of `parameters`, as previously entered.
+    fn exit_binders(&mut self, parameters: &[Option<StringId>]) {
+        if let Option::Some(environment) = self.environment() {
+            environment.unbind_parameters(parameters);
+        }
+    }
+}
+
+/// Traversals of expressions for their side effects. Expressions are visited
+/// in pre-order, and every hook defaults to doing nothing, so implementations
+/// only override the cases they are interested in.
+pub trait Visitor<'a>: Scoping + Sized {
+    fn expressions(&self) -> &'a ExpressionArena;
+
+    /// Visits `expression` and its sub-expressions.
+    fn visit_expression(&mut self, expression: ExpressionId) {
+        walk_expression(self, expression);
+    }
+
+    /// Called before visiting `expression`, the sub-expression of `parent`
+    /// if any. Returns whether to visit `expression`.
+    fn enter_expression(
+        &mut self,
+        _parent: Option<ExpressionId>,
+        _expression: ExpressionId,
+    ) -> bool {
+        true
+    }
+
+    /// Called after visiting `expression` and its sub-expressions.
+    fn exit_expression(&mut self, _expression: ExpressionId) {}
+
+    fn visit_variable(&mut self, _expression: ExpressionId, _identifier: StringId) {}
+
+    fn visit_nameless_variable(&mut self, _expression: ExpressionId, _index: DeBruijnIndex) {}
+
+    fn visit_constant(&mut self, _expression: ExpressionId, _constant: Constant) {}
+}
+
+/// Bottom-up traversals of expressions computing a result per expression from
+/// the results for its sub-expressions.
+pub trait Fold<'a>: Scoping + Sized {
+    type Output;
+
+    fn expressions(&self) -> &'a ExpressionArena;
+
+    /// Computes the result for `expression`.
+    fn fold_expression(&mut self, expression: ExpressionId) -> Self::Output {
+        walk_fold(self, expression)
+    }
+
+    /// Computes the result for `expression` from the results for its
+    /// sub-expressions, in the order given by [Expression::children]. This is
+    /// called within the scope of the binders over `expression`.
+    fn combine(&mut self, expression: ExpressionId, children: Vec<Self::Output>) -> Self::Output;
+}
+
+/// Traversals of expressions constructing new expressions in the same arena.
+/// By default, expressions are rebuilt from their rewritten sub-expressions,
+/// and expressions whose sub-expressions are all unchanged are kept as is.
+pub trait Rewrite: Scoping + Sized {
+    /// Rewrites `expression` and its sub-expressions.
+    fn rewrite_expression(
+        &mut self,
+        expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+    ) -> ExpressionId {
+        walk_rewrite(self, expressions, expression)
+    }
+
+    /// Called before rewriting the sub-expressions of `expression`. Returns
+    /// the rewritten expression to replace `expression` with as a whole, or
+    /// `None` to rewrite its sub-expressions.
+    fn enter_expression(
+        &mut self,
+        _expressions: &mut ExpressionArena,
+        _expression: ExpressionId,
+    ) -> Option<ExpressionId> {
+        Option::None
+    }
+
+    /// Called with `expression` once rebuilt from its rewritten
+    /// sub-expressions. Returns the rewritten expression.
+    fn exit_expression(
+        &mut self,
+        _expressions: &mut ExpressionArena,
+        expression: ExpressionId,
+    ) -> ExpressionId {
+        expression
+    }
+}
+
+/// Binders of nameless abstractions, let-bindings and dependent function
+/// types.
+const NAMELESS_BINDER: &[Option<StringId>] = &[Option::None];
+
+impl Expression {
+    /// The immediate sub-expressions of this expression, in traversal order.
+    pub fn children(&self) -> Vec<ExpressionId> {
+        match self {
+            Expression::Variable { identifier: _ } => vec![],
+            Expression::Constant { constant: _ } => vec![],
+            Expression::NamelessVariable { index: _ } => vec![],
+            Expression::Abstraction {
+                parameter: _,
+                annotation,
+                body,
+            }
+            | Expression::NamelessAbstraction { annotation, body } => {
+                annotation.iter().copied().chain([*body]).collect()
+            }
+            Expression::Application {
+                function,
+                arguments,
+            } => [*function]
+                .into_iter()
+                .chain(arguments.iter().copied())
+                .collect(),
+            Expression::Let {
+                parameter: _,
+                definition,
+                body,
+            }
+            | Expression::NamelessLet { definition, body } => vec![*definition, *body],
+            Expression::LetRec {
+                parameters: _,
+                definitions,
+                body,
+            }
+            | Expression::NamelessLetRec { definitions, body } => {
+                definitions.iter().copied().chain([*body]).collect()
+            }
+            Expression::Pi {
+                parameter: _,
+                domain,
+                codomain,
+            }
+            | Expression::NamelessPi { domain, codomain } => vec![*domain, *codomain],
+            Expression::Case {
+                scrutinee,
+                branches,
+            } => [*scrutinee]
+                .into_iter()
+                .chain(branches.iter().map(CaseBranch::body))
+                .collect(),
+        }
+    }
+
+    /// The parameters bound over the `child`-th sub-expression of this
+    /// expression, in binding order.
+    pub fn child_binders(&self, child: usize) -> Cow<'_, [Option<StringId>]> {
+        match self {
+            Expression::Abstraction {
+                parameter,
+                annotation,
+                body: _,
+            } if child == annotation.iter().len() => Cow::Borrowed(std::slice::from_ref(parameter)),
+            Expression::NamelessAbstraction {
+                annotation,
+                body: _,
+            } if child == annotation.iter().len() => Cow::Borrowed(NAMELESS_BINDER),
+            Expression::Let {
+                parameter,
+                definition: _,
+                body: _,
+            }
+            | Expression::Pi {
+                parameter,
+                domain: _,
+                codomain: _,
+            } if child == 1 => Cow::Borrowed(std::slice::from_ref(parameter)),
+            Expression::NamelessLet {
+                definition: _,
+                body: _,
+            }
+            | Expression::NamelessPi {
+                domain: _,
+                codomain: _,
+            } if child == 1 => Cow::Borrowed(NAMELESS_BINDER),
+            Expression::LetRec {
+                parameters,
+                definitions: _,
+                body: _,
+            } => Cow::Borrowed(parameters),
+            Expression::NamelessLetRec {
+                definitions,
+                body: _,
+            } => Cow::Owned(vec![Option::None; definitions.len()]),
+            Expression::Case {
+                scrutinee: _,
+                branches,
+            } if child > 0 => match &branches[child - 1] {
+                CaseBranch::Named { parameters, .. } => Cow::Borrowed(parameters),
+                CaseBranch::Nameless { arity, .. } => Cow::Owned(vec![Option::None; *arity]),
+            },
+            _ => Cow::Borrowed(&[]),
+        }
+    }
+
+    /// Copies this expression with its sub-expressions replaced by
+    /// `children`, given in the order of [Expression::children].
+    pub fn with_children(&self, children: &[ExpressionId]) -> Expression {
+        match self {
+            Expression::Variable { identifier } => Expression::Variable {
+                identifier: *identifier,
+            },
+            Expression::NamelessVariable { index } => {
+                Expression::NamelessVariable { index: *index }
+            }
+            Expression::Constant { constant } => Expression::Constant {
+                constant: *constant,
+            },
+            Expression::Abstraction {
+                parameter,
+                annotation,
+                body: _,
+            } => Expression::Abstraction {
+                parameter: *parameter,
+                annotation: annotation.map(|_| children[0]),
+                body: children[children.len() - 1],
+            },
+            Expression::NamelessAbstraction {
+                annotation,
+                body: _,
+            } => Expression::NamelessAbstraction {
+                annotation: annotation.map(|_| children[0]),
+                body: children[children.len() - 1],
+            },
+            Expression::Application {
+                function: _,
+                arguments: _,
+            } => Expression::Application {
+                function: children[0],
+                arguments: children[1..].to_vec(),
+            },
+            Expression::Let {
+                parameter,
+                definition: _,
+                body: _,
+            } => Expression::Let {
+                parameter: *parameter,
+                definition: children[0],
+                body: children[1],
+            },
+            Expression::NamelessLet {
+                definition: _,
+                body: _,
+            } => Expression::NamelessLet {
+                definition: children[0],
+                body: children[1],
+            },
+            Expression::LetRec {
+                parameters,
+                definitions: _,
+                body: _,
+            } => Expression::LetRec {
+                parameters: parameters.clone(),
+                definitions: children[..children.len() - 1].to_vec(),
+                body: children[children.len() - 1],
+            },
+            Expression::NamelessLetRec {
+                definitions: _,
+                body: _,
+            } => Expression::NamelessLetRec {
+                definitions: children[..children.len() - 1].to_vec(),
+                body: children[children.len() - 1],
+            },
+            Expression::Pi {
+                parameter,
+                domain: _,
+                codomain: _,
+            } => Expression::Pi {
+                parameter: *parameter,
+                domain: children[0],
+                codomain: children[1],
+            },
+            Expression::NamelessPi {
+                domain: _,
+                codomain: _,
+            } => Expression::NamelessPi {
+                domain: children[0],
+                codomain: children[1],
+            },
+            Expression::Case {
+                scrutinee: _,
+                branches,
+            } => Expression::Case {
+                scrutinee: children[0],
+                branches: branches
+                    .iter()
+                    .zip(&children[1..])
+                    .map(|(branch, &body)| match branch {
+                        CaseBranch::Named {
+                            constructor,
+                            parameters,
+                            body: _,
+                        } => CaseBranch::Named {
+                            constructor: *constructor,
+                            parameters: parameters.clone(),
+                            body,
+                        },
+                        CaseBranch::Nameless {
+                            constructor,
+                            arity,
+                            body: _,
+                        } => CaseBranch::Nameless {
+                            constructor: *constructor,
+                            arity: *arity,
+                            body,
+                        },
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Steps of the traversals below. Like them, every traversal of expressions
+/// in the crate keeps its steps on an explicit stack rather than on the call
+/// stack, so that the depth of expressions is bounded only by heap memory.
+enum Step {
+    Enter {
+        parent: Option<ExpressionId>,
+        expression: ExpressionId,
+    },
+    Exit {
+        expression: ExpressionId,
+    },
+    Bind {
+        expression: ExpressionId,
+        child: usize,
+    },
+    Unbind {
+        expression: ExpressionId,
+        child: usize,
+    },
+}
+
+/// Schedules entering the sub-expressions of `expression` in order, each
+/// within the scope of the binders over it.
+fn push_children(steps: &mut Vec<Step>, expressions: &ExpressionArena, expression: ExpressionId) {
+    let node = &expressions[expression];
+    for (child, &id) in node.children().iter().enumerate().rev() {
+        let binds = !node.child_binders(child).is_empty();
+        if binds {
+            steps.push(Step::Unbind { expression, child });
+        }
+        steps.push(Step::Enter {
+            parent: Option::Some(expression),
+            expression: id,
+        });
+        if binds {
+            steps.push(Step::Bind { expression, child });
+        }
+    }
+}
+
+/// Enters or exits the scope of the binders over the `child`-th
+/// sub-expression of `expression`.
+fn scope<S: Scoping>(
+    scoping: &mut S,
+    expressions: &ExpressionArena,
+    expression: ExpressionId,
+    child: usize,
+    enter: bool,
+) {
+    let binders = expressions[expression].child_binders(child);
+    if enter {
+        scoping.enter_binders(&binders);
+    } else {
+        scoping.exit_binders(&binders);
+    }
+}
+
+/// The default traversal of [Visitor::visit_expression].
+pub fn walk_expression<'a, V: Visitor<'a>>(visitor: &mut V, expression: ExpressionId) {
+    let expressions = visitor.expressions();
+    let mut steps = vec![Step::Enter {
+        parent: Option::None,
+        expression,
+    }];
+    while let Option::Some(step) = steps.pop() {
+        match step {
+            Step::Enter { parent, expression } => {
+                if !visitor.enter_expression(parent, expression) {
+                    continue;
+                }
+                match &expressions[expression] {
+                    Expression::Variable { identifier } => {
+                        visitor.visit_variable(expression, *identifier)
+                    }
+                    Expression::NamelessVariable { index } => {
+                        visitor.visit_nameless_variable(expression, *index)
+                    }
+                    Expression::Constant { constant } => {
+                        visitor.visit_constant(expression, *constant)
+                    }
+                    _ => {}
+                }
+                steps.push(Step::Exit { expression });
+                push_children(&mut steps, expressions, expression);
+            }
+            Step::Exit { expression } => visitor.exit_expression(expression),
+            Step::Bind { expression, child } => {
+                scope(visitor, expressions, expression, child, true)
+            }
+            Step::Unbind { expression, child } => {
+                scope(visitor, expressions, expression, child, false)
+            }
+        }
+    }
+}
+
+/// The default traversal of [Fold::fold_expression].
+pub fn walk_fold<'a, F: Fold<'a>>(fold: &mut F, expression: ExpressionId) -> F::Output {
+    let expressions = fold.expressions();
+    let mut steps = vec![Step::Enter {
+        parent: Option::None,
+        expression,
+    }];
+    let mut results = Vec::new();
+    while let Option::Some(step) = steps.pop() {
+        match step {
+            Step::Enter {
+                parent: _,
+                expression,
+            } => {
+                steps.push(Step::Exit { expression });
+                push_children(&mut steps, expressions, expression);
+            }
+            Step::Exit { expression } => {
+                let count = expressions[expression].children().len();
+                let children = results.split_off(results.len() - count);
+                let result = fold.combine(expression, children);
+                results.push(result);
+            }
+            Step::Bind { expression, child } => scope(fold, expressions, expression, child, true),
+            Step::Unbind { expression, child } => {
+                scope(fold, expressions, expression, child, false)
+            }
+        }
+    }
+    results.pop().unwrap()
+}
+
+/// The default traversal of [Rewrite::rewrite_expression].
+pub fn walk_rewrite<R: Rewrite>(
+    rewrite: &mut R,
+    expressions: &mut ExpressionArena,
+    expression: ExpressionId,
+) -> ExpressionId {
+    let mut steps = vec![Step::Enter {
+        parent: Option::None,
+        expression,
+    }];
+    let mut results = Vec::new();
+    while let Option::Some(step) = steps.pop() {
+        match step {
+            Step::Enter {
+                parent: _,
+                expression,
+            } => match rewrite.enter_expression(expressions, expression) {
+                Option::Some(rewritten) => results.push(rewritten),
+                Option::None => {
+                    steps.push(Step::Exit { expression });
+                    push_children(&mut steps, expressions, expression);
+                }
+            },
+            Step::Exit { expression } => {
+                let node = &expressions[expression];
+                let children = node.children();
+                let rewritten_children = results.split_off(results.len() - children.len());
+                let rebuilt = if rewritten_children == children {
+                    expression
+                } else {
+                    let rebuilt = node.with_children(&rewritten_children);
+                    expressions.add(rebuilt)
+                };
+                let rewritten = rewrite.exit_expression(expressions, rebuilt);
+                results.push(rewritten);
+            }
+            Step::Bind { expression, child } => {
+                scope(rewrite, expressions, expression, child, true)
+            }
+            Step::Unbind { expression, child } => {
+                scope(rewrite, expressions, expression, child, false)
+            }
+        }
+    }
+    results.pop().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::strings::StringArena;
+
+    use super::*;
+
+    struct Indices<'a> {
+        expressions: &'a ExpressionArena,
+        environment: ReferencingEnvironment,
+        indices: Vec<Option<usize>>,
+    }
+
+    impl Scoping for Indices<'_> {
+        fn environment(&mut self) -> Option<&mut ReferencingEnvironment> {
+            Option::Some(&mut self.environment)
+        }
+    }
+
+    impl<'a> Visitor<'a> for Indices<'a> {
+        fn expressions(&self) -> &'a ExpressionArena {
+            self.expressions
+        }
+
+        fn visit_variable(&mut self, _expression: ExpressionId, identifier: StringId) {
+            let index = self.environment.lookup_index(identifier);
+            self.indices.push(index.map(DeBruijnIndex::into_usize));
+        }
+    }
+
+    fn check_indices(input: &str, expected: Vec<Option<usize>>) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+
+        let mut visitor = Indices {
+            expressions: &expressions,
+            environment: ReferencingEnvironment::new(),
+            indices: Vec::new(),
+        };
+        visitor.visit_expression(expression);
+        assert_eq!(visitor.indices, expected);
+        assert!(visitor.environment.is_empty());
+    }
+
+    #[test]
+    fn visitor_maintains_environment() {
+        check_indices("x", vec![Option::None]);
+        check_indices(
+            "λx. λ. x (λy. y x)",
+            vec![Option::Some(2), Option::Some(1), Option::Some(3)],
+        );
+        check_indices("λx : x. x", vec![Option::None, Option::Some(1)]);
+        check_indices(
+            "letrec f = g and g = f in let h = f in h",
+            vec![
+                Option::Some(1),
+                Option::Some(2),
+                Option::Some(2),
+                Option::Some(1),
+            ],
+        );
+        check_indices(
+            "case p of { c a b => a | d/1 => b }",
+            vec![Option::None, Option::Some(2), Option::None],
+        );
+    }
+
+    struct Substitute {
+        environment: ReferencingEnvironment,
+        variable: StringId,
+        replacement: StringId,
+    }
+
+    impl Scoping for Substitute {
+        fn environment(&mut self) -> Option<&mut ReferencingEnvironment> {
+            Option::Some(&mut self.environment)
+        }
+    }
+
+    impl Rewrite for Substitute {
+        fn enter_expression(
+            &mut self,
+            expressions: &mut ExpressionArena,
+            expression: ExpressionId,
+        ) -> Option<ExpressionId> {
+            match expressions[expression] {
+                Expression::Variable { identifier }
+                    if identifier == self.variable
+                        && self.environment.lookup(identifier).is_none() =>
+                {
+                    Option::Some(expressions.variable(self.replacement))
+                }
+                _ => Option::None,
+            }
+        }
+    }
+
+    fn check_rewrite(input: &str, expected: &str) {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, input.as_bytes())
+                .unwrap();
+        let expected =
+            Expression::parse_mixed_expression(&mut strings, &mut expressions, expected.as_bytes())
+                .unwrap();
+
+        let mut rewrite = Substitute {
+            environment: ReferencingEnvironment::new_frame(Rc::new(ReferencingEnvironment::new())),
+            variable: strings.intern(b"x"),
+            replacement: strings.intern(b"y"),
+        };
+        let rewritten = rewrite.rewrite_expression(&mut expressions, expression);
+        assert!(Expression::equals(
+            (&expressions, rewritten),
+            (&expressions, expected)
+        ));
+    }
+
+    #[test]
+    fn rewrite_rebuilds_changed_expressions() {
+        check_rewrite("x", "y");
+        check_rewrite("λz. x (λx. x) z", "λz. y (λx. x) z");
+        check_rewrite("λ : x. 1 x", "λ : y. 1 y");
+        check_rewrite("letrec f = x and x = f in x", "letrec f = x and x = f in x");
+        check_rewrite(
+            "case x of { c x => x | d/1 => x }",
+            "case y of { c x => x | d/1 => y }",
+        );
+    }
+
+    #[test]
+    fn rewrite_keeps_unchanged_expressions() {
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+
+        let expression = Expression::parse_mixed_expression(
+            &mut strings,
+            &mut expressions,
+            "λz. z (λx. x) w".as_bytes(),
+        )
+        .unwrap();
+        let size = expressions.len();
+
+        let mut rewrite = Substitute {
+            environment: ReferencingEnvironment::new(),
+            variable: strings.intern(b"x"),
+            replacement: strings.intern(b"y"),
+        };
+        assert_eq!(
+            rewrite.rewrite_expression(&mut expressions, expression),
+            expression
+        );
+        assert_eq!(expressions.len(), size);
+    }
+
+    /// `λ. λ. 2 (2 (… (2 1)))` with `count` applications.
+    fn church_numeral(expressions: &mut ExpressionArena, count: usize) -> ExpressionId {
+        let mut body = expressions.nameless_variable(DeBruijnIndex::new(1));
+        for _ in 0..count {
+            let function = expressions.nameless_variable(DeBruijnIndex::new(2));
+            body = expressions.application(function, vec![body]);
+        }
+        let abstraction = expressions.nameless_abstraction(body);
+        expressions.nameless_abstraction(abstraction)
+    }
+
+    /// `let x = 0 in let x = x in … x` with `count` let-bindings.
+    fn let_chain(
+        strings: &mut StringArena,
+        expressions: &mut ExpressionArena,
+        count: usize,
+    ) -> ExpressionId {
+        let x = strings.intern(b"x");
+        let mut expression = expressions.variable(x);
+        for i in 0..count {
+            let definition = if i + 1 == count {
+                expressions.constant(Constant::Integer(0))
+            } else {
+                expressions.variable(x)
+            };
+            expression = expressions.let_binding(Option::Some(x), definition, expression);
+        }
+        expression
+    }
+
+    #[test]
+    fn traversals_handle_deep_expressions() {
+        let count = 1_000_000;
+        let mut strings = StringArena::new();
+        let mut expressions = ExpressionArena::new();
+        let environment = Rc::new(ReferencingEnvironment::new());
+
+        let numeral = church_numeral(&mut expressions, count);
+        assert_eq!(Expression::size(&expressions, numeral), 2 * count + 3);
+        assert_eq!(Expression::height(&expressions, numeral), 2);
+        assert!(Expression::is_locally_nameless(&expressions, numeral));
+        assert!(!Expression::is_named(&expressions, numeral));
+        assert_eq!(
+            Expression::check_scope(&environment, &expressions, numeral),
+            vec![]
+        );
+        assert!(Expression::equals(
+            (&expressions, numeral),
+            (&expressions, numeral)
+        ));
+
+        let chain = let_chain(&mut strings, &mut expressions, count);
+        assert_eq!(Expression::height(&expressions, chain), count);
+        assert!(Expression::is_named(&expressions, chain));
+        assert!(Expression::free_variables(environment.clone(), &expressions, chain).is_empty());
+        let parents = Expression::parent_expressions(&expressions, chain);
+        assert_eq!(
+            parents.iter().filter(|parent| parent.is_some()).count(),
+            2 * count
+        );
+
+        let mut rewrite = Substitute {
+            environment: ReferencingEnvironment::new(),
+            variable: strings.intern(b"x"),
+            replacement: strings.intern(b"y"),
+        };
+        assert_eq!(rewrite.rewrite_expression(&mut expressions, chain), chain);
+    }
+}